@@ -1,8 +1,9 @@
 use std::path::Path;
-use std::time::SystemTime;
+use std::time::{Duration, SystemTime};
+use walkdir::WalkDir;
 
 #[cfg(windows)]
-use std::time::{Duration, UNIX_EPOCH};
+use std::time::UNIX_EPOCH;
 
 use crate::models::LastUsedSource;
 
@@ -126,10 +127,80 @@ pub fn select_last_used_time(
     }
 }
 
-pub fn is_dormant(timestamp: SystemTime, days_threshold: i64) -> bool {
+/// Best-effort guess of which toolchain/ecosystem installed a binary, from its location.
+pub fn ecosystem_for(path: &Path) -> &'static str {
+    let p = path.to_string_lossy().replace('\\', "/").to_lowercase();
+    let known: [(&str, &str); 9] = [
+        ("/.cargo/", "cargo"),
+        ("/.rustup/", "rustup"),
+        ("/go/bin/", "go"),
+        ("/node_modules/", "npm"),
+        ("/.nvm/", "node"),
+        ("/.pyenv/", "python"),
+        ("/pipx/", "python"),
+        ("/.local/bin/", "local"),
+        ("/.bin-expire/", "bin-expire"),
+    ];
+    known
+        .iter()
+        .find(|(needle, _)| p.contains(needle))
+        .map(|(_, eco)| *eco)
+        .unwrap_or("other")
+}
+
+/// Size and last use of an installed toolchain or runtime version.
+#[derive(Debug, Clone, Copy)]
+pub struct DirUsage {
+    /// Total size of every file below the directory.
+    pub size: u64,
+    /// Newest last-used time of the programs in its `bin` directory (of the directory itself
+    /// if there is none).
+    pub last_used: SystemTime,
+    pub last_used_source: LastUsedSource,
+}
+
+/// Total size of the files below `dir` (symlinks are not followed).
+pub fn dir_size(dir: &Path) -> u64 {
+    WalkDir::new(dir)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter_map(|e| e.metadata().ok())
+        .filter(|m| m.is_file())
+        .map(|m| m.len())
+        .sum()
+}
+
+/// Measure an install directory. Only metadata is read, so atimes are left alone. Version
+/// managers' shims and proxies run the programs in `<dir>/bin`, which is what marks it as used.
+pub fn dir_usage(dir: &Path, windows_use_access_time: bool) -> DirUsage {
+    let size = dir_size(dir);
+
+    let newest = std::fs::read_dir(dir.join("bin"))
+        .into_iter()
+        .flatten()
+        .filter_map(|e| e.ok())
+        .filter_map(|e| get_file_info(&e.path()))
+        .map(|info| select_last_used_time(info.times, windows_use_access_time))
+        .filter(|(_, source)| *source != LastUsedSource::Unknown)
+        .max_by_key(|(time, _)| *time);
+    let (last_used, last_used_source) = newest.unwrap_or_else(|| {
+        get_file_info(dir)
+            .map(|info| select_last_used_time(info.times, windows_use_access_time))
+            .unwrap_or((SystemTime::now(), LastUsedSource::Unknown))
+    });
+
+    DirUsage {
+        size,
+        last_used,
+        last_used_source,
+    }
+}
+
+/// True when `timestamp` is further in the past than `threshold` (full durations, not whole days).
+pub fn is_dormant(timestamp: SystemTime, threshold: Duration) -> bool {
     let now = SystemTime::now();
     let duration = now.duration_since(timestamp).unwrap_or_default();
-    (duration.as_secs() / 86400) as i64 > days_threshold
+    duration > threshold
 }
 
 #[cfg(test)]
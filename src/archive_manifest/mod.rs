@@ -4,6 +4,8 @@ use std::fs;
 use std::path::{Path, PathBuf};
 
 use crate::config::manifest_file_path;
+use crate::exit_status::ErrorKind;
+use crate::fs_util::replace_file;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ArchiveEntry {
@@ -23,49 +25,59 @@ fn load_manifest(path: &Path) -> Result<ArchiveManifest> {
         return Ok(ArchiveManifest::default());
     }
     let raw = fs::read_to_string(path)
-        .with_context(|| format!("Failed to read manifest: {}", path.display()))?;
+        .with_context(|| format!("Failed to read manifest: {}", path.display()))
+        .context(ErrorKind::Manifest)?;
     let manifest = serde_json::from_str::<ArchiveManifest>(&raw)
-        .with_context(|| format!("Failed to parse manifest JSON: {}", path.display()))?;
+        .with_context(|| format!("Failed to parse manifest JSON: {}", path.display()))
+        .context(ErrorKind::Manifest)?;
     Ok(manifest)
 }
 
 fn save_manifest_atomic(path: &Path, manifest: &ArchiveManifest) -> Result<()> {
+    write_manifest(path, manifest).context(ErrorKind::Manifest)
+}
+
+fn write_manifest(path: &Path, manifest: &ArchiveManifest) -> Result<()> {
     if let Some(parent) = path.parent() {
         fs::create_dir_all(parent).with_context(|| {
             format!("Failed to create manifest directory: {}", parent.display())
         })?;
     }
 
-    let tmp = path.with_extension("json.tmp");
     let raw = serde_json::to_string_pretty(manifest).context("Failed to serialize manifest")?;
-    fs::write(&tmp, raw)
-        .with_context(|| format!("Failed to write temp manifest: {}", tmp.display()))?;
+    replace_file(path, raw)
+}
 
-    // Windows doesn't allow rename over an existing file.
-    if path.exists() {
-        let _ = fs::remove_file(path);
+impl ArchiveEntry {
+    pub fn new(name: &str, original_path: &Path, archived_path: &Path) -> Self {
+        Self {
+            name: name.to_string(),
+            original_path: original_path.to_path_buf(),
+            archived_path: archived_path.to_path_buf(),
+            moved_at: humantime::format_rfc3339_seconds(std::time::SystemTime::now()).to_string(),
+        }
     }
-
-    fs::rename(&tmp, path)
-        .with_context(|| format!("Failed to replace manifest {}", path.display()))?;
-
-    Ok(())
 }
 
 pub fn record_archive(name: &str, original_path: &Path, archived_path: &Path) -> Result<()> {
+    record_archives(vec![ArchiveEntry::new(name, original_path, archived_path)])
+}
+
+/// Append several entries with a single manifest write (either all are recorded or none are).
+pub fn record_archives(entries: Vec<ArchiveEntry>) -> Result<()> {
     let path = manifest_file_path();
     let mut manifest = load_manifest(&path)?;
 
-    manifest.entries.push(ArchiveEntry {
-        name: name.to_string(),
-        original_path: original_path.to_path_buf(),
-        archived_path: archived_path.to_path_buf(),
-        moved_at: humantime::format_rfc3339_seconds(std::time::SystemTime::now()).to_string(),
-    });
+    manifest.entries.extend(entries);
 
     save_manifest_atomic(&path, &manifest)
 }
 
+pub fn all_entries() -> Result<Vec<ArchiveEntry>> {
+    let path = manifest_file_path();
+    Ok(load_manifest(&path)?.entries)
+}
+
 pub fn latest_entry_by_name(name: &str) -> Result<ArchiveEntry> {
     let path = manifest_file_path();
     let manifest = load_manifest(&path)?;
@@ -0,0 +1,118 @@
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::analyzer::get_file_info;
+use crate::archiver::unique_destination;
+use crate::models::BinaryInfo;
+
+/// Bump when the plan file layout changes in an incompatible way.
+pub const PLAN_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlanItem {
+    pub name: String,
+    pub source: PathBuf,
+    pub destination: PathBuf,
+    /// Size in bytes when the plan was made.
+    pub size: u64,
+    /// RFC 3339 mtime (nanosecond precision) when the plan was made.
+    pub modified: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivePlan {
+    pub version: u32,
+    pub created_at: String,
+    pub archive_path: PathBuf,
+    pub items: Vec<PlanItem>,
+}
+
+/// Pick a destination for each binary exactly as a real archive run would,
+/// reserving names so two binaries with the same file name get distinct suffixes.
+pub fn build_plan(binaries: &[BinaryInfo], archive_dir: &Path) -> ArchivePlan {
+    let mut reserved: HashSet<PathBuf> = HashSet::new();
+    let mut items = Vec::with_capacity(binaries.len());
+
+    for bin in binaries {
+        let destination = unique_destination(archive_dir, &bin.name, &reserved);
+        reserved.insert(destination.clone());
+        items.push(PlanItem {
+            name: bin.name.clone(),
+            source: bin.path.clone(),
+            destination,
+            size: bin.size,
+            modified: bin
+                .modified
+                .map(|t| humantime::format_rfc3339_nanos(t).to_string()),
+        });
+    }
+
+    ArchivePlan {
+        version: PLAN_VERSION,
+        created_at: humantime::format_rfc3339_seconds(SystemTime::now()).to_string(),
+        archive_path: archive_dir.to_path_buf(),
+        items,
+    }
+}
+
+pub fn write_plan(path: &Path, plan: &ArchivePlan) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        if !parent.as_os_str().is_empty() {
+            fs::create_dir_all(parent).with_context(|| {
+                format!("Failed to create plan directory: {}", parent.display())
+            })?;
+        }
+    }
+    let raw = serde_json::to_string_pretty(plan).context("Failed to serialize plan")?;
+    fs::write(path, raw).with_context(|| format!("Failed to write plan: {}", path.display()))
+}
+
+pub fn read_plan(path: &Path) -> Result<ArchivePlan> {
+    let raw = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read plan: {}", path.display()))?;
+    let plan = serde_json::from_str::<ArchivePlan>(&raw)
+        .with_context(|| format!("Failed to parse plan JSON: {}", path.display()))?;
+    if plan.version != PLAN_VERSION {
+        bail!(
+            "Unsupported plan version {} in {} (expected {})",
+            plan.version,
+            path.display(),
+            PLAN_VERSION
+        );
+    }
+    Ok(plan)
+}
+
+/// Check that the source file still looks exactly like it did when the plan was made.
+pub fn verify_item(item: &PlanItem) -> Result<()> {
+    let Some(info) = get_file_info(&item.source) else {
+        bail!("Source no longer exists: {}", item.source.display());
+    };
+
+    if info.size != item.size {
+        bail!(
+            "Size changed for {} (plan: {} bytes, now: {} bytes)",
+            item.source.display(),
+            item.size,
+            info.size
+        );
+    }
+
+    if let Some(planned) = &item.modified {
+        let planned = humantime::parse_rfc3339(planned)
+            .with_context(|| format!("Invalid mtime '{}' in plan", planned))?;
+        if info.times.modified != Some(planned) {
+            bail!("Modified time changed for {}", item.source.display());
+        }
+    }
+
+    if item.destination.exists() {
+        bail!("Destination already exists: {}", item.destination.display());
+    }
+
+    Ok(())
+}
@@ -1,12 +1,24 @@
-use crate::models::BinaryInfo;
 use anyhow::Context;
-use anyhow::Result;
+use anyhow::{bail, Result};
+use std::collections::HashSet;
 use std::fs;
 use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
 
+/// Move a file, or a whole directory (e.g. a rustup toolchain), falling back to copy + delete
+/// when a rename isn't possible (different filesystem).
 pub fn move_file_with_fallback(src: &Path, dest: &Path) -> Result<()> {
     match fs::rename(src, dest) {
         Ok(_) => Ok(()),
+        Err(_) if fs::symlink_metadata(src).is_ok_and(|m| m.is_dir()) => {
+            if let Err(err) = copy_dir(src, dest) {
+                // Leave nothing half-copied behind; the original is still complete.
+                let _ = fs::remove_dir_all(dest);
+                return Err(err);
+            }
+            fs::remove_dir_all(src)
+                .with_context(|| format!("Failed to remove original {} after copy", src.display()))
+        }
         Err(rename_err) => {
             fs::copy(src, dest).with_context(|| {
                 format!("Failed to copy {} to {}", src.display(), dest.display())
@@ -20,9 +32,88 @@ pub fn move_file_with_fallback(src: &Path, dest: &Path) -> Result<()> {
     }
 }
 
-fn unique_destination(archive_dir: &Path, file_name: &str) -> PathBuf {
+/// Copy the tree at `src` to `dest` (which must not exist), keeping symlinks as symlinks.
+fn copy_dir(src: &Path, dest: &Path) -> Result<()> {
+    for entry in WalkDir::new(src) {
+        let entry = entry.with_context(|| format!("Failed to read {}", src.display()))?;
+        let rel = entry.path().strip_prefix(src).unwrap_or(entry.path());
+        let target = dest.join(rel);
+        let file_type = entry.file_type();
+        if file_type.is_dir() {
+            fs::create_dir_all(&target)
+                .with_context(|| format!("Failed to create {}", target.display()))?;
+        } else if file_type.is_symlink() {
+            let link = fs::read_link(entry.path())
+                .with_context(|| format!("Failed to read link {}", entry.path().display()))?;
+            copy_symlink(&link, &target)
+                .with_context(|| format!("Failed to create link {}", target.display()))?;
+        } else {
+            fs::copy(entry.path(), &target).with_context(|| {
+                format!(
+                    "Failed to copy {} to {}",
+                    entry.path().display(),
+                    target.display()
+                )
+            })?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn copy_symlink(link: &Path, target: &Path) -> std::io::Result<()> {
+    std::os::unix::fs::symlink(link, target)
+}
+
+#[cfg(windows)]
+fn copy_symlink(link: &Path, target: &Path) -> std::io::Result<()> {
+    std::os::windows::fs::symlink_file(link, target)
+}
+
+/// Delete a file or a whole directory tree. Read-only directories (the Go module cache makes
+/// everything read-only) are made writable first so their entries can be removed.
+pub fn remove_all(path: &Path) -> Result<()> {
+    let meta =
+        fs::symlink_metadata(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    if !meta.is_dir() {
+        return fs::remove_file(path)
+            .with_context(|| format!("Failed to delete {}", path.display()));
+    }
+    if fs::remove_dir_all(path).is_ok() {
+        return Ok(());
+    }
+    for entry in WalkDir::new(path).into_iter().filter_map(|e| e.ok()) {
+        if entry.file_type().is_dir() {
+            if let Ok(meta) = entry.metadata() {
+                let mut perms = meta.permissions();
+                if perms.readonly() {
+                    #[cfg(unix)]
+                    {
+                        use std::os::unix::fs::PermissionsExt;
+                        perms.set_mode(perms.mode() | 0o200);
+                    }
+                    #[cfg(not(unix))]
+                    #[allow(clippy::permissions_set_readonly_false)]
+                    perms.set_readonly(false);
+                    let _ = fs::set_permissions(entry.path(), perms);
+                }
+            }
+        }
+    }
+    fs::remove_dir_all(path).with_context(|| format!("Failed to delete {}", path.display()))
+}
+
+/// Pick a non-colliding path for `file_name` inside `archive_dir`.
+/// `reserved` holds destinations already promised to other items in the same batch.
+pub fn unique_destination(
+    archive_dir: &Path,
+    file_name: &str,
+    reserved: &HashSet<PathBuf>,
+) -> PathBuf {
+    let is_taken = |p: &Path| p.exists() || reserved.contains(p);
+
     let mut candidate = archive_dir.join(file_name);
-    if !candidate.exists() {
+    if !is_taken(&candidate) {
         return candidate;
     }
 
@@ -30,7 +121,7 @@ fn unique_destination(archive_dir: &Path, file_name: &str) -> PathBuf {
     for i in 1..10000u32 {
         let with_suffix = format!("{}.{}", file_name, i);
         candidate = archive_dir.join(with_suffix);
-        if !candidate.exists() {
+        if !is_taken(&candidate) {
             return candidate;
         }
     }
@@ -43,12 +134,17 @@ fn unique_destination(archive_dir: &Path, file_name: &str) -> PathBuf {
     archive_dir.join(format!("{}.{}", file_name, ts))
 }
 
-pub fn archive_binary(bin: &BinaryInfo, archive_dir: &Path) -> Result<PathBuf> {
-    fs::create_dir_all(archive_dir)
-        .with_context(|| format!("Failed to create archive dir: {}", archive_dir.display()))?;
+/// Move `src` to a destination chosen ahead of time (see `unique_destination`).
+/// Never overwrites: fails if something appeared at `dest` since it was chosen.
+pub fn archive_to(src: &Path, dest: &Path) -> Result<()> {
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create archive dir: {}", parent.display()))?;
+    }
 
-    let dest = unique_destination(archive_dir, &bin.name);
+    if dest.exists() {
+        bail!("Destination already exists: {}", dest.display());
+    }
 
-    move_file_with_fallback(&bin.path, &dest)?;
-    Ok(dest)
+    move_file_with_fallback(src, dest)
 }
@@ -0,0 +1,296 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use walkdir::WalkDir;
+
+use crate::analyzer::{get_file_info, select_last_used_time};
+use crate::models::{Config, LastUsedSource};
+use crate::pool;
+
+/// A package cache directory bin-expire knows how to split into entries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    /// `$CARGO_HOME/registry/cache/<index>/<crate>-<version>.crate`
+    CargoCrates,
+    /// `$CARGO_HOME/registry/src/<index>/<crate>-<version>/`
+    CargoSources,
+    /// `$CARGO_HOME/git/checkouts/<repo>/<rev>/`
+    CargoGit,
+    /// `$GOMODCACHE/<module>@<version>/`
+    GoModules,
+}
+
+impl Kind {
+    /// Machine-readable name, used in records.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Kind::CargoCrates => "cargo-crates",
+            Kind::CargoSources => "cargo-sources",
+            Kind::CargoGit => "cargo-git",
+            Kind::GoModules => "go-modules",
+        }
+    }
+
+    /// What the entries are, for the summary table.
+    pub fn label(self) -> &'static str {
+        match self {
+            Kind::CargoCrates => "cargo crate archives",
+            Kind::CargoSources => "cargo extracted sources",
+            Kind::CargoGit => "cargo git checkouts",
+            Kind::GoModules => "go module versions",
+        }
+    }
+}
+
+/// One cache directory that exists on this machine.
+#[derive(Debug, Clone)]
+pub struct Cache {
+    pub kind: Kind,
+    pub dir: PathBuf,
+}
+
+/// One deletable unit of a cache: a crate archive, an extracted crate, a git checkout or a
+/// module version. The package manager downloads it again when a build needs it.
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub kind: Kind,
+    /// Relative to the cache directory, e.g. "index.crates.io-6f17d22bba15001f/serde-1.0.193"
+    /// or "github.com/spf13/cobra@v1.8.0".
+    pub name: String,
+    pub path: PathBuf,
+    pub size: u64,
+    /// Newest last-used time of any file in it.
+    pub last_used: SystemTime,
+    pub last_used_source: LastUsedSource,
+}
+
+fn env_dir(var: &str) -> Option<PathBuf> {
+    std::env::var_os(var)
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+}
+
+/// `$CARGO_HOME`, else `~/.cargo`.
+pub fn cargo_home() -> Option<PathBuf> {
+    env_dir("CARGO_HOME").or_else(|| dirs::home_dir().map(|home| home.join(".cargo")))
+}
+
+/// `$GOMODCACHE`, else `pkg/mod` in the first `$GOPATH` entry, else `~/go/pkg/mod`.
+pub fn go_mod_cache() -> Option<PathBuf> {
+    if let Some(dir) = env_dir("GOMODCACHE") {
+        return Some(dir);
+    }
+    let gopath = env_dir("GOPATH")
+        .and_then(|paths| std::env::split_paths(&paths).next())
+        .or_else(|| dirs::home_dir().map(|home| home.join("go")))?;
+    Some(gopath.join("pkg").join("mod"))
+}
+
+/// Every known cache directory that exists.
+pub fn locate() -> Vec<Cache> {
+    let mut caches = Vec::new();
+    if let Some(home) = cargo_home() {
+        caches.push(Cache {
+            kind: Kind::CargoCrates,
+            dir: home.join("registry").join("cache"),
+        });
+        caches.push(Cache {
+            kind: Kind::CargoSources,
+            dir: home.join("registry").join("src"),
+        });
+        caches.push(Cache {
+            kind: Kind::CargoGit,
+            dir: home.join("git").join("checkouts"),
+        });
+    }
+    if let Some(dir) = go_mod_cache() {
+        caches.push(Cache {
+            kind: Kind::GoModules,
+            dir,
+        });
+    }
+    caches.retain(|c| c.dir.is_dir());
+    caches
+}
+
+/// Sorted `(name, path)` of the children of `dir`; `want_dirs` picks directories or files.
+fn children(dir: &Path, want_dirs: bool) -> Vec<(String, PathBuf)> {
+    let mut out: Vec<(String, PathBuf)> = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|e| e.ok())
+        .filter(|e| {
+            e.file_type()
+                .is_ok_and(|t| t.is_dir() == want_dirs && !t.is_symlink())
+        })
+        .map(|e| (e.file_name().to_string_lossy().to_string(), e.path()))
+        .collect();
+    out.sort();
+    out
+}
+
+/// Two levels below `dir`: `<group>/<entry>`, named "group/entry".
+fn two_levels(dir: &Path, want_dirs: bool) -> Vec<(String, PathBuf)> {
+    children(dir, true)
+        .into_iter()
+        .flat_map(|(group, group_path)| {
+            children(&group_path, want_dirs)
+                .into_iter()
+                .map(move |(name, path)| (format!("{}/{}", group, name), path))
+        })
+        .collect()
+}
+
+/// Module version directories: the first directory on each path whose name has an "@".
+/// Go's own download cache (`cache/`) holds zips and checksums for the same versions and is
+/// left to `go clean -modcache`.
+fn module_versions(dir: &Path) -> Vec<(String, PathBuf)> {
+    let mut out = Vec::new();
+    let mut walker = WalkDir::new(dir)
+        .min_depth(1)
+        .sort_by_file_name()
+        .into_iter();
+    while let Some(entry) = walker.next() {
+        let Ok(entry) = entry else { continue };
+        if !entry.file_type().is_dir() {
+            continue;
+        }
+        if entry.depth() == 1 && entry.file_name() == "cache" {
+            walker.skip_current_dir();
+            continue;
+        }
+        if entry.file_name().to_string_lossy().contains('@') {
+            let name = entry
+                .path()
+                .strip_prefix(dir)
+                .unwrap_or(entry.path())
+                .to_string_lossy()
+                .replace('\\', "/");
+            out.push((name, entry.path().to_path_buf()));
+            walker.skip_current_dir();
+        }
+    }
+    out
+}
+
+/// Size and newest last-used time of everything below `path` (or of `path` itself, for a
+/// file). Only metadata is read.
+fn measure(kind: Kind, name: String, path: PathBuf, windows_use_access_time: bool) -> Entry {
+    let mut size = 0;
+    let mut newest: Option<(SystemTime, LastUsedSource)> = None;
+    for entry in WalkDir::new(&path).into_iter().filter_map(|e| e.ok()) {
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let Some(info) = get_file_info(entry.path()) else {
+            continue;
+        };
+        size += info.size;
+        let used = select_last_used_time(info.times, windows_use_access_time);
+        if used.1 != LastUsedSource::Unknown && newest.is_none_or(|(time, _)| used.0 > time) {
+            newest = Some(used);
+        }
+    }
+    // An empty directory: fall back to its own times.
+    let (last_used, last_used_source) = newest.unwrap_or_else(|| {
+        get_file_info(&path)
+            .map(|info| select_last_used_time(info.times, windows_use_access_time))
+            .unwrap_or((SystemTime::now(), LastUsedSource::Unknown))
+    });
+    Entry {
+        kind,
+        name,
+        path,
+        size,
+        last_used,
+        last_used_source,
+    }
+}
+
+/// Every entry of `caches`, in cache order then by name. Entries are measured on
+/// `scan_threads` workers.
+pub fn entries(caches: &[Cache], config: &Config) -> Vec<Entry> {
+    let found: Vec<(Kind, String, PathBuf)> = caches
+        .iter()
+        .flat_map(|cache| {
+            let list = match cache.kind {
+                Kind::CargoCrates => two_levels(&cache.dir, false)
+                    .into_iter()
+                    .filter(|(name, _)| name.ends_with(".crate"))
+                    .collect(),
+                Kind::CargoSources | Kind::CargoGit => two_levels(&cache.dir, true),
+                Kind::GoModules => module_versions(&cache.dir),
+            };
+            list.into_iter()
+                .map(move |(name, path)| (cache.kind, name, path))
+        })
+        .collect();
+
+    pool::map(&found, config.scan_threads, |(kind, name, path)| {
+        measure(
+            *kind,
+            name.clone(),
+            path.clone(),
+            config.windows_use_access_time,
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_caches_into_entries() {
+        let root = std::env::temp_dir().join(format!("bin-expire-caches-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+
+        let crates = root
+            .join("registry")
+            .join("cache")
+            .join("index.crates.io-1");
+        fs::create_dir_all(&crates).unwrap();
+        fs::write(crates.join("serde-1.0.0.crate"), b"crate").unwrap();
+        fs::write(crates.join(".package-cache"), b"").unwrap();
+
+        let modules = root.join("mod");
+        let cobra = modules
+            .join("github.com")
+            .join("spf13")
+            .join("cobra@v1.8.0");
+        fs::create_dir_all(cobra.join("doc")).unwrap();
+        fs::write(cobra.join("doc").join("x.go"), b"package doc").unwrap();
+        fs::create_dir_all(modules.join("cache").join("download").join("x@v1")).unwrap();
+
+        let caches = [
+            Cache {
+                kind: Kind::CargoCrates,
+                dir: root.join("registry").join("cache"),
+            },
+            Cache {
+                kind: Kind::GoModules,
+                dir: modules,
+            },
+        ];
+        let config = Config {
+            scan_threads: 1,
+            ..Config::default()
+        };
+        let found = entries(&caches, &config);
+        let names: Vec<(&str, &str)> = found
+            .iter()
+            .map(|e| (e.kind.as_str(), e.name.as_str()))
+            .collect();
+        assert_eq!(
+            names,
+            [
+                ("cargo-crates", "index.crates.io-1/serde-1.0.0.crate"),
+                ("go-modules", "github.com/spf13/cobra@v1.8.0"),
+            ]
+        );
+        assert_eq!(found[0].size, 5);
+        assert_eq!(found[1].size, 11);
+
+        let _ = fs::remove_dir_all(&root);
+    }
+}
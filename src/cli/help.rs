@@ -1,11 +1,31 @@
 pub const TOP_LONG_ABOUT: &str = "bin-expire scans your bin directories, identifies stale binaries, and can archive/restore them.";
 
-pub const TOP_AFTER_HELP: &str = "EXAMPLES:\n  bin-expire scan\n  bin-expire scan --days 30\n  bin-expire scan --verbose\n  bin-expire scan --only-stale\n  bin-expire scan --verbose --hide-ok\n  bin-expire archive --days 30\n  bin-expire restore <name>\n\nSCAN OUTPUT:\n  Default scan shows only stale (✗) and stub (·) rows.\n  Use --verbose to include PATH and OK (✓) rows.\n\nSTATUS GLYPHS:\n  ✗  stale: last_used is older than the threshold (non-stub only)\n  ✓  ok: not stale (shown in --verbose)\n  ·  stub: a 0-byte .exe placeholder (App Execution Alias), not a real binary (never archived)\n\nSTUB DETAILS:\n  Windows can create 0-byte *.exe stubs via App Execution Aliases (often Store-related).\n  They can appear in PATH like normal executables but aren't real binaries you should archive.\n  Detection heuristic: size==0 AND extension==.exe\n\nWINDOWS NOTE:\n  On Windows, access times (atime) are best-effort and can be updated by scanning/listing. If results look suspicious, set windows_use_access_time=false in config.toml to use mtime.";
+pub const TOP_AFTER_HELP: &str = "EXAMPLES:\n  bin-expire scan\n  bin-expire scan --days 30\n  bin-expire scan --older-than 6months\n  bin-expire scan --verbose\n  bin-expire scan --only-stale\n  bin-expire scan --verbose --hide-ok\n  bin-expire check --days 90\n  bin-expire archive --older-than 2w\n  bin-expire archive --days 30 --interactive\n  bin-expire toolchains --archive\n  bin-expire runtimes --archive\n  bin-expire caches --delete\n  bin-expire sweep --archive\n  bin-expire list\n  bin-expire restore <name>\n  bin-expire history\n  bin-expire diff\n  bin-expire config show\n  bin-expire --profile cleanup config show\n  bin-expire pin <name> --reason \"on-call runbook\" --until 2027-01-01\n  bin-expire scan --output json | jq '.records[] | select(.status == \"stale\")'\n\nSCAN OUTPUT:\n  Default scan shows only stale (✗) and stub (·) rows.\n  Use --verbose to include PATH and OK (✓) rows.\n\nSTATUS GLYPHS:\n  ✗  stale: last_used is older than the threshold (non-stub only)\n  !  broken: a symlink whose target no longer exists (never archived)\n  P  pinned: protected with `bin-expire pin` until the pin expires (never archived)\n  R  referenced: would be stale, but a systemd unit, crontab, shell rc, git or ssh config\n     (or .tool-versions) mentions it (never archived)\n  ✓  ok: not stale (shown in --verbose)\n  ·  stub: a 0-byte .exe placeholder (App Execution Alias), not a real binary (never archived)\n\nSTUB DETAILS:\n  Windows can create 0-byte *.exe stubs via App Execution Aliases (often Store-related).\n  They can appear in PATH like normal executables but aren't real binaries you should archive.\n  Detection heuristic: size==0 AND extension==.exe\n\nMACHINE-READABLE OUTPUT:\n  --output json|ndjson|csv prints one record per item on stdout (scan: per binary; archive and\n  restore: per item moved; list: per manifest entry). Messages go to stderr.\n  JSON is {\"schema_version\": 1, \"command\": ..., \"records\": [...]}; each NDJSON line carries\n  schema_version and command alongside the record fields.\n\nTHRESHOLDS:\n  --older-than takes a duration: 90d, 2w, 6months, 1y, 36h (a bare number means days).\n  --days N is the same as --older-than Nd. Without either, default_threshold (e.g. \"90d\")\n  or default_threshold_days from config.toml is used.\n\nEXIT CODES:\n  0    success (check / --fail-if-stale: nothing stale or broken)\n  1    error (e.g. none of the directories exist)\n  2    invalid command-line usage\n  3    stale binaries or broken symlinks found (check / --fail-if-stale)\n  4    partial failure: some archive moves failed\n  5    configuration error\n  6    archive manifest error (archive.json unreadable or not written)\n  130  interrupted (Ctrl-C)\n\nWINDOWS NOTE:\n  On Windows, access times (atime) are best-effort and can be updated by scanning/listing. If results look suspicious, set windows_use_access_time=false in config.toml to use mtime.";
 
 pub const SCAN_LONG_ABOUT: &str = "Scan directories for binaries older than the given threshold.\n\nDates:\n  ACCESSED (atime): last read/execute (best-effort on Windows)\n  MODIFIED (mtime): last content change\n\nDefault view:\n  Shows only stale (✗) and stub (·) rows with short dates (YYYY-MM-DD).\n\nVerbose view (--verbose):\n  Adds PATH column and also shows OK (✓) rows.\n  Adds SRC column showing where last_used came from: A=atime, M=mtime, ?=unknown.";
 
-pub const SCAN_AFTER_HELP: &str = "FILTERS:\n  --only-stale   Show only stale rows (hides OK and stubs)\n  --hide-ok      Hide OK rows (mainly useful with --verbose)\n  --hide-stub    Hide stub rows (0-byte .exe App Execution Alias stubs)\n\nEXAMPLES:\n  bin-expire scan --days 30\n  bin-expire scan --only-stale\n  bin-expire scan --verbose --hide-ok\n  bin-expire scan --verbose --hide-stub";
+pub const SCAN_AFTER_HELP: &str = "FILTERS:\n  --only-stale   Show only stale and broken rows (hides OK and stubs)\n  --hide-ok      Hide OK rows (mainly useful with --verbose)\n  --hide-stub    Hide stub rows (0-byte .exe App Execution Alias stubs)\n\nSORTING / GROUPING / COLUMNS:\n  --sort name|size|age|accessed|modified|dir   (size: largest first; times: oldest first)\n  --reverse                                    Flip the sort order\n  --group-by dir|ecosystem|status              Separate tables with count + size subtotals\n  --columns st,src,name,size,accessed,modified,last-used,dir,ecosystem,kind,path\n\nINSPECTING:\n  --inspect   Read each binary for its kind, SHA-256 and Go build info (kind column, JSON fields)\n              and cache the result in scan-cache.json by dev/inode/size/ctime; later scans show\n              cached results and --inspect only reads changed files. Files are opened with\n              O_NOATIME (Linux) or get their atime put back, so reading them doesn't count as a\n              use; binaries owned by other users are not read.\n  --no-cache  Neither use nor update the cache\n  Files are read on scan_threads workers (default: one per CPU); --threads N or --single-threaded\n  override it. Output order is the same either way.\n\nNESTED LAYOUTS:\n  -p and scan_dirs accept patterns such as \"~/.local/share/*/bin\" or \"/opt/*/bin\".\n  --max-depth N        Look N levels deep (default 1); below the top only executables count\n  --one-file-system    Don't cross into other filesystems\n  Symlinked directories are followed; symlink loops are reported and skipped.\n\nIGNORE RULES:\n  ignored_bins entries are names, globs (cargo-*) or regexes (re:^gopls$), optionally scoped with\n  { pattern = \"...\", dir = \"~/go/bin\" }. A .bin-expire-ignore file in a bin dir works like\n  .gitignore (#comments, !pattern re-includes); only the scanned directory's own file is read, and\n  its rules match names at any --max-depth. --verbose lists ignored binaries and their rule.\n\nPROJECTS:\n  --projects  Search project_roots (config) for Makefiles, justfiles, package.json scripts,\n              .github/workflows, .pre-commit-config.yaml and build.rs that use stale binaries\n\nCI:\n  --fail-if-stale  Exit with code 3 when stale binaries or broken symlinks are found\n\nEXAMPLES:\n  bin-expire scan --days 30\n  bin-expire scan --older-than 6months\n  bin-expire scan --only-stale\n  bin-expire scan --verbose --hide-ok\n  bin-expire scan --verbose --hide-stub\n  bin-expire scan --verbose --sort size --group-by ecosystem\n  bin-expire scan --inspect --columns name,kind,size\n  bin-expire scan --columns name,size,path --sort age\n  bin-expire scan --projects\n  bin-expire scan -p \"/opt/*/bin\"\n  bin-expire scan -p ~/.local/share --max-depth 4 --one-file-system";
 
-pub const ARCHIVE_AFTER_HELP: &str = "NOTES:\n  - Stub entries (0-byte .exe App Execution Aliases) are never archived.\n  - Archiving records entries in archive.json so restore can put files back.";
+pub const CHECK_AFTER_HELP: &str = "Equivalent to `scan --only-stale --fail-if-stale`: lists stale binaries and broken symlinks,\nand exits with code 3 if there are any. Useful for dotfiles / dev-image CI.\n\nEXAMPLES:\n  bin-expire check\n  bin-expire check -p ~/.cargo/bin --older-than 6months";
+
+pub const ARCHIVE_AFTER_HELP: &str = "SELECTING BY NAME:\n  bin-expire archive <NAME|GLOB>... archives matching binaries from the scanned directories\n  regardless of age. Add --days N or --older-than DURATION to only archive them if they are also stale.\n  Stubs and ignored_bins entries are still skipped.\n\nSTALE STREAKS:\n  With confirm_stale_scans = K and/or confirm_stale_period = \"7d\" in config.toml, a binary is only\n  archived once K runs of scan or archive saw it stale, the first at least that long ago.\n  Streaks are kept in eligibility.json and start over when a binary is used.\n\nCONFIRMATION:\n  archive lists the stale binaries and asks before moving anything.\n  --yes skips the prompt (for scripts); --interactive lets you deselect individual binaries.\n\nNOTES:\n  - Stub entries (0-byte .exe App Execution Aliases) are never archived.\n  - Archiving records entries in archive.json so restore can put files back.\n  - Ctrl-C finishes the current file, then stops (remaining files are left in place).\n  - --atomic rolls back every file moved in the run if any move fails or Ctrl-C is pressed;\n    archive.json is only updated once the whole batch has moved.\n\nPLANS:\n  --dry-run           Print each move (including the archive file name that will be used)\n  --plan plan.json    Write the same list to a file for review; nothing is moved\n  --apply plan.json   Move exactly what the plan lists. Fails without moving anything if any\n                      file's size or mtime no longer matches the plan.\n\nEXAMPLES:\n  bin-expire archive --days 30\n  bin-expire archive --older-than 2w\n  bin-expire archive ripgrep \"cargo-*\"\n  bin-expire archive --days 30 --yes\n  bin-expire archive --days 30 --interactive\n  bin-expire archive --days 30 --atomic\n  bin-expire archive --days 30 --dry-run\n  bin-expire archive --days 30 --plan plan.json\n  bin-expire archive --apply plan.json";
+
+pub const TOOLCHAINS_AFTER_HELP: &str = "Lists every toolchain in $RUSTUP_HOME/toolchains (default ~/.rustup) with its size and when a\nprogram in its bin/ directory was last used.\n\nKEPT BY:\n  R  the default toolchain, a directory override (settings.toml), or a rust-toolchain(.toml)\n     file in a project under project_roots\n  P  pinned with `bin-expire pin <toolchain>`\n  L  linked with `rustup toolchain link` (never moved)\n  A channel such as \"stable\" or \"1.75.0\" keeps the toolchain of that name for any host triple.\n\nCLEANING UP:\n  Only stale (✗) toolchains are touched, after a confirmation prompt (--yes skips it).\n  --archive  Move them to <archive_path>/toolchains and record them in archive.json;\n             `bin-expire restore <toolchain>` moves one back\n  --remove   Delete them for good (`rustup toolchain install` brings one back)\n  Toolchains with a file in use by a running process are skipped.\n\nEXAMPLES:\n  bin-expire toolchains\n  bin-expire toolchains --older-than 6months\n  bin-expire toolchains --archive --dry-run\n  bin-expire toolchains --archive\n  bin-expire restore nightly-2024-01-01-x86_64-unknown-linux-gnu\n  bin-expire toolchains --remove --yes";
+
+pub const RUNTIMES_AFTER_HELP: &str = "Lists every installed version in ~/.nvm/versions/node, ~/.pyenv/versions, ~/.rbenv/versions,\n~/.asdf/installs and ~/.local/share/mise/installs (NVM_DIR, PYENV_ROOT, RBENV_ROOT, ASDF_DATA_DIR\nand MISE_DATA_DIR are honoured) with its size and when a program in its bin/ directory was last used.\n\nKEPT BY:\n  R  a manager's default (nvm alias/default, pyenv/rbenv version, ~/.tool-versions, mise\n     config.toml) or a .nvmrc, .python-version, .ruby-version, .tool-versions or mise.toml file\n     in a project under project_roots. \"20\" keeps every 20.x; \"lts/*\" or \"latest\" keeps the\n     newest installed version.\n  P  pinned with `bin-expire pin <manager>/<tool>@<version>`\n\nARCHIVING:\n  --archive moves stale (✗) version directories to <archive_path>/runtimes after a confirmation\n  prompt (--yes skips it) and records them in archive.json; `bin-expire restore <name>` moves one\n  back. Versions with a file in use by a running process are skipped. Run `asdf reshim` or\n  `mise reshim` afterwards to drop shims of archived versions.\n\nEXAMPLES:\n  bin-expire runtimes\n  bin-expire runtimes --older-than 6months\n  bin-expire runtimes --archive --dry-run\n  bin-expire runtimes --archive\n  bin-expire restore pyenv/python@3.9.18";
+
+pub const CACHES_AFTER_HELP: &str = "Looks at crate archives in ~/.cargo/registry/cache, extracted crates in ~/.cargo/registry/src,\ncheckouts in ~/.cargo/git/checkouts (CARGO_HOME is honoured) and module versions in the Go module\ncache (GOMODCACHE, else GOPATH/pkg/mod, else ~/go/pkg/mod). An entry is stale when no file in it\nwas used within the threshold; the summary shows the reclaimable size per cache.\n\nDELETING:\n  --delete removes stale entries after a confirmation prompt (--yes skips it). Nothing is\n  archived: cargo and go download an entry again when a build needs it. Go's download cache\n  (pkg/mod/cache) is left alone; `go clean -modcache` clears all of it.\n\nEXAMPLES:\n  bin-expire caches\n  bin-expire caches --older-than 6months --verbose\n  bin-expire caches --delete --dry-run\n  bin-expire caches --delete";
+
+pub const SWEEP_AFTER_HELP: &str = "Walks project_roots (or each --dir) for Cargo target/ directories (tagged with CACHEDIR.TAG or\nnext to a Cargo.toml) and node_modules/ directories, and shows each with its size and when its\nproject was last touched: the newest modification time of the project's own files (build output,\ndependencies and .git don't count). A directory is stale (✗) when its project wasn't touched\nwithin the threshold.\n\nCLEANING UP:\n  Only stale directories are touched, after a confirmation prompt (--yes skips it).\n  --archive  Move them to <archive_path>/sweep/<project> and record them in archive.json;\n             <project> is the path below its root, e.g. work/my-app.\n             `bin-expire restore <project>/target` moves one back\n  --remove   Delete them (`cargo build` or `npm install` recreates them)\n  Directories with a file in use by a running process are skipped.\n\nEXAMPLES:\n  bin-expire sweep\n  bin-expire sweep --dir ~/src --older-than 30d\n  bin-expire sweep --remove --dry-run\n  bin-expire sweep --archive\n  bin-expire restore my-app/node_modules";
+
+pub const LIST_AFTER_HELP: &str = "Shows every entry in archive.json (newest last). ST is ! when the archived file is missing.\n\nEXAMPLES:\n  bin-expire list\n  bin-expire list --output json";
+
+pub const HISTORY_AFTER_HELP: &str = "Every `scan` is saved to history.jsonl next to config.toml (the last 1000 scans are kept;\n`check` is not recorded). WENT STALE is when the current stale stretch began; a binary is\n\"gone\" once a scan of its directory no longer finds it.\n\nEXAMPLES:\n  bin-expire history\n  bin-expire history --sizes\n  bin-expire history ripgrep\n  bin-expire history --output json";
+
+pub const DIFF_AFTER_HELP: &str = "Scan numbers are shown by `bin-expire history --sizes`. Only directories scanned both times are\ncompared. Changes: went stale, used again (stale before, newer last_used now), added, removed.\n\nEXAMPLES:\n  bin-expire diff\n  bin-expire diff 3 7\n  bin-expire diff --output json";
 
 pub const RESTORE_AFTER_HELP: &str = "EXAMPLE:\n  bin-expire restore old_tool.exe\n\nRestores the most recent archived entry for that name using archive.json.";
+
+pub const PIN_AFTER_HELP: &str = "Pins are stored in pins.json next to config.toml. A pinned binary shows as PINNED (P) in scan,\nwith its reason, and is never archived. Once --until has passed the pin expires and the binary\nbecomes a candidate again (run `bin-expire unpin <name>` to remove it early).\n\nEXAMPLES:\n  bin-expire pin ripgrep --reason \"on-call runbook\" --until 2027-01-01\n  bin-expire pin gopls --until 6months\n  bin-expire pin\n  bin-expire unpin ripgrep";
+
+pub const CONFIG_AFTER_HELP: &str = "LAYERS (later wins):\n  defaults < system (/etc/bin-expire/config.toml) < user config.toml < [profile.<name>]\n  < BIN_EXPIRE_THRESHOLD / BIN_EXPIRE_ARCHIVE_PATH < --config <file>\n  ignored_bins lists are merged across layers; min_threshold keeps the strictest value.\n\nPROFILES:\n  [profile.<name>] sections override any key. Selected by --profile, then BIN_EXPIRE_PROFILE,\n  then default_profile.\n\nOlder config files are migrated step by step on load (see config_version); migrations and\n`config set` edit the file in place, so comments and formatting are kept.\n\nEXAMPLES:\n  bin-expire config show\n  bin-expire config get archive_path\n  bin-expire config set default_threshold 6months\n  bin-expire config set ignored_bins \"cargo, rustc, re:^go(pls|imports)$\"\n  bin-expire config edit\n  bin-expire config validate";
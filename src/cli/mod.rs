@@ -1,4 +1,12 @@
+use chrono::NaiveDate;
 use clap::{Parser, Subcommand};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::output::OutputFormat;
+use crate::pins;
+use crate::threshold;
+use crate::ui::table::{Column, GroupBy, SortKey};
 
 mod help;
 
@@ -10,6 +18,34 @@ mod help;
     after_help = help::TOP_AFTER_HELP
 )]
 pub struct Cli {
+    /// Output format. Machine-readable formats write records to stdout and messages to stderr
+    #[arg(long, global = true, value_enum, default_value_t = OutputFormat::Table)]
+    pub output: OutputFormat,
+
+    /// Extra config file applied on top of the system/user config and environment variables
+    #[arg(long, global = true, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+
+    /// Use the [profile.<name>] section of the config (overrides BIN_EXPIRE_PROFILE and default_profile)
+    #[arg(long, global = true, value_name = "NAME")]
+    pub profile: Option<String>,
+
+    /// Worker threads for scanning (overrides scan_threads; 0 = one per CPU)
+    #[arg(long, global = true, value_name = "N")]
+    pub threads: Option<usize>,
+
+    /// Scan on the calling thread only (same as --threads 1)
+    #[arg(long, global = true, conflicts_with = "threads")]
+    pub single_threaded: bool,
+
+    /// Look this many levels deep in each scan dir (overrides max_depth; 1 = only its files)
+    #[arg(long, global = true, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    pub max_depth: Option<u32>,
+
+    /// Don't descend into directories on a different filesystem than the scan dir
+    #[arg(long, global = true)]
+    pub one_file_system: bool,
+
     #[command(subcommand)]
     pub command: Commands,
 }
@@ -19,12 +55,15 @@ pub enum Commands {
     /// Scan directories for stale binaries
     #[command(long_about = help::SCAN_LONG_ABOUT, after_help = help::SCAN_AFTER_HELP)]
     Scan {
-        /// Directory to scan (e.g., ~/.cargo/bin)
+        /// Directory to scan (e.g., ~/.cargo/bin, or a pattern like "~/.local/share/*/bin")
         #[arg(short = 'p', long)]
         dir: Option<String>,
         /// Threshold in days for stale files
-        #[arg(short, long)]
-        days: Option<i64>,
+        #[arg(short, long, conflicts_with = "older_than")]
+        days: Option<u64>,
+        /// Threshold as a duration, e.g. 90d, 2w, 6months
+        #[arg(long, value_name = "DURATION", value_parser = threshold::parse_cli_threshold)]
+        older_than: Option<Duration>,
 
         /// Show a more detailed table (includes PATH, SRC) and also shows OK rows
         #[arg(short, long)]
@@ -39,22 +78,275 @@ pub enum Commands {
         /// Hide stub rows (0-byte .exe App Execution Alias stubs) from the scan output table
         #[arg(long)]
         hide_stub: bool,
+
+        /// Exit with code 3 if any stale binaries or broken symlinks are found (for CI)
+        #[arg(long)]
+        fail_if_stale: bool,
+        /// Also search project_roots for build and CI files that still use stale binaries
+        #[arg(long)]
+        projects: bool,
+        /// Read new or changed binaries for their kind, hash and Go build info (otherwise only
+        /// results cached by an earlier --inspect are shown)
+        #[arg(long)]
+        inspect: bool,
+        /// Don't reuse or update kind/hash/build info from earlier scans
+        #[arg(long)]
+        no_cache: bool,
+
+        /// Sort rows by this key (sizes: largest first; times: oldest first)
+        #[arg(long, value_enum, default_value_t = SortKey::Name)]
+        sort: SortKey,
+        /// Reverse the sort order
+        #[arg(long)]
+        reverse: bool,
+        /// Split the table into groups with per-group count and size subtotals
+        #[arg(long, value_enum)]
+        group_by: Option<GroupBy>,
+        /// Comma-separated columns to show (e.g. "st,name,size,path")
+        #[arg(long, value_enum, value_delimiter = ',')]
+        columns: Option<Vec<Column>>,
+    },
+
+    /// Fail (exit code 3) if any stale binaries or broken symlinks are found; for CI gates
+    #[command(after_help = help::CHECK_AFTER_HELP)]
+    Check {
+        /// Directory to scan (e.g., ~/.cargo/bin, or a pattern like "~/.local/share/*/bin")
+        #[arg(short = 'p', long)]
+        dir: Option<String>,
+        /// Threshold in days for stale files
+        #[arg(short, long, conflicts_with = "older_than")]
+        days: Option<u64>,
+        /// Threshold as a duration, e.g. 90d, 2w, 6months
+        #[arg(long, value_name = "DURATION", value_parser = threshold::parse_cli_threshold)]
+        older_than: Option<Duration>,
     },
 
-    /// Move stale binaries to the archive folder
+    /// Move stale (or explicitly named) binaries to the archive folder
     #[command(after_help = help::ARCHIVE_AFTER_HELP)]
     Archive {
-        /// Directory to scan (e.g., ~/.cargo/bin)
-        #[arg(short = 'p', long)]
+        /// Binary names or globs to archive right away, regardless of age (e.g. "ripgrep", "cargo-*")
+        #[arg(value_name = "NAME", conflicts_with = "apply")]
+        targets: Vec<String>,
+
+        /// Directory to scan (e.g., ~/.cargo/bin, or a pattern like "~/.local/share/*/bin")
+        #[arg(short = 'p', long, conflicts_with = "apply")]
         dir: Option<String>,
+        /// Threshold in days for stale files (with NAME arguments: only archive them if also stale)
+        #[arg(short, long, conflicts_with_all = ["apply", "older_than"])]
+        days: Option<u64>,
+        /// Threshold as a duration, e.g. 90d, 2w, 6months
+        #[arg(long, value_name = "DURATION", value_parser = threshold::parse_cli_threshold, conflicts_with = "apply")]
+        older_than: Option<Duration>,
+
+        /// All-or-nothing: if any move fails (or Ctrl-C is pressed), move everything back
+        /// and leave the manifest unchanged
+        #[arg(long)]
+        atomic: bool,
+
+        /// Print what would be moved where, without moving anything
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Write the archive plan to this JSON file instead of moving anything
+        #[arg(long, value_name = "FILE", conflicts_with = "apply")]
+        plan: Option<PathBuf>,
+
+        /// Execute a plan written by --plan (after checking files haven't changed)
+        #[arg(long, value_name = "FILE")]
+        apply: Option<PathBuf>,
+
+        /// Don't ask for confirmation before moving files (for scripts)
         #[arg(short, long)]
-        days: Option<i64>,
+        yes: bool,
+
+        /// Pick which stale binaries to archive from a checklist
+        #[arg(short, long)]
+        interactive: bool,
+    },
+
+    /// List installed rustup toolchains; archive or remove stale ones nothing refers to
+    #[command(after_help = help::TOOLCHAINS_AFTER_HELP)]
+    Toolchains {
+        /// Threshold in days for stale toolchains
+        #[arg(short, long, conflicts_with = "older_than")]
+        days: Option<u64>,
+        /// Threshold as a duration, e.g. 90d, 2w, 6months
+        #[arg(long, value_name = "DURATION", value_parser = threshold::parse_cli_threshold)]
+        older_than: Option<Duration>,
+
+        /// Move stale, unreferenced toolchains to the archive folder (undo with `restore`)
+        #[arg(long, group = "action")]
+        archive: bool,
+
+        /// Delete stale, unreferenced toolchains (reinstall with `rustup toolchain install`)
+        #[arg(long, group = "action")]
+        remove: bool,
+
+        /// Print what would be archived or removed, without touching anything
+        #[arg(long, requires = "action")]
+        dry_run: bool,
+
+        /// Don't ask for confirmation (for scripts)
+        #[arg(short, long, requires = "action")]
+        yes: bool,
     },
 
-    /// Restore a previously archived binary back to its original path
+    /// List runtime versions installed by nvm, pyenv, rbenv, asdf and mise; archive stale ones
+    #[command(after_help = help::RUNTIMES_AFTER_HELP)]
+    Runtimes {
+        /// Threshold in days for stale versions
+        #[arg(short, long, conflicts_with = "older_than")]
+        days: Option<u64>,
+        /// Threshold as a duration, e.g. 90d, 2w, 6months
+        #[arg(long, value_name = "DURATION", value_parser = threshold::parse_cli_threshold)]
+        older_than: Option<Duration>,
+
+        /// Move stale, unpinned versions to the archive folder (undo with `restore`)
+        #[arg(long)]
+        archive: bool,
+
+        /// Print what would be archived, without moving anything
+        #[arg(long, requires = "archive")]
+        dry_run: bool,
+
+        /// Don't ask for confirmation (for scripts)
+        #[arg(short, long, requires = "archive")]
+        yes: bool,
+    },
+
+    /// Report stale entries in the cargo registry, cargo git and Go module caches; delete them
+    #[command(after_help = help::CACHES_AFTER_HELP)]
+    Caches {
+        /// Threshold in days for stale entries
+        #[arg(short, long, conflicts_with = "older_than")]
+        days: Option<u64>,
+        /// Threshold as a duration, e.g. 90d, 2w, 6months
+        #[arg(long, value_name = "DURATION", value_parser = threshold::parse_cli_threshold)]
+        older_than: Option<Duration>,
+
+        /// List every stale entry, not just the per-cache summary
+        #[arg(short, long)]
+        verbose: bool,
+
+        /// Delete stale entries (they are downloaded again when a build needs them)
+        #[arg(long)]
+        delete: bool,
+
+        /// Print what would be deleted, without deleting anything
+        #[arg(long, requires = "delete")]
+        dry_run: bool,
+
+        /// Don't ask for confirmation (for scripts)
+        #[arg(short, long, requires = "delete")]
+        yes: bool,
+    },
+
+    /// Find Cargo target/ and node_modules/ directories of stale projects; archive or delete them
+    #[command(after_help = help::SWEEP_AFTER_HELP)]
+    Sweep {
+        /// Directory to search instead of project_roots (repeatable)
+        #[arg(short = 'p', long = "dir", value_name = "DIR")]
+        dirs: Vec<String>,
+        /// Threshold in days since a project was last touched
+        #[arg(short, long, conflicts_with = "older_than")]
+        days: Option<u64>,
+        /// Threshold as a duration, e.g. 90d, 2w, 6months
+        #[arg(long, value_name = "DURATION", value_parser = threshold::parse_cli_threshold)]
+        older_than: Option<Duration>,
+
+        /// Move build directories of stale projects to the archive folder (undo with `restore`)
+        #[arg(long, group = "action")]
+        archive: bool,
+
+        /// Delete them (rebuild with `cargo build` / `npm install`)
+        #[arg(long, group = "action")]
+        remove: bool,
+
+        /// Print what would be archived or removed, without touching anything
+        #[arg(long, requires = "action")]
+        dry_run: bool,
+
+        /// Don't ask for confirmation (for scripts)
+        #[arg(short, long, requires = "action")]
+        yes: bool,
+    },
+
+    /// List archived binaries recorded in the manifest
+    #[command(after_help = help::LIST_AFTER_HELP)]
+    List,
+
+    /// Restore an archived binary, toolchain, runtime or build directory to its original path
     #[command(after_help = help::RESTORE_AFTER_HELP)]
     Restore {
         /// The archived file name to restore (e.g., "ripgrep" or "old_tool.exe")
         name: String,
     },
+
+    /// Protect a binary from archiving (without a NAME: list pins)
+    #[command(after_help = help::PIN_AFTER_HELP)]
+    Pin {
+        /// Binary file name to pin (e.g., "ripgrep" or "tool.exe")
+        name: Option<String>,
+
+        /// Why the binary is protected (shown in scan output)
+        #[arg(short, long, requires = "name")]
+        reason: Option<String>,
+
+        /// Last day the pin is in effect: a date (2027-01-01) or a duration from today (90d, 6months)
+        #[arg(short, long, value_name = "DATE", requires = "name", value_parser = parse_until)]
+        until: Option<NaiveDate>,
+    },
+
+    /// Remove a pin so the binary can be archived again
+    Unpin {
+        /// Pinned binary file name
+        name: String,
+    },
+
+    /// Show what recorded scans saw: per binary, per directory size, or one binary over time
+    #[command(after_help = help::HISTORY_AFTER_HELP)]
+    History {
+        /// Show one binary's status in every recorded scan
+        #[arg(conflicts_with = "sizes")]
+        name: Option<String>,
+        /// Show total size per scanned directory for every recorded scan
+        #[arg(long)]
+        sizes: bool,
+    },
+
+    /// Compare two recorded scans (default: the last two)
+    #[command(after_help = help::DIFF_AFTER_HELP)]
+    Diff {
+        /// Older scan number (default: the scan before TO)
+        from: Option<u64>,
+        /// Newer scan number (default: the latest scan)
+        to: Option<u64>,
+    },
+
+    /// Show, change and check config.toml
+    #[command(after_help = help::CONFIG_AFTER_HELP)]
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ConfigAction {
+    /// Print every effective value and where it came from
+    Show,
+    /// Print one effective value
+    Get { key: String },
+    /// Change one value in config.toml (comments and layout are kept)
+    Set { key: String, value: String },
+    /// Open config.toml in $VISUAL / $EDITOR, then validate it
+    Edit,
+    /// Check config.toml without changing it (exit code 5 if invalid)
+    Validate,
+    /// Print the path of config.toml
+    Path,
+}
+
+fn parse_until(input: &str) -> Result<NaiveDate, String> {
+    pins::parse_until(input).map_err(|err| err.to_string())
 }
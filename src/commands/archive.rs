@@ -1,35 +1,248 @@
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
 use colored::Colorize;
+use globset::{Glob, GlobMatcher};
 use std::path::PathBuf;
+use std::time::Duration;
 
 use crate::analyzer::is_dormant;
-use crate::archive_manifest::record_archive;
-use crate::archiver::archive_binary;
-use crate::fs_scanner::scan_directory;
-use crate::models::Config;
+use crate::archive_manifest::{record_archive, record_archives, ArchiveEntry};
+use crate::archive_plan::{build_plan, read_plan, verify_item, write_plan, PlanItem};
+use crate::archiver::{archive_to, move_file_with_fallback};
+use crate::eligibility::{Policy, Tracker};
+use crate::exit_status::{ErrorKind, ExitStatus};
+use crate::fs_scanner::{expand_dir_spec, scan_directories};
+use crate::ignore::{IgnoreRules, Ignored};
+use crate::in_use::InUse;
+use crate::interrupt;
+use crate::models::{BinaryInfo, Config};
+use crate::output::{self, ArchiveRecord};
+use crate::pins;
+use crate::references::References;
+use crate::say;
+use crate::threshold;
 use crate::ui;
+use crate::ui::prompt;
 
-pub fn run(dir: Option<String>, days: Option<i64>, config: &Config) -> Result<()> {
-    let days = days.unwrap_or(config.default_threshold_days);
+pub struct ArchiveArgs {
+    /// Binary names or globs to archive regardless of age (unless `threshold` is also given).
+    pub targets: Vec<String>,
+    pub dir: Option<String>,
+    /// Explicit threshold from the command line (falls back to the config default).
+    pub threshold: Option<Duration>,
+    pub atomic: bool,
+    pub dry_run: bool,
+    /// Write the plan to this file instead of moving anything.
+    pub plan: Option<PathBuf>,
+    /// Execute a previously written plan instead of scanning.
+    pub apply: Option<PathBuf>,
+    /// Skip the confirmation prompt.
+    pub yes: bool,
+    /// Let the user deselect individual binaries before anything moves.
+    pub interactive: bool,
+}
+
+pub fn run(args: ArchiveArgs, config: &Config) -> Result<ExitStatus> {
+    let mut plan = match &args.apply {
+        Some(plan_path) => {
+            say!("{}", "─".repeat(60).dimmed());
+            say!("{}", "Applying archive plan".cyan().bold());
+            say!("{}", "─".repeat(60).dimmed());
+
+            let plan = read_plan(plan_path)?;
+            say!(
+                "{} {} ({} items, created {})",
+                "[*]".blue(),
+                plan_path.display(),
+                plan.items.len(),
+                plan.created_at
+            );
+
+            // Refuse to touch anything if the filesystem drifted since the plan was reviewed,
+            // or if something was pinned in the meantime.
+            let active_pins = pins::active_pins()?;
+            let references = References::collect();
+            let mismatches: Vec<String> = plan
+                .items
+                .iter()
+                .filter_map(|item| {
+                    if let Some(pin) = active_pins.iter().find(|p| p.name == item.name) {
+                        return Some(format!("{} is pinned: {}", item.name, pin.describe()));
+                    }
+                    if let Some(reference) = references.find_path(&item.name, &item.source) {
+                        return Some(format!("{} is referenced by {}", item.name, reference));
+                    }
+                    verify_item(item).err().map(|e| format!("{:#}", e))
+                })
+                .collect();
+            if !mismatches.is_empty() {
+                for m in &mismatches {
+                    eprintln!("{} {}", "✗".red(), m);
+                }
+                bail!(
+                    "Plan no longer matches the filesystem ({} item(s) changed); nothing was moved",
+                    mismatches.len()
+                );
+            }
+            plan
+        }
+        None => {
+            say!("{}", "─".repeat(60).dimmed());
+            if args.targets.is_empty() {
+                say!("{}", "Archiving stale binaries".cyan().bold());
+            } else {
+                say!("{}", "Archiving selected binaries".cyan().bold());
+            }
+            say!("{}", "─".repeat(60).dimmed());
+
+            // Previews show what a real run would decide but don't count as observations.
+            let record = !args.dry_run && args.plan.is_none();
+            let candidates = collect_candidates(
+                args.dir.clone(),
+                args.threshold,
+                &args.targets,
+                record,
+                config,
+            )?;
+            build_plan(&candidates, &config.archive_path)
+        }
+    };
+
+    if plan.items.is_empty() {
+        say!();
+        say!("{} Nothing to archive.", "✓".green().bold());
+        output::emit::<ArchiveRecord>("archive", &[])?;
+        return Ok(ExitStatus::Success);
+    }
+
+    if args.interactive {
+        let labels: Vec<String> = plan
+            .items
+            .iter()
+            .map(|item| {
+                format!(
+                    "{} ({}) {}",
+                    item.name,
+                    ui::format_bytes(item.size),
+                    item.source.display().to_string().dimmed()
+                )
+            })
+            .collect();
+        let Some(selected) = prompt::checklist("Select binaries to archive:", &labels)? else {
+            say!("{} Aborted; nothing was moved.", "[i]".blue());
+            return Ok(ExitStatus::Success);
+        };
+        let mut selected = selected.into_iter();
+        plan.items.retain(|_| selected.next().unwrap_or(false));
+
+        if plan.items.is_empty() {
+            say!("{} Nothing selected; nothing was moved.", "[i]".blue());
+            return Ok(ExitStatus::Success);
+        }
+    }
+
+    if !args.interactive || args.dry_run || args.plan.is_some() {
+        print_plan(&plan.items);
+    }
+
+    if let Some(plan_path) = &args.plan {
+        write_plan(plan_path, &plan)?;
+        say!(
+            "{} Wrote plan to {}. Review it, then run {}.",
+            "✓".green().bold(),
+            plan_path.display(),
+            format!("bin-expire archive --apply {}", plan_path.display())
+                .cyan()
+                .underline()
+        );
+    }
+
+    if args.dry_run || args.plan.is_some() {
+        let records: Vec<ArchiveRecord> = plan
+            .items
+            .iter()
+            .map(|item| archive_record(item, "planned", None))
+            .collect();
+        output::emit("archive", &records)?;
+        if args.dry_run {
+            say!("{} Dry run: nothing was moved.", "[i]".blue());
+        }
+        return Ok(ExitStatus::Success);
+    }
+
+    // Accepting the interactive checklist already counts as confirmation.
+    if !args.yes && !args.interactive {
+        let question = format!(
+            "Move {} binaries to {}?",
+            plan.items.len(),
+            plan.archive_path.display()
+        );
+        match prompt::confirm(&question)? {
+            Some(true) => {}
+            Some(false) => {
+                say!("{} Aborted; nothing was moved.", "[i]".blue());
+                return Ok(ExitStatus::Success);
+            }
+            None => bail!(
+                "No confirmation received (stdin closed); re-run with --yes to archive without prompting"
+            ),
+        }
+    }
+
+    execute(&plan.items, args.atomic, config)
+}
+
+/// Scan the requested directories and return the binaries that are eligible for archiving:
+/// stale ones by default, or the ones named by `targets` (optionally also required to be stale).
+/// Stale streaks are only written back to eligibility.json when `record` is set.
+fn collect_candidates(
+    dir: Option<String>,
+    threshold: Option<Duration>,
+    targets: &[String],
+    record: bool,
+    config: &Config,
+) -> Result<Vec<BinaryInfo>> {
+    // With explicit targets, age only matters if a threshold was given on the command line
+    // (or an admin set min_threshold).
+    let age_check = match threshold {
+        None if !targets.is_empty() => threshold::configured_minimum(config)?,
+        explicit => Some(threshold::resolve(explicit, config)?),
+    };
+
+    let matchers = targets
+        .iter()
+        .map(|t| {
+            Glob::new(t)
+                .map(|g| g.compile_matcher())
+                .with_context(|| format!("Invalid binary name or glob: '{}'", t))
+        })
+        .collect::<Result<Vec<GlobMatcher>>>()?;
+
+    let ignore_rules = IgnoreRules::from_config(config)?;
+    let active_pins = pins::active_pins()?;
+    let references = References::collect();
+    // Stale streaks only matter when archiving by age, and only if a policy is configured.
+    let policy = Policy::from_config(config).context(ErrorKind::Config)?;
+    let mut tracker = match age_check {
+        Some(_) if policy.is_enabled() => Some(Tracker::load(policy)?),
+        _ => None,
+    };
 
     let dirs: Vec<PathBuf> = match dir {
-        Some(path_str) => vec![ui::expand_tilde(&path_str)],
-        None => vec![
-            ui::expand_tilde("~/.cargo/bin"),
-            ui::expand_tilde("~/go/bin"),
-        ],
+        Some(spec) => expand_dir_spec(&spec),
+        None => config
+            .scan_dirs
+            .iter()
+            .flat_map(|d| expand_dir_spec(d))
+            .collect(),
     };
 
     let mut binaries = Vec::new();
-    let mut any_dir = false;
+    let mut ignored: Vec<Ignored> = Vec::new();
+    let mut existing: Vec<PathBuf> = Vec::new();
 
     #[cfg(windows)]
     let scan_start = std::time::SystemTime::now();
 
-    println!("{}", "─".repeat(60).dimmed());
-    println!("{}", "Archiving stale binaries".cyan().bold());
-    println!("{}", "─".repeat(60).dimmed());
-
     for path in dirs {
         if !path.exists() {
             eprintln!(
@@ -39,15 +252,30 @@ pub fn run(dir: Option<String>, days: Option<i64>, config: &Config) -> Result<()
             );
             continue;
         }
-        any_dir = true;
         ui::print_mount_option_warning(&path);
-        println!(
-            "{} {} for files > {} days old",
-            "[*]".blue(),
-            path.display(),
-            days
-        );
-        binaries.extend(scan_directory(&path, config.windows_use_access_time));
+        let what = if targets.is_empty() {
+            "files".to_string()
+        } else {
+            targets.join(", ")
+        };
+        match age_check {
+            Some(age) => say!(
+                "{} {} for {} older than {}",
+                "[*]".blue(),
+                path.display(),
+                what,
+                threshold::describe(age)
+            ),
+            None => say!("{} {} for {}", "[*]".blue(), path.display(), what),
+        }
+        existing.push(path);
+    }
+
+    let found = scan_directories(&existing, config);
+    for (path, found) in existing.iter().zip(found) {
+        let (kept, skipped) = ignore_rules.for_dir(path)?.partition(found);
+        binaries.extend(kept);
+        ignored.extend(skipped);
     }
 
     #[cfg(windows)]
@@ -63,19 +291,48 @@ pub fn run(dir: Option<String>, days: Option<i64>, config: &Config) -> Result<()
         );
     }
 
-    if !any_dir {
-        eprintln!("{} No valid directories found to archive.", "[ERROR]".red());
-        return Ok(());
+    if existing.is_empty() {
+        bail!("No valid directories found to archive");
     }
 
-    let mut stale: Vec<crate::models::BinaryInfo> = Vec::new();
-    let mut success_count = 0u64;
-    let mut fail_count = 0u64;
+    let mut candidates: Vec<BinaryInfo> = Vec::new();
+    let mut target_hits = vec![0usize; matchers.len()];
+
+    // Ignored binaries are never archived; say so when one was asked for by name.
+    for Ignored { bin, rule } in &ignored {
+        let mut matched = false;
+        for (i, m) in matchers.iter().enumerate() {
+            if m.is_match(&bin.name) {
+                target_hits[i] += 1;
+                matched = true;
+            }
+        }
+        if matched {
+            eprintln!(
+                "{} Skipping '{}': ignored by {}.",
+                "[!]".yellow(),
+                bin.name,
+                rule
+            );
+        }
+    }
 
     for bin in binaries {
-        if config.ignored_bins.iter().any(|b| b == &bin.name) {
-            continue;
+        if !matchers.is_empty() {
+            let mut matched = false;
+            for (i, m) in matchers.iter().enumerate() {
+                if m.is_match(&bin.name) {
+                    target_hits[i] += 1;
+                    matched = true;
+                }
+            }
+            if !matched {
+                continue;
+            }
         }
+
+        let is_named = !matchers.is_empty();
+
         let is_probable_stub = bin.size == 0
             && bin
                 .path
@@ -83,52 +340,353 @@ pub fn run(dir: Option<String>, days: Option<i64>, config: &Config) -> Result<()
                 .is_some_and(|ext| ext.to_string_lossy().eq_ignore_ascii_case("exe"));
 
         if is_probable_stub {
+            if is_named {
+                eprintln!(
+                    "{} Skipping '{}': 0-byte .exe stub (App Execution Alias), never archived.",
+                    "[!]".yellow(),
+                    bin.name
+                );
+            }
             continue;
         }
 
-        if is_dormant(bin.last_used, days) {
-            stale.push(bin);
+        if let Some(pin) = active_pins.iter().find(|p| p.name == bin.name) {
+            if is_named {
+                eprintln!(
+                    "{} Skipping '{}': pinned ({}).",
+                    "[!]".yellow(),
+                    bin.name,
+                    pin.describe()
+                );
+            }
+            continue;
         }
-    }
 
-    if stale.is_empty() {
-        println!();
-        println!("{} Nothing to archive.", "✓".green().bold());
-        return Ok(());
-    }
+        if bin.is_broken_link {
+            if is_named {
+                eprintln!(
+                    "{} Skipping '{}': broken symlink (target is missing).",
+                    "[!]".yellow(),
+                    bin.name
+                );
+            }
+            continue;
+        }
 
-    println!();
-    println!("Moving {} binaries to archive...", stale.len());
-    println!("{}", "─".repeat(60).dimmed());
-    for bin in &stale {
-        match archive_binary(bin, &config.archive_path) {
-            Ok(dest) => {
-                if let Err(err) = record_archive(&bin.name, &bin.path, &dest) {
+        match age_check {
+            Some(age) if !is_dormant(bin.last_used, age) => {
+                if let Some(tracker) = tracker.as_mut() {
+                    tracker.saw_used(&bin.path);
+                }
+                if is_named {
                     eprintln!(
-                        "{} Archived but failed to record manifest for '{}': {:#}",
-                        "[WARN]".yellow(),
+                        "{} Skipping '{}': used within the last {}.",
+                        "[!]".yellow(),
                         bin.name,
-                        err
+                        threshold::describe(age)
                     );
                 }
-                println!("{} Moved '{}' -> {}", "✓".green(), bin.name, dest.display());
+            }
+            _ => match references.find(&bin) {
+                Some(reference) => eprintln!(
+                    "{} Skipping '{}': referenced by {}.",
+                    "[!]".yellow(),
+                    bin.name,
+                    reference
+                ),
+                None => {
+                    if let Some(tracker) = tracker.as_mut() {
+                        let streak = tracker.saw_stale(&bin.name, &bin.path).clone();
+                        if !tracker.is_eligible(&streak) {
+                            eprintln!(
+                                "{} Skipping '{}': not confirmed stale yet ({}).",
+                                "[!]".yellow(),
+                                bin.name,
+                                tracker.progress(&streak)
+                            );
+                            continue;
+                        }
+                    }
+                    candidates.push(bin);
+                }
+            },
+        }
+    }
+
+    if let Some(tracker) = tracker.filter(|_| record) {
+        // Losing one observation only delays archiving; don't fail the run over it.
+        if let Err(err) = tracker.save() {
+            eprintln!("{} Failed to save stale streaks: {:#}", "[!]".yellow(), err);
+        }
+    }
+
+    for (target, hits) in targets.iter().zip(&target_hits) {
+        if *hits == 0 {
+            eprintln!(
+                "{} No binary matching '{}' found in the scanned directories.",
+                "[!]".yellow(),
+                target
+            );
+        }
+    }
+    if !targets.is_empty() && target_hits.iter().all(|h| *h == 0) {
+        bail!("None of the requested binaries were found");
+    }
+
+    Ok(candidates)
+}
+
+fn print_plan(items: &[PlanItem]) {
+    say!();
+    say!("Would move {} binaries to archive:", items.len());
+    say!("{}", "─".repeat(60).dimmed());
+    for item in items {
+        say!(
+            "{} '{}' {} -> {} ({})",
+            "→".cyan(),
+            item.name,
+            item.source.display(),
+            item.destination.display(),
+            ui::format_bytes(item.size)
+        );
+    }
+    say!("{}", "─".repeat(60).dimmed());
+}
+
+fn execute(items: &[PlanItem], atomic: bool, config: &Config) -> Result<ExitStatus> {
+    let mut success_count = 0u64;
+    let mut skip_count = 0u64;
+    let mut fail_count = 0u64;
+    let mut manifest_failed = false;
+
+    say!();
+    say!("Moving {} binaries to archive...", items.len());
+    if atomic {
+        say!(
+            "{} Atomic mode: any failure (or Ctrl-C) rolls back the whole batch.",
+            "[i]".blue()
+        );
+    }
+    say!("{}", "─".repeat(60).dimmed());
+
+    // Ctrl-C only sets a flag; we check it between files so a move is never cut in half.
+    interrupt::install_handler();
+
+    let mut moved: Vec<ArchiveEntry> = Vec::new();
+    let mut records: Vec<ArchiveRecord> = Vec::new();
+    let mut interrupted = false;
+
+    // A daemon or language server can look dormant by atime and still be running; moving its
+    // binary breaks self-re-exec and in-place upgrades.
+    let in_use = InUse::snapshot();
+
+    for item in items {
+        if interrupt::requested() {
+            interrupted = true;
+            break;
+        }
+        if let Some(user) = in_use.user_of(&item.source) {
+            eprintln!(
+                "{} Skipping '{}': in use ({}).",
+                "[!]".yellow(),
+                item.name,
+                user
+            );
+            records.push(archive_record(
+                item,
+                "skipped",
+                Some(format!("in use: {}", user)),
+            ));
+            skip_count += 1;
+            continue;
+        }
+        match archive_to(&item.source, &item.destination) {
+            Ok(()) => {
+                if !atomic {
+                    if let Err(err) = record_archive(&item.name, &item.source, &item.destination) {
+                        eprintln!(
+                            "{} Archived but failed to record manifest for '{}': {:#}",
+                            "[WARN]".yellow(),
+                            item.name,
+                            err
+                        );
+                        manifest_failed = true;
+                    }
+                }
+                say!(
+                    "{} Moved '{}' -> {}",
+                    "✓".green(),
+                    item.name,
+                    item.destination.display()
+                );
+                moved.push(ArchiveEntry::new(
+                    &item.name,
+                    &item.source,
+                    &item.destination,
+                ));
+                records.push(archive_record(item, "moved", None));
                 success_count += 1;
             }
             Err(err) => {
-                eprintln!("{} Failed to move '{}': {:#}", "✗".red(), bin.name, err);
+                eprintln!("{} Failed to move '{}': {:#}", "✗".red(), item.name, err);
+                records.push(archive_record(item, "failed", Some(format!("{:#}", err))));
                 fail_count += 1;
+                if atomic {
+                    break;
+                }
             }
         }
     }
 
-    println!("{}", "─".repeat(60).dimmed());
-    println!("{} Archive operation completed.", "✓".green().bold());
-    println!(
-        "{} Success: {} | Failed: {}",
+    if interrupt::requested() {
+        interrupted = true;
+    }
+
+    if atomic {
+        let failure = if interrupted {
+            Some("interrupted".to_string())
+        } else if fail_count > 0 {
+            Some(format!("{} item(s) failed to move", fail_count))
+        } else {
+            // Everything moved: record the batch with one manifest write.
+            record_archives(moved.clone())
+                .err()
+                .map(|err| format!("failed to record manifest: {:#}", err))
+        };
+
+        if let Some(reason) = failure {
+            say!("{}", "─".repeat(60).dimmed());
+            eprintln!(
+                "{} Atomic archive aborted ({}). Rolling back {} moved item(s)...",
+                "[!]".yellow(),
+                reason,
+                moved.len()
+            );
+            let restored = rollback(&moved);
+            // Whatever couldn't be moved back must at least be restorable later.
+            let stranded: Vec<ArchiveEntry> = moved
+                .iter()
+                .zip(&restored)
+                .filter(|(_, ok)| !**ok)
+                .map(|(entry, _)| entry.clone())
+                .collect();
+            let recorded = if stranded.is_empty() {
+                Ok(())
+            } else {
+                record_archives(stranded.clone())
+            };
+
+            // Moved items are the "moved" records, in the same order.
+            let mut restored = restored.into_iter();
+            for record in records.iter_mut().filter(|r| r.result == "moved") {
+                if restored.next().unwrap_or(false) {
+                    record.result = "rolled_back";
+                }
+            }
+            output::emit("archive", &records)?;
+
+            let message = if !stranded.is_empty() {
+                let names: Vec<&str> = stranded.iter().map(|e| e.name.as_str()).collect();
+                let manifest = match &recorded {
+                    Ok(()) => {
+                        "they are in the manifest, so `restore` can move them back".to_string()
+                    }
+                    Err(err) => format!("recording them in the manifest also failed: {:#}", err),
+                };
+                format!(
+                    "Atomic archive aborted ({}); {} item(s) could not be rolled back and remain in {}: {} ({})",
+                    reason,
+                    stranded.len(),
+                    config.archive_path.display(),
+                    names.join(", "),
+                    manifest
+                )
+            } else {
+                format!(
+                    "Atomic archive aborted ({}); all moved items were restored",
+                    reason
+                )
+            };
+            if interrupted {
+                return Err(anyhow::Error::new(ErrorKind::Interrupted).context(message));
+            }
+            if recorded.is_err() {
+                return Err(anyhow::Error::new(ErrorKind::Manifest).context(message));
+            }
+            bail!(message);
+        }
+    }
+
+    say!("{}", "─".repeat(60).dimmed());
+    if interrupted {
+        say!(
+            "{} Archive operation interrupted; remaining items were left in place.",
+            "[!]".yellow()
+        );
+    } else {
+        say!("{} Archive operation completed.", "✓".green().bold());
+    }
+    say!(
+        "{} Success: {} | Skipped (in use): {} | Failed: {}",
         "   ".dimmed(),
         success_count.to_string().green(),
+        skip_count.to_string().yellow(),
         fail_count.to_string().red()
     );
 
-    Ok(())
+    output::emit("archive", &records)?;
+
+    if interrupted {
+        return Err(anyhow::Error::new(ErrorKind::Interrupted)
+            .context("Archive interrupted; remaining items were left in place"));
+    }
+
+    if manifest_failed {
+        return Ok(ExitStatus::ManifestError);
+    }
+    if fail_count > 0 {
+        return Ok(ExitStatus::PartialFailure);
+    }
+
+    Ok(ExitStatus::Success)
+}
+
+fn archive_record(item: &PlanItem, result: &'static str, error: Option<String>) -> ArchiveRecord {
+    ArchiveRecord {
+        name: item.name.clone(),
+        result,
+        source: item.source.display().to_string(),
+        destination: item.destination.display().to_string(),
+        size: item.size,
+        error,
+    }
+}
+
+/// Move every already-archived item back to its original path, newest first.
+/// Returns, for each entry of `moved` (in its original order), whether it was put back.
+fn rollback(moved: &[ArchiveEntry]) -> Vec<bool> {
+    let mut restored = vec![false; moved.len()];
+    for (i, entry) in moved.iter().enumerate().rev() {
+        match move_file_with_fallback(&entry.archived_path, &entry.original_path) {
+            Ok(()) => {
+                eprintln!(
+                    "{} Rolled back '{}' -> {}",
+                    "↺".yellow(),
+                    entry.name,
+                    entry.original_path.display()
+                );
+                restored[i] = true;
+            }
+            Err(err) => {
+                eprintln!(
+                    "{} Failed to roll back '{}' (still at {}): {:#}",
+                    "✗".red(),
+                    entry.name,
+                    entry.archived_path.display(),
+                    err
+                );
+            }
+        }
+    }
+    restored
 }
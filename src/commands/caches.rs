@@ -0,0 +1,214 @@
+use anyhow::{bail, Result};
+use colored::Colorize;
+use std::time::Duration;
+use tabled::settings::style::Style;
+use tabled::Table;
+
+use crate::analyzer::is_dormant;
+use crate::archiver::remove_all;
+use crate::caches::{self, Cache, Entry};
+use crate::exit_status::{ErrorKind, ExitStatus};
+use crate::interrupt;
+use crate::models::Config;
+use crate::output::{self, CacheRecord};
+use crate::say;
+use crate::threshold;
+use crate::ui;
+use crate::ui::prompt;
+
+pub struct CachesArgs {
+    /// Explicit threshold from the command line (falls back to the config default).
+    pub threshold: Option<Duration>,
+    /// List every stale entry, not just the per-cache summary.
+    pub verbose: bool,
+    /// Delete stale entries.
+    pub delete: bool,
+    pub dry_run: bool,
+    /// Skip the confirmation prompt.
+    pub yes: bool,
+}
+
+pub fn run(args: CachesArgs, config: &Config) -> Result<ExitStatus> {
+    let threshold = threshold::resolve(args.threshold, config)?;
+
+    say!("{}", "─".repeat(60).dimmed());
+    say!("{}", "Package caches".cyan().bold());
+    say!("{}", "─".repeat(60).dimmed());
+
+    let found = caches::locate();
+    if found.is_empty() {
+        say!(
+            "{} No cargo registry, cargo git or Go module cache found.",
+            "✓".green().bold()
+        );
+        output::emit::<CacheRecord>("caches", &[])?;
+        return Ok(ExitStatus::Success);
+    }
+    say!(
+        "{} Entries not used in {} are stale",
+        "[*]".blue(),
+        threshold::describe(threshold)
+    );
+
+    let entries = caches::entries(&found, config);
+    let stale: Vec<&Entry> = entries
+        .iter()
+        .filter(|e| is_dormant(e.last_used, threshold))
+        .collect();
+    let mut records: Vec<CacheRecord> = stale
+        .iter()
+        .map(|entry| CacheRecord {
+            ecosystem: entry.kind.as_str(),
+            name: entry.name.clone(),
+            size: entry.size,
+            last_used: humantime::format_rfc3339_seconds(entry.last_used).to_string(),
+            last_used_source: entry.last_used_source.as_str(),
+            path: entry.path.display().to_string(),
+            action: None,
+            error: None,
+        })
+        .collect();
+
+    if args.verbose {
+        print_entries(&stale);
+    }
+    print_summary(&found, &entries, &stale);
+
+    let reclaimable: u64 = stale.iter().map(|e| e.size).sum();
+    say!();
+    if stale.is_empty() {
+        say!(
+            "{} {} cache entries ({}); none are stale.",
+            "✓".green().bold(),
+            entries.len(),
+            ui::format_bytes(entries.iter().map(|e| e.size).sum())
+        );
+        output::emit("caches", &records)?;
+        return Ok(ExitStatus::Success);
+    }
+    if !args.delete {
+        say!(
+            "{} {} reclaimable in {} stale entries. Delete them with {}.",
+            "[i]".blue(),
+            ui::format_bytes(reclaimable),
+            stale.len(),
+            "bin-expire caches --delete".cyan()
+        );
+        output::emit("caches", &records)?;
+        return Ok(ExitStatus::Success);
+    }
+
+    if args.dry_run {
+        for record in &mut records {
+            record.action = Some("planned");
+        }
+        say!(
+            "{} Dry run: would delete {} stale entries ({}); nothing was changed.",
+            "[i]".blue(),
+            stale.len(),
+            ui::format_bytes(reclaimable)
+        );
+        output::emit("caches", &records)?;
+        return Ok(ExitStatus::Success);
+    }
+
+    if !args.yes {
+        let question = format!(
+            "Delete {} stale cache entries ({})? Builds download them again when needed.",
+            stale.len(),
+            ui::format_bytes(reclaimable)
+        );
+        match prompt::confirm(&question)? {
+            Some(true) => {}
+            Some(false) => {
+                say!("{} Aborted; nothing was changed.", "[i]".blue());
+                output::emit("caches", &records)?;
+                return Ok(ExitStatus::Success);
+            }
+            None => bail!(
+                "No confirmation received (stdin closed); re-run with --yes to delete without prompting"
+            ),
+        }
+    }
+
+    interrupt::install_handler();
+    let mut freed = 0u64;
+    let mut deleted = 0usize;
+    let mut fail_count = 0usize;
+    for (entry, record) in stale.iter().zip(&mut records) {
+        if interrupt::requested() {
+            break;
+        }
+        match remove_all(&entry.path) {
+            Ok(()) => {
+                record.action = Some("removed");
+                freed += entry.size;
+                deleted += 1;
+            }
+            Err(err) => {
+                eprintln!("{} Failed to delete '{}': {:#}", "✗".red(), entry.name, err);
+                record.action = Some("failed");
+                record.error = Some(format!("{:#}", err));
+                fail_count += 1;
+            }
+        }
+    }
+    say!(
+        "{} Deleted {} entries, freed {}.",
+        "✓".green(),
+        deleted,
+        ui::format_bytes(freed)
+    );
+    output::emit("caches", &records)?;
+
+    if interrupt::requested() {
+        return Err(anyhow::Error::new(ErrorKind::Interrupted)
+            .context("Interrupted; remaining cache entries were left in place"));
+    }
+    if fail_count > 0 {
+        return Ok(ExitStatus::PartialFailure);
+    }
+    Ok(ExitStatus::Success)
+}
+
+fn print_summary(found: &[Cache], entries: &[Entry], stale: &[&Entry]) {
+    if output::is_machine() {
+        return;
+    }
+    let rows: Vec<ui::CacheSummaryRow> = found
+        .iter()
+        .map(|cache| {
+            let all: Vec<&Entry> = entries.iter().filter(|e| e.kind == cache.kind).collect();
+            let old: Vec<&&Entry> = stale.iter().filter(|e| e.kind == cache.kind).collect();
+            ui::CacheSummaryRow {
+                cache: cache.kind.label(),
+                entries: all.len(),
+                size: ui::format_bytes(all.iter().map(|e| e.size).sum()),
+                stale: old.len(),
+                reclaimable: ui::format_bytes(old.iter().map(|e| e.size).sum()),
+                path: cache.dir.display().to_string(),
+            }
+        })
+        .collect();
+    let mut table = Table::new(rows);
+    table.with(Style::modern());
+    println!("{}", table);
+}
+
+fn print_entries(stale: &[&Entry]) {
+    if output::is_machine() || stale.is_empty() {
+        return;
+    }
+    let rows: Vec<ui::CacheEntryRow> = stale
+        .iter()
+        .map(|entry| ui::CacheEntryRow {
+            cache: entry.kind.label(),
+            name: entry.name.clone(),
+            size: ui::format_bytes(entry.size),
+            last_used: ui::format_date_short(Some(entry.last_used)),
+        })
+        .collect();
+    let mut table = Table::new(rows);
+    table.with(Style::modern());
+    println!("{}", table);
+}
@@ -0,0 +1,216 @@
+use anyhow::{bail, Result};
+use colored::Colorize;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+use crate::archive_manifest::record_archive;
+use crate::archiver::{archive_to, remove_all, unique_destination};
+use crate::exit_status::{ErrorKind, ExitStatus};
+use crate::in_use::{InUse, User};
+use crate::interrupt;
+use crate::say;
+use crate::ui;
+use crate::ui::prompt;
+
+/// A whole directory (toolchain, runtime version, ...) that may be archived or deleted.
+pub struct Item {
+    /// Shown in messages and recorded in the manifest: what `restore` takes.
+    pub name: String,
+    pub path: PathBuf,
+    pub size: u64,
+    /// Where archiving puts it; a non-colliding name is picked inside.
+    pub archive_dir: PathBuf,
+}
+
+pub struct Options<'a> {
+    /// Delete instead of archiving.
+    pub remove: bool,
+    pub dry_run: bool,
+    /// Skip the confirmation prompt.
+    pub yes: bool,
+    /// Plural noun for messages, e.g. "toolchains".
+    pub noun: &'a str,
+    /// Shown in the confirmation question.
+    pub archive_path: &'a Path,
+}
+
+/// What happened to one item, for the command's records.
+#[derive(Debug, Default)]
+pub struct Outcome {
+    /// "planned" (dry run), "archived", "removed", "skipped" (in use) or "failed"; `None` if
+    /// the user declined.
+    pub action: Option<&'static str>,
+    pub destination: Option<String>,
+    pub error: Option<String>,
+}
+
+/// Print what would happen to `items`, ask, then archive each one (recorded in archive.json, so
+/// `restore` brings it back) or delete it. Directories with a file in use by a running process
+/// are skipped. Returns one outcome per item plus the command's result; the caller emits its
+/// records before returning that result.
+pub fn run(items: &[Item], options: &Options) -> Result<(Vec<Outcome>, Result<ExitStatus>)> {
+    let verb = if options.remove { "delete" } else { "move" };
+    let total: u64 = items.iter().map(|i| i.size).sum();
+    let mut outcomes: Vec<Outcome> = items.iter().map(|_| Outcome::default()).collect();
+
+    let mut reserved = HashSet::new();
+    let mut destinations: Vec<Option<PathBuf>> = Vec::new();
+    say!(
+        "Would {} {} {} ({}):",
+        verb,
+        items.len(),
+        options.noun,
+        ui::format_bytes(total)
+    );
+    say!("{}", "─".repeat(60).dimmed());
+    for item in items {
+        if options.remove {
+            say!("{} '{}' {}", "✗".red(), item.name, item.path.display());
+            destinations.push(None);
+        } else {
+            let file_name = item
+                .path
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_else(|| item.name.clone());
+            let dest = unique_destination(&item.archive_dir, &file_name, &reserved);
+            reserved.insert(dest.clone());
+            say!(
+                "{} '{}' {} -> {}",
+                "→".cyan(),
+                item.name,
+                item.path.display(),
+                dest.display()
+            );
+            destinations.push(Some(dest));
+        }
+    }
+    say!("{}", "─".repeat(60).dimmed());
+
+    if options.dry_run {
+        for (outcome, dest) in outcomes.iter_mut().zip(&destinations) {
+            outcome.action = Some("planned");
+            outcome.destination = dest.as_ref().map(|d| d.display().to_string());
+        }
+        say!("{} Dry run: nothing was changed.", "[i]".blue());
+        return Ok((outcomes, Ok(ExitStatus::Success)));
+    }
+
+    if !options.yes {
+        let question = if options.remove {
+            format!(
+                "Delete {} {}? They can't be restored.",
+                items.len(),
+                options.noun
+            )
+        } else {
+            format!(
+                "Move {} {} to {}?",
+                items.len(),
+                options.noun,
+                options.archive_path.display()
+            )
+        };
+        match prompt::confirm(&question)? {
+            Some(true) => {}
+            Some(false) => {
+                say!("{} Aborted; nothing was changed.", "[i]".blue());
+                return Ok((outcomes, Ok(ExitStatus::Success)));
+            }
+            None => bail!(
+                "No confirmation received (stdin closed); re-run with --yes to {} without prompting",
+                verb
+            ),
+        }
+    }
+
+    interrupt::install_handler();
+    let in_use = InUse::snapshot();
+    let mut fail_count = 0u64;
+    let mut manifest_failed = false;
+
+    for ((item, dest), outcome) in items.iter().zip(&destinations).zip(&mut outcomes) {
+        if interrupt::requested() {
+            break;
+        }
+        if let Some(user) = user_of(&item.path, &in_use) {
+            eprintln!(
+                "{} Skipping '{}': in use ({}).",
+                "[!]".yellow(),
+                item.name,
+                user
+            );
+            outcome.action = Some("skipped");
+            outcome.error = Some(format!("in use: {}", user));
+            continue;
+        }
+
+        let result = match dest {
+            Some(dest) => archive_to(&item.path, dest),
+            None => remove_all(&item.path),
+        };
+        match (result, dest) {
+            (Ok(()), Some(dest)) => {
+                // Same manifest as archived binaries, so `restore <name>` puts it back.
+                if let Err(err) = record_archive(&item.name, &item.path, dest) {
+                    eprintln!(
+                        "{} Archived but failed to record manifest for '{}': {:#}",
+                        "[WARN]".yellow(),
+                        item.name,
+                        err
+                    );
+                    manifest_failed = true;
+                }
+                say!(
+                    "{} Moved '{}' -> {}",
+                    "✓".green(),
+                    item.name,
+                    dest.display()
+                );
+                outcome.action = Some("archived");
+                outcome.destination = Some(dest.display().to_string());
+            }
+            (Ok(()), None) => {
+                say!("{} Deleted '{}'", "✓".green(), item.name);
+                outcome.action = Some("removed");
+            }
+            (Err(err), _) => {
+                eprintln!(
+                    "{} Failed to {} '{}': {:#}",
+                    "✗".red(),
+                    verb,
+                    item.name,
+                    err
+                );
+                outcome.action = Some("failed");
+                outcome.error = Some(format!("{:#}", err));
+                fail_count += 1;
+            }
+        }
+    }
+
+    let status = if interrupt::requested() {
+        Err(anyhow::Error::new(ErrorKind::Interrupted).context(format!(
+            "Interrupted; remaining {} were left in place",
+            options.noun
+        )))
+    } else if manifest_failed {
+        Ok(ExitStatus::ManifestError)
+    } else if fail_count > 0 {
+        Ok(ExitStatus::PartialFailure)
+    } else {
+        Ok(ExitStatus::Success)
+    };
+    Ok((outcomes, status))
+}
+
+/// A running process executing or mapping any file below `dir` (rust-analyzer, a language
+/// server, a long-running build, ...).
+fn user_of<'a>(dir: &Path, in_use: &'a InUse) -> Option<&'a User> {
+    WalkDir::new(dir)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .find_map(|e| in_use.user_of(e.path()))
+}
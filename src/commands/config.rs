@@ -0,0 +1,307 @@
+use anyhow::{anyhow, bail, Context, Result};
+use colored::Colorize;
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tabled::builder::Builder;
+use tabled::settings::style::Style;
+use toml_edit::{Array, Value};
+
+use crate::config::{self, migrate, Origin, Overrides, KEYS};
+use crate::exit_status::{ErrorKind, ExitStatus};
+use crate::models::Config;
+use crate::output::{self, ConfigRecord};
+use crate::say;
+use crate::threshold;
+
+/// Effective value of `key` as TOML text, or `None` when unset.
+fn effective_value(cfg: &Config, key: &str) -> Result<Option<toml::Value>> {
+    let table = toml::Table::try_from(cfg).context("Failed to serialize config")?;
+    Ok(table.get(key).cloned())
+}
+
+/// Print values the way a script wants them: strings without quotes, everything else as TOML.
+fn plain(value: &toml::Value) -> String {
+    match value {
+        toml::Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn check_key(key: &str) -> Result<()> {
+    if !KEYS.contains(&key) {
+        bail!(
+            "Unknown config key '{}' (known keys: {})",
+            key,
+            KEYS.join(", ")
+        );
+    }
+    Ok(())
+}
+
+/// "default", or every layer that contributed (merged lists can have several).
+fn describe_origins(origins: &[Origin]) -> String {
+    if origins.is_empty() {
+        return Origin::Default.to_string();
+    }
+    origins
+        .iter()
+        .map(Origin::to_string)
+        .collect::<Vec<_>>()
+        .join(" + ")
+}
+
+pub fn show(overrides: Overrides) -> Result<ExitStatus> {
+    let loaded = config::load(overrides).context(ErrorKind::Config)?;
+
+    let mut records = Vec::new();
+    for (key, origins) in &loaded.origins {
+        records.push(ConfigRecord {
+            key: key.to_string(),
+            value: effective_value(&loaded.config, key)?.map(|v| v.to_string()),
+            origin: describe_origins(origins),
+        });
+    }
+
+    if output::is_machine() {
+        output::emit("config", &records)?;
+        return Ok(ExitStatus::Success);
+    }
+
+    say!(
+        "{} Layers (later wins): default < system < user < profile < environment < --config",
+        "[*]".blue()
+    );
+    match &loaded.profile {
+        Some((name, source)) => say!("{} Profile: {} (from {})", "[*]".blue(), name, source),
+        None => say!("{} Profile: none", "[*]".blue()),
+    }
+    let mut builder = Builder::default();
+    builder.push_record(["KEY", "VALUE", "ORIGIN"]);
+    for r in records {
+        builder.push_record([
+            r.key,
+            r.value.unwrap_or_else(|| "(unset)".to_string()),
+            r.origin,
+        ]);
+    }
+    let mut table = builder.build();
+    table.with(Style::modern());
+    println!("{}", table);
+
+    Ok(ExitStatus::Success)
+}
+
+pub fn get(key: &str, overrides: Overrides) -> Result<ExitStatus> {
+    check_key(key)?;
+    let loaded = config::load(overrides).context(ErrorKind::Config)?;
+    let value = effective_value(&loaded.config, key)?;
+
+    if output::is_machine() {
+        let origin = loaded
+            .origins
+            .iter()
+            .find(|(k, _)| *k == key)
+            .map(|(_, o)| describe_origins(o))
+            .unwrap_or_default();
+        output::emit(
+            "config",
+            &[ConfigRecord {
+                key: key.to_string(),
+                value: value.map(|v| v.to_string()),
+                origin,
+            }],
+        )?;
+    } else if let Some(value) = value {
+        println!("{}", plain(&value));
+    }
+
+    Ok(ExitStatus::Success)
+}
+
+/// Turn command-line text into a TOML value of the right type for `key`.
+fn parse_value(key: &str, raw: &str) -> Result<Value> {
+    let value = match key {
+        "config_version" => bail!("config_version is managed by migrations and can't be set"),
+        "ignored_bins" | "scan_dirs" | "project_roots" if raw.trim_start().starts_with('[') => raw
+            .parse::<Value>()
+            .map_err(|err| anyhow!("Invalid TOML array for {}: {}", key, err))?,
+        // A plain comma-separated list is easier to type than a TOML array.
+        "ignored_bins" | "scan_dirs" | "project_roots" => Value::Array(
+            raw.split(',')
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .collect::<Array>(),
+        ),
+        "default_threshold_days" => Value::from(
+            raw.trim()
+                .parse::<i64>()
+                .map_err(|_| anyhow!("{} must be an integer, got '{}'", key, raw))?,
+        ),
+        "confirm_stale_scans" | "scan_threads" | "max_depth" => Value::from(
+            raw.trim()
+                .parse::<u32>()
+                .map_err(|_| anyhow!("{} must be a non-negative integer, got '{}'", key, raw))?
+                as i64,
+        ),
+        "windows_use_access_time" | "one_file_system" => Value::from(
+            raw.trim()
+                .parse::<bool>()
+                .map_err(|_| anyhow!("{} must be true or false, got '{}'", key, raw))?,
+        ),
+        _ => Value::from(raw),
+    };
+    Ok(value)
+}
+
+/// Change one key in place (in the `--config` file if given, else the user config). The rest
+/// of the file, including comments, is left as it was.
+pub fn set(key: &str, raw: &str, overrides: Overrides) -> Result<ExitStatus> {
+    check_key(key)?;
+    // Don't load the merged config: that would refuse to run on the broken file being fixed.
+    let path = existing_target(overrides).context(ErrorKind::Config)?;
+    let mut doc = config::read_document(&path).context(ErrorKind::Config)?;
+
+    let mut value = parse_value(key, raw).context(ErrorKind::Config)?;
+    // Keep any trailing comment on the line being replaced.
+    if let Some(existing) = doc.get(key).and_then(|item| item.as_value()) {
+        *value.decor_mut() = existing.decor().clone();
+    }
+    doc[key] = toml_edit::Item::Value(value);
+
+    // Nothing is written unless the whole file is still valid.
+    config::parse_document(&doc, &path).context(ErrorKind::Config)?;
+    config::write_document(&path, &doc).context(ErrorKind::Config)?;
+
+    say!("{} {} = {}", "✓".green(), key, doc[key].to_string().trim());
+    Ok(ExitStatus::Success)
+}
+
+fn target_path(overrides: Overrides) -> PathBuf {
+    overrides
+        .file
+        .map(Path::to_path_buf)
+        .unwrap_or_else(config::config_file_path)
+}
+
+/// `target_path`, creating the user config from its template if it doesn't exist yet.
+fn existing_target(overrides: Overrides) -> Result<PathBuf> {
+    let path = target_path(overrides);
+    if !path.exists() {
+        if overrides.file.is_some() {
+            bail!("--config file not found: {}", path.display());
+        }
+        config::write_default_config(&path)?;
+    }
+    Ok(path)
+}
+
+/// Open config.toml (or the `--config` file) in $VISUAL / $EDITOR, then validate the result.
+pub fn edit(overrides: Overrides) -> Result<ExitStatus> {
+    let path = existing_target(overrides).context(ErrorKind::Config)?;
+
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .unwrap_or_else(|_| {
+            if cfg!(windows) {
+                "notepad".to_string()
+            } else {
+                "vi".to_string()
+            }
+        });
+    // Allow editors with arguments, e.g. EDITOR="code --wait".
+    let mut parts = editor.split_whitespace();
+    let program = parts.next().ok_or_else(|| anyhow!("$EDITOR is empty"))?;
+    let status = Command::new(program)
+        .args(parts)
+        .arg(&path)
+        .status()
+        .with_context(|| format!("Failed to start editor '{}'", editor))?;
+    if !status.success() {
+        bail!("Editor '{}' exited with {}", editor, status);
+    }
+
+    validate(overrides)
+}
+
+/// Check one file without changing it: syntax, values, version and unknown keys.
+/// Returns the migration steps that will run on the next load (user file only).
+fn validate_file(path: &Path) -> Result<Vec<String>> {
+    let mut doc = config::read_document(path)?;
+    let defaults = migrate::MigrationDefaults {
+        archive_path: String::new(),
+    };
+    let pending = migrate::migrate(&mut doc, &defaults)?;
+    config::parse_document(&doc, path)?;
+
+    for key in config::unknown_keys(&doc) {
+        eprintln!(
+            "{} {}: unknown key '{}' is ignored (known keys: {}).",
+            "[!]".yellow(),
+            path.display(),
+            key,
+            KEYS.join(", ")
+        );
+    }
+    Ok(pending)
+}
+
+/// Check every config layer without changing anything, then the merged result.
+pub fn validate(overrides: Overrides) -> Result<ExitStatus> {
+    let user = config::config_file_path();
+    let mut files: Vec<(&str, PathBuf)> = Vec::new();
+    if let Some(system) = config::system_config_path().filter(|p| p.exists()) {
+        files.push(("system", system));
+    }
+    if user.exists() {
+        files.push(("user", user.clone()));
+    } else {
+        say!(
+            "{} {} does not exist yet; it will be created on the next run.",
+            "[i]".blue(),
+            user.display()
+        );
+    }
+    if let Some(path) = overrides.file {
+        files.push(("--config", path.to_path_buf()));
+    }
+
+    for (label, path) in &files {
+        let pending = validate_file(path).context(ErrorKind::Config)?;
+        if *label == "user" {
+            for step in &pending {
+                say!("{} Will migrate on next run: {}", "[i]".blue(), step);
+            }
+        }
+        say!("{} {} ({}) is valid.", "✓".green(), path.display(), label);
+    }
+
+    for var in [config::ENV_THRESHOLD, config::ENV_ARCHIVE_PATH] {
+        if let Ok(value) = env::var(var) {
+            if var == config::ENV_THRESHOLD {
+                threshold::parse_threshold(&value)
+                    .with_context(|| format!("Invalid {}", var))
+                    .context(ErrorKind::Config)?;
+            }
+            say!("{} {}={} overrides the files.", "[i]".blue(), var, value);
+        }
+    }
+
+    // The same layering, profile selection and checks every other command goes through.
+    let loaded = config::load_without_writing(overrides).context(ErrorKind::Config)?;
+    match &loaded.profile {
+        Some((name, source)) => say!(
+            "{} Merged configuration with [profile.{}] (from {}) is valid.",
+            "✓".green(),
+            name,
+            source
+        ),
+        None => say!("{} Merged configuration is valid.", "✓".green()),
+    }
+    Ok(ExitStatus::Success)
+}
+
+pub fn path(overrides: Overrides) -> Result<ExitStatus> {
+    println!("{}", target_path(overrides).display());
+    Ok(ExitStatus::Success)
+}
@@ -0,0 +1,372 @@
+use anyhow::{anyhow, bail, Result};
+use colored::Colorize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use tabled::settings::style::Style;
+use tabled::Table;
+
+use crate::exit_status::ExitStatus;
+use crate::history::{self, short_time, Change, ScanSnapshot};
+use crate::output::{self, DiffRecord, HistoryRecord, ScanSizeRecord, TimelineRecord};
+use crate::say;
+use crate::ui;
+
+fn header(title: &str) {
+    say!("{}", "─".repeat(60).dimmed());
+    say!("{}", title.cyan().bold());
+    say!("{}", "─".repeat(60).dimmed());
+}
+
+fn print_table<T: tabled::Tabled>(rows: Vec<T>) {
+    let mut table = Table::new(rows);
+    table.with(Style::modern());
+    println!("{}", table);
+}
+
+fn no_scans() -> Result<ExitStatus> {
+    say!(
+        "{} No scans recorded yet. Every {} adds one.",
+        "[i]".blue(),
+        "bin-expire scan".cyan()
+    );
+    Ok(ExitStatus::Success)
+}
+
+/// "+1.2 MB" / "-300 B" / "0 B".
+fn signed_bytes(change: i64) -> String {
+    let sign = if change > 0 {
+        "+"
+    } else if change < 0 {
+        "-"
+    } else {
+        ""
+    };
+    format!("{}{}", sign, ui::format_bytes(change.unsigned_abs()))
+}
+
+/// Fold every scan into one record per binary (by path).
+fn summarize(scans: &[ScanSnapshot]) -> Vec<HistoryRecord> {
+    let mut records: Vec<HistoryRecord> = Vec::new();
+    let mut index: HashMap<PathBuf, usize> = HashMap::new();
+
+    for scan in scans {
+        for entry in &scan.entries {
+            let i = *index.entry(entry.path.clone()).or_insert_with(|| {
+                records.push(HistoryRecord {
+                    name: entry.name.clone(),
+                    path: entry.path.display().to_string(),
+                    status: String::new(),
+                    first_seen: scan.taken_at.clone(),
+                    went_stale: None,
+                    last_seen: scan.taken_at.clone(),
+                    size: entry.size,
+                    scans: 0,
+                });
+                records.len() - 1
+            });
+            let record = &mut records[i];
+            if entry.status == "stale" {
+                if record.status != "stale" {
+                    record.went_stale = Some(scan.taken_at.clone());
+                }
+            } else {
+                record.went_stale = None;
+            }
+            record.status = entry.status.clone();
+            record.last_seen = scan.taken_at.clone();
+            record.size = entry.size;
+            record.scans += 1;
+        }
+
+        // Looked at its directory and didn't find it: archived, uninstalled or renamed.
+        let found = scan.by_path();
+        for (path, i) in &index {
+            if scan.covers(path) && !found.contains_key(path.as_path()) {
+                records[*i].status = "gone".to_string();
+                records[*i].went_stale = None;
+            }
+        }
+    }
+
+    records.sort_by(|a, b| a.name.cmp(&b.name).then_with(|| a.path.cmp(&b.path)));
+    records
+}
+
+/// Total size per scanned directory, per scan, with the change since that directory's
+/// previous scan. Binaries in nested directories count towards the deepest scanned root
+/// they're in, so overlapping roots don't count them twice.
+fn sizes(scans: &[ScanSnapshot]) -> Vec<ScanSizeRecord> {
+    let mut previous: HashMap<&PathBuf, u64> = HashMap::new();
+    let mut records = Vec::new();
+    for scan in scans {
+        for dir in &scan.dirs {
+            let in_dir: Vec<_> = scan
+                .entries
+                .iter()
+                .filter(|e| scan.root_of(&e.path) == Some(dir))
+                .collect();
+            let total = in_dir
+                .iter()
+                .fold(0u64, |acc, e| acc.saturating_add(e.size));
+            let change = previous
+                .insert(dir, total)
+                .map(|before| total as i64 - before as i64);
+            records.push(ScanSizeRecord {
+                scan: scan.id,
+                taken_at: scan.taken_at.clone(),
+                dir: dir.display().to_string(),
+                binaries: in_dir.len(),
+                total_size: total,
+                change,
+            });
+        }
+    }
+    records
+}
+
+pub fn history(name: Option<&str>, show_sizes: bool) -> Result<ExitStatus> {
+    let scans = history::all_scans()?;
+
+    if let Some(name) = name {
+        return timeline(&scans, name);
+    }
+
+    if show_sizes {
+        let records = sizes(&scans);
+        if output::is_machine() {
+            output::emit("history", &records)?;
+            return Ok(ExitStatus::Success);
+        }
+        header("Bin directory sizes");
+        if scans.is_empty() {
+            return no_scans();
+        }
+        print_table(
+            records
+                .into_iter()
+                .map(|r| ui::ScanSizeRow {
+                    scan: r.scan,
+                    taken_at: short_time(&r.taken_at),
+                    dir: r.dir,
+                    binaries: r.binaries,
+                    total: ui::format_bytes(r.total_size),
+                    change: r.change.map_or("-".to_string(), signed_bytes),
+                })
+                .collect::<Vec<_>>(),
+        );
+        return Ok(ExitStatus::Success);
+    }
+
+    let records = summarize(&scans);
+    if output::is_machine() {
+        output::emit("history", &records)?;
+        return Ok(ExitStatus::Success);
+    }
+
+    header("Scan history");
+    if scans.is_empty() {
+        return no_scans();
+    }
+    say!(
+        "{} {} scans from {} to {}",
+        "[*]".blue(),
+        scans.len(),
+        short_time(&scans[0].taken_at),
+        short_time(&scans[scans.len() - 1].taken_at)
+    );
+    print_table(
+        records
+            .into_iter()
+            .map(|r| ui::HistoryRow {
+                name: r.name,
+                status: r.status,
+                first_seen: short_time(&r.first_seen),
+                went_stale: r.went_stale.as_deref().map_or("-".to_string(), short_time),
+                last_seen: short_time(&r.last_seen),
+                size: ui::format_bytes(r.size),
+            })
+            .collect::<Vec<_>>(),
+    );
+
+    // First and latest size of each directory.
+    let sizes = sizes(&scans);
+    let mut dirs: Vec<&str> = Vec::new();
+    for r in &sizes {
+        if !dirs.contains(&r.dir.as_str()) {
+            dirs.push(&r.dir);
+        }
+    }
+    say!();
+    for dir in dirs {
+        let of_dir: Vec<&ScanSizeRecord> = sizes.iter().filter(|r| r.dir == dir).collect();
+        let (first, last) = (of_dir[0], of_dir[of_dir.len() - 1]);
+        say!(
+            "{} {}: {} ({}) -> {} ({}), {}",
+            "[i]".blue(),
+            dir,
+            ui::format_bytes(first.total_size),
+            short_time(&first.taken_at),
+            ui::format_bytes(last.total_size),
+            short_time(&last.taken_at),
+            signed_bytes(last.total_size as i64 - first.total_size as i64)
+        );
+    }
+    say!(
+        "{} Per-scan sizes: {}. One binary over time: {}.",
+        "[i]".blue(),
+        "bin-expire history --sizes".cyan(),
+        "bin-expire history <name>".cyan()
+    );
+    Ok(ExitStatus::Success)
+}
+
+fn timeline(scans: &[ScanSnapshot], name: &str) -> Result<ExitStatus> {
+    let records: Vec<TimelineRecord> = scans
+        .iter()
+        .flat_map(|scan| {
+            scan.entries
+                .iter()
+                .filter(|e| e.name == name)
+                .map(|e| TimelineRecord {
+                    scan: scan.id,
+                    taken_at: scan.taken_at.clone(),
+                    name: e.name.clone(),
+                    path: e.path.display().to_string(),
+                    status: e.status.clone(),
+                    size: e.size,
+                    last_used: e.last_used.clone(),
+                })
+        })
+        .collect();
+    if records.is_empty() {
+        bail!("'{}' does not appear in any recorded scan", name);
+    }
+
+    if output::is_machine() {
+        output::emit("history", &records)?;
+        return Ok(ExitStatus::Success);
+    }
+
+    header(&format!("History of '{}'", name));
+    print_table(
+        records
+            .into_iter()
+            .map(|r| ui::TimelineRow {
+                scan: r.scan,
+                taken_at: short_time(&r.taken_at),
+                status: r.status,
+                size: ui::format_bytes(r.size),
+                last_used: short_time(&r.last_used),
+                path: r.path,
+            })
+            .collect::<Vec<_>>(),
+    );
+    Ok(ExitStatus::Success)
+}
+
+/// Compare two recorded scans; by default the last two.
+pub fn diff(from: Option<u64>, to: Option<u64>) -> Result<ExitStatus> {
+    let scans = history::all_scans()?;
+    let find = |id: u64| {
+        scans.iter().position(|s| s.id == id).ok_or_else(|| {
+            anyhow!(
+                "No recorded scan #{} (see `bin-expire history --sizes`)",
+                id
+            )
+        })
+    };
+
+    let to_idx = match to {
+        Some(id) => find(id)?,
+        None if scans.len() >= 2 => scans.len() - 1,
+        None => bail!(
+            "Need at least two recorded scans to diff (found {}); run `bin-expire scan` again later",
+            scans.len()
+        ),
+    };
+    let from_idx = match from {
+        Some(id) => find(id)?,
+        None if to_idx > 0 => to_idx - 1,
+        None => bail!(
+            "Scan #{} is the first recorded scan; nothing to compare it with",
+            scans[to_idx].id
+        ),
+    };
+    let (before, after) = (&scans[from_idx], &scans[to_idx]);
+
+    let mut changes = history::diff(before, after);
+    changes.sort_by_key(|(change, b, a)| {
+        let name = a.or(*b).map(|e| e.name.clone()).unwrap_or_default();
+        (*change as u8, name)
+    });
+    let records: Vec<DiffRecord> = changes
+        .iter()
+        .map(|(change, b, a)| {
+            let entry = a.or(*b).expect("a change has at least one side");
+            DiffRecord {
+                change: change.as_str(),
+                name: entry.name.clone(),
+                path: entry.path.display().to_string(),
+                status_before: b.map(|e| e.status.clone()),
+                status_after: a.map(|e| e.status.clone()),
+                last_used_before: b.map(|e| e.last_used.clone()),
+                last_used_after: a.map(|e| e.last_used.clone()),
+            }
+        })
+        .collect();
+
+    if output::is_machine() {
+        output::emit("diff", &records)?;
+        return Ok(ExitStatus::Success);
+    }
+
+    header(&format!(
+        "Scan #{} ({}) -> scan #{} ({})",
+        before.id,
+        short_time(&before.taken_at),
+        after.id,
+        short_time(&after.taken_at)
+    ));
+    if before.threshold != after.threshold {
+        say!(
+            "{} The scans used different thresholds ({} vs {}); some status changes come from that.",
+            "[!]".yellow(),
+            before.threshold,
+            after.threshold
+        );
+    }
+    if records.is_empty() {
+        say!("{} No changes.", "✓".green().bold());
+        return Ok(ExitStatus::Success);
+    }
+
+    let count = |c: Change| changes.iter().filter(|(k, _, _)| *k == c).count();
+    let summary = format!(
+        "{} went stale | {} used again | {} added | {} removed",
+        count(Change::WentStale),
+        count(Change::UsedAgain),
+        count(Change::Added),
+        count(Change::Removed)
+    );
+    print_table(
+        records
+            .into_iter()
+            .map(|r| ui::DiffRow {
+                change: r.change.replace('_', " "),
+                name: r.name,
+                before: r
+                    .last_used_before
+                    .as_deref()
+                    .map_or("-".to_string(), short_time),
+                after: r
+                    .last_used_after
+                    .as_deref()
+                    .map_or("-".to_string(), short_time),
+                path: r.path,
+            })
+            .collect::<Vec<_>>(),
+    );
+    say!();
+    say!("{} {}", ">>>".bold(), summary);
+    Ok(ExitStatus::Success)
+}
@@ -0,0 +1,71 @@
+use anyhow::Result;
+use colored::Colorize;
+use tabled::settings::style::Style;
+use tabled::Table;
+
+use crate::archive_manifest::all_entries;
+use crate::exit_status::ExitStatus;
+use crate::output::{self, ListRecord};
+use crate::say;
+use crate::ui;
+
+pub fn run() -> Result<ExitStatus> {
+    let records: Vec<ListRecord> = all_entries()?
+        .into_iter()
+        .map(|e| ListRecord {
+            archived_exists: e.archived_path.exists(),
+            name: e.name,
+            moved_at: e.moved_at,
+            original_path: e.original_path.display().to_string(),
+            archived_path: e.archived_path.display().to_string(),
+        })
+        .collect();
+
+    if output::is_machine() {
+        output::emit("list", &records)?;
+        return Ok(ExitStatus::Success);
+    }
+
+    say!("{}", "─".repeat(60).dimmed());
+    say!("{}", "Archived binaries".cyan().bold());
+    say!("{}", "─".repeat(60).dimmed());
+
+    if records.is_empty() {
+        say!("{} Nothing has been archived.", "✓".green().bold());
+        return Ok(ExitStatus::Success);
+    }
+
+    let missing = records.iter().filter(|r| !r.archived_exists).count();
+    let rows: Vec<ui::ListRow> = records
+        .into_iter()
+        .map(|r| ui::ListRow {
+            st: if r.archived_exists { "✓" } else { "!" },
+            name: r.name,
+            moved_at: r.moved_at,
+            original_path: r.original_path,
+            archived_path: r.archived_path,
+        })
+        .collect();
+    let count = rows.len();
+
+    let mut table = Table::new(rows);
+    table.with(Style::modern());
+    println!("{}", table);
+
+    say!();
+    say!(
+        "{} {} archived entries. Restore one with {}.",
+        "[i]".blue(),
+        count,
+        "bin-expire restore <name>".cyan()
+    );
+    if missing > 0 {
+        say!(
+            "{} {} entries (!) point at archived files that no longer exist.",
+            "[!]".yellow(),
+            missing
+        );
+    }
+
+    Ok(ExitStatus::Success)
+}
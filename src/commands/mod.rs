@@ -1,3 +1,12 @@
 pub mod archive;
+pub mod caches;
+mod cleanup;
+pub mod config;
+pub mod history;
+pub mod list;
+pub mod pin;
 pub mod restore;
+pub mod runtimes;
 pub mod scan;
+pub mod sweep;
+pub mod toolchains;
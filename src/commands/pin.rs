@@ -0,0 +1,85 @@
+use anyhow::Result;
+use chrono::NaiveDate;
+use colored::Colorize;
+use tabled::settings::style::Style;
+use tabled::Table;
+
+use crate::exit_status::ExitStatus;
+use crate::output::{self, PinRecord};
+use crate::pins::{self, Pin};
+use crate::say;
+use crate::ui;
+
+fn pin_record(pin: &Pin, expired: bool) -> PinRecord {
+    PinRecord {
+        name: pin.name.clone(),
+        reason: pin.reason.clone(),
+        until: pin.until.clone(),
+        pinned_at: pin.pinned_at.clone(),
+        expired,
+    }
+}
+
+pub fn pin(name: &str, reason: Option<String>, until: Option<NaiveDate>) -> Result<ExitStatus> {
+    let pin = pins::add_pin(name, reason, until)?;
+    say!("{} Pinned '{}': {}", "✓".green(), pin.name, pin.describe());
+    output::emit("pin", &[pin_record(&pin, false)])?;
+    Ok(ExitStatus::Success)
+}
+
+pub fn unpin(name: &str) -> Result<ExitStatus> {
+    let pin = pins::remove_pin(name)?;
+    say!("{} Unpinned '{}'", "✓".green(), pin.name);
+    output::emit("unpin", &[pin_record(&pin, pin.is_expired(pins::today()))])?;
+    Ok(ExitStatus::Success)
+}
+
+pub fn list() -> Result<ExitStatus> {
+    let today = pins::today();
+    let records: Vec<PinRecord> = pins::all_pins()?
+        .iter()
+        .map(|p| pin_record(p, p.is_expired(today)))
+        .collect();
+
+    if output::is_machine() {
+        output::emit("pin", &records)?;
+        return Ok(ExitStatus::Success);
+    }
+
+    say!("{}", "─".repeat(60).dimmed());
+    say!("{}", "Pinned binaries".cyan().bold());
+    say!("{}", "─".repeat(60).dimmed());
+
+    if records.is_empty() {
+        say!("{} Nothing is pinned.", "✓".green().bold());
+        return Ok(ExitStatus::Success);
+    }
+
+    let expired = records.iter().filter(|r| r.expired).count();
+    let rows: Vec<ui::PinRow> = records
+        .into_iter()
+        .map(|r| ui::PinRow {
+            st: if r.expired { "!" } else { "P" },
+            name: r.name,
+            reason: r.reason.unwrap_or_default(),
+            until: r.until.unwrap_or_else(|| "-".to_string()),
+            pinned_at: r.pinned_at,
+        })
+        .collect();
+
+    let mut table = Table::new(rows);
+    table.with(Style::modern());
+    println!("{}", table);
+
+    if expired > 0 {
+        say!();
+        say!(
+            "{} {} pin(s) (!) have expired and no longer protect anything. Remove them with {}.",
+            "[!]".yellow(),
+            expired,
+            "bin-expire unpin <name>".cyan()
+        );
+    }
+
+    Ok(ExitStatus::Success)
+}
@@ -1,38 +1,32 @@
 use anyhow::{bail, Result};
 use colored::Colorize;
 
-use crate::archive_manifest::{latest_entry_by_name, take_latest_entry_by_name};
+use crate::archive_manifest::{latest_entry_by_name, take_latest_entry_by_name, ArchiveEntry};
 use crate::archiver::move_file_with_fallback;
+use crate::exit_status::ExitStatus;
 use crate::models::Config;
+use crate::output::{self, RestoreRecord};
+use crate::say;
 
-pub fn run(name: &str, _config: &Config) -> Result<()> {
-    println!("{}", "─".repeat(60).dimmed());
-    println!("{}", "Restoring binary".cyan().bold());
-    println!("{}", "─".repeat(60).dimmed());
+pub fn run(name: &str, _config: &Config) -> Result<ExitStatus> {
+    say!("{}", "─".repeat(60).dimmed());
+    say!("{}", "Restoring".cyan().bold());
+    say!("{}", "─".repeat(60).dimmed());
 
     // Do not mutate the manifest until we've validated and completed the restore.
     let entry = latest_entry_by_name(name)?;
 
-    if !entry.archived_path.exists() {
-        bail!(
-            "Archived file does not exist: {}",
-            entry.archived_path.display()
-        );
-    }
-    if entry.original_path.exists() {
-        bail!(
-            "Destination already exists: {}",
-            entry.original_path.display()
-        );
-    }
-    if let Some(parent) = entry.original_path.parent() {
-        std::fs::create_dir_all(parent)?;
+    if let Err(err) = restore_entry(&entry) {
+        output::emit(
+            "restore",
+            &[restore_record(&entry, "failed", Some(format!("{:#}", err)))],
+        )?;
+        return Err(err);
     }
 
-    move_file_with_fallback(&entry.archived_path, &entry.original_path)?;
-
     // Now that we've restored the file, remove the manifest entry.
     // If this fails, warn but don't fail the restore itself.
+    let mut status = ExitStatus::Success;
     if let Err(err) = take_latest_entry_by_name(name) {
         eprintln!(
             "{} Restored but failed to update manifest for '{}': {:#}",
@@ -40,14 +34,50 @@ pub fn run(name: &str, _config: &Config) -> Result<()> {
             name,
             err
         );
+        status = ExitStatus::ManifestError;
     }
 
-    println!(
+    say!(
         "{} Restored '{}' -> {}",
         "✓".green(),
         entry.name,
         entry.original_path.display()
     );
+    output::emit("restore", &[restore_record(&entry, "restored", None)])?;
+
+    Ok(status)
+}
+
+fn restore_entry(entry: &ArchiveEntry) -> Result<()> {
+    if !entry.archived_path.exists() {
+        bail!(
+            "Archived file does not exist: {}",
+            entry.archived_path.display()
+        );
+    }
+    if entry.original_path.exists() {
+        bail!(
+            "Destination already exists: {}",
+            entry.original_path.display()
+        );
+    }
+    if let Some(parent) = entry.original_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    move_file_with_fallback(&entry.archived_path, &entry.original_path)
+}
 
-    Ok(())
+fn restore_record(
+    entry: &ArchiveEntry,
+    result: &'static str,
+    error: Option<String>,
+) -> RestoreRecord {
+    RestoreRecord {
+        name: entry.name.clone(),
+        result,
+        archived_path: entry.archived_path.display().to_string(),
+        original_path: entry.original_path.display().to_string(),
+        error,
+    }
 }
@@ -0,0 +1,188 @@
+use anyhow::Result;
+use colored::Colorize;
+use std::time::Duration;
+use tabled::settings::style::Style;
+use tabled::Table;
+
+use super::cleanup;
+use crate::analyzer::is_dormant;
+use crate::exit_status::ExitStatus;
+use crate::models::Config;
+use crate::output::{self, RuntimeRecord};
+use crate::pins;
+use crate::projects;
+use crate::runtimes::{self, Runtime};
+use crate::say;
+use crate::threshold;
+use crate::ui;
+
+pub struct RuntimesArgs {
+    /// Explicit threshold from the command line (falls back to the config default).
+    pub threshold: Option<Duration>,
+    /// Move stale, unpinned versions into `archive_path/runtimes`.
+    pub archive: bool,
+    pub dry_run: bool,
+    /// Skip the confirmation prompt.
+    pub yes: bool,
+}
+
+pub fn run(args: RuntimesArgs, config: &Config) -> Result<ExitStatus> {
+    let threshold = threshold::resolve(args.threshold, config)?;
+
+    say!("{}", "─".repeat(60).dimmed());
+    say!("{}", "Language runtimes".cyan().bold());
+    say!("{}", "─".repeat(60).dimmed());
+
+    let dirs = runtimes::install_dirs();
+    if dirs.is_empty() {
+        say!(
+            "{} No nvm, pyenv, rbenv, asdf or mise installs found.",
+            "✓".green().bold()
+        );
+        output::emit::<RuntimeRecord>("runtimes", &[])?;
+        return Ok(ExitStatus::Success);
+    }
+    for (manager, dir) in &dirs {
+        say!("{} {}: {}", "[*]".blue(), manager, dir.display());
+    }
+    say!(
+        "{} Stale after {}",
+        "[*]".blue(),
+        threshold::describe(threshold)
+    );
+
+    let roots = projects::existing_roots(config);
+    let version_pins = runtimes::pins(&roots);
+    let active_pins = pins::active_pins()?;
+
+    let installed = runtimes::installed(config);
+    let mut records: Vec<RuntimeRecord> = Vec::new();
+    let mut stale: Vec<usize> = Vec::new();
+    for runtime in &installed {
+        let name = runtime.name();
+        let (status, kept_by) = if let Some(pin) = active_pins.iter().find(|p| p.name == name) {
+            ("pinned", Some(pin.describe()))
+        } else if let Some(kept) = runtimes::kept_by(runtime, &version_pins, &installed) {
+            (kept.as_str(), Some(kept.to_string()))
+        } else if is_dormant(runtime.last_used, threshold) {
+            stale.push(records.len());
+            ("stale", None)
+        } else {
+            ("ok", None)
+        };
+        records.push(RuntimeRecord {
+            name,
+            manager: runtime.manager,
+            tool: runtime.tool.clone(),
+            version: runtime.version.clone(),
+            status,
+            size: runtime.size,
+            last_used: humantime::format_rfc3339_seconds(runtime.last_used).to_string(),
+            last_used_source: runtime.last_used_source.as_str(),
+            kept_by,
+            path: runtime.path.display().to_string(),
+            action: None,
+            destination: None,
+            error: None,
+        });
+    }
+
+    print_table(&installed, &records);
+
+    let stale_size: u64 = stale.iter().map(|&i| installed[i].size).sum();
+    say!();
+    if installed.is_empty() {
+        say!("{} No runtime versions installed.", "✓".green().bold());
+    } else if stale.is_empty() {
+        say!(
+            "{} {} runtime versions ({}); none are stale and unpinned.",
+            "✓".green().bold(),
+            installed.len(),
+            ui::format_bytes(installed.iter().map(|r| r.size).sum())
+        );
+    } else if !args.archive {
+        say!(
+            "{} {} of {} runtime versions are stale and unpinned ({}). Move them aside with {}.",
+            "[i]".blue(),
+            stale.len(),
+            installed.len(),
+            ui::format_bytes(stale_size),
+            "bin-expire runtimes --archive".cyan()
+        );
+    }
+
+    if stale.is_empty() || !args.archive {
+        output::emit("runtimes", &records)?;
+        return Ok(ExitStatus::Success);
+    }
+
+    let items: Vec<cleanup::Item> = stale
+        .iter()
+        .map(|&i| {
+            let runtime = &installed[i];
+            cleanup::Item {
+                name: records[i].name.clone(),
+                path: runtime.path.clone(),
+                size: runtime.size,
+                archive_dir: config
+                    .archive_path
+                    .join("runtimes")
+                    .join(runtime.manager)
+                    .join(&runtime.tool),
+            }
+        })
+        .collect();
+    let (outcomes, status) = cleanup::run(
+        &items,
+        &cleanup::Options {
+            remove: false,
+            dry_run: args.dry_run,
+            yes: args.yes,
+            noun: "runtime versions",
+            archive_path: &config.archive_path,
+        },
+    )?;
+    let archived = outcomes.iter().any(|o| o.action == Some("archived"));
+    for (&i, outcome) in stale.iter().zip(outcomes) {
+        records[i].action = outcome.action;
+        records[i].destination = outcome.destination;
+        records[i].error = outcome.error;
+    }
+    if archived {
+        say!(
+            "{} asdf and mise keep shims for archived versions until {} / {}.",
+            "[i]".blue(),
+            "asdf reshim".cyan(),
+            "mise reshim".cyan()
+        );
+    }
+    output::emit("runtimes", &records)?;
+    status
+}
+
+fn print_table(installed: &[Runtime], records: &[RuntimeRecord]) {
+    if output::is_machine() || records.is_empty() {
+        return;
+    }
+    let rows: Vec<ui::RuntimeRow> = installed
+        .iter()
+        .zip(records)
+        .map(|(runtime, record)| ui::RuntimeRow {
+            st: match record.status {
+                "stale" => "✗",
+                "ok" => "✓",
+                "pinned" => "P",
+                _ => "R",
+            },
+            manager: runtime.manager,
+            tool: runtime.tool.clone(),
+            version: runtime.version.clone(),
+            size: ui::format_bytes(runtime.size),
+            last_used: ui::format_date_short(Some(runtime.last_used)),
+            kept_by: record.kept_by.clone().unwrap_or_default(),
+        })
+        .collect();
+    let mut table = Table::new(rows);
+    table.with(Style::modern());
+    println!("{}", table);
+}
@@ -1,44 +1,100 @@
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
 use colored::Colorize;
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
-use tabled::settings::style::Style;
-use tabled::Table;
+use std::time::Duration;
 
 use crate::analyzer::is_dormant;
-use crate::fs_scanner::scan_directory;
-use crate::models::Config;
+use crate::eligibility::{Policy, Tracker};
+use crate::exit_status::{ErrorKind, ExitStatus};
+use crate::fs_scanner::{expand_dir_spec, scan_directories};
+use crate::history::{self, ScanEntry};
+use crate::ignore::{IgnoreRules, Ignored};
+use crate::inspect;
+use crate::models::{BinaryInfo, BinaryStatus, Config};
+use crate::output::{self, ScanRecord};
+use crate::pins::{self, Pin};
+use crate::projects::{self, ProjectUse};
+use crate::references::{Reference, References};
+use crate::say;
+use crate::threshold;
 use crate::ui;
+use crate::ui::table::{self, Column, GroupBy, SortKey};
 
-pub fn run(
-    dir: Option<String>,
-    days: Option<i64>,
-    verbose: bool,
-    only_stale: bool,
-    hide_ok: bool,
-    hide_stub: bool,
-    config: &Config,
-) -> Result<()> {
-    let days = days.unwrap_or(config.default_threshold_days);
-    let hide_ok = only_stale || hide_ok;
-    let hide_stub = only_stale || hide_stub;
-
-    let dirs: Vec<PathBuf> = match dir {
-        Some(path_str) => vec![ui::expand_tilde(&path_str)],
-        None => vec![
-            ui::expand_tilde("~/.cargo/bin"),
-            ui::expand_tilde("~/go/bin"),
-        ],
+pub struct ScanArgs {
+    pub dir: Option<String>,
+    /// Explicit threshold from the command line (falls back to the config default).
+    pub threshold: Option<Duration>,
+    pub verbose: bool,
+    pub only_stale: bool,
+    pub hide_ok: bool,
+    pub hide_stub: bool,
+    /// Exit with `ExitStatus::StaleFound` if anything stale or broken was found (CI gates).
+    pub fail_if_stale: bool,
+    /// Look for stale binaries in the build and CI files under `project_roots`.
+    pub projects: bool,
+    /// Save the results to the scan history (`history` / `diff`).
+    pub save_history: bool,
+    /// Read new or changed binaries for their kind, hash and build info (slow; see
+    /// `use_cache`). Without it only cached details are shown.
+    pub inspect: bool,
+    /// Reuse results from scan-cache.json for files whose dev/inode/size/ctime are unchanged.
+    pub use_cache: bool,
+    pub sort: SortKey,
+    pub reverse: bool,
+    pub group_by: Option<GroupBy>,
+    /// Overrides the default (or verbose) column set.
+    pub columns: Option<Vec<Column>>,
+}
+
+pub fn run(args: ScanArgs, config: &Config) -> Result<ExitStatus> {
+    let threshold = threshold::resolve(args.threshold, config)?;
+    if args.projects && config.project_roots.is_empty() {
+        return Err(anyhow::anyhow!(
+            "--projects needs project_roots in config.toml, e.g. project_roots = [\"~/src\"]"
+        ))
+        .context(ErrorKind::Config);
+    }
+    let ignore_rules = IgnoreRules::from_config(config)?;
+    let today = pins::today();
+    let (active_pins, expired_pins): (Vec<Pin>, Vec<Pin>) = pins::all_pins()?
+        .into_iter()
+        .partition(|p| !p.is_expired(today));
+    let pin_for = |name: &str| active_pins.iter().find(|p| p.name == name);
+    let references = References::collect();
+    // Each saved scan counts towards the stale streaks `archive` checks.
+    let policy = Policy::from_config(config).context(ErrorKind::Config)?;
+    let mut tracker = if args.save_history && policy.is_enabled() {
+        Some(Tracker::load(policy)?)
+    } else {
+        None
+    };
+    // Stale binaries by path: (stale runs so far, confirmed for archiving).
+    let mut streaks: HashMap<PathBuf, (u32, bool)> = HashMap::new();
+    let verbose = args.verbose;
+    let hide_ok = args.only_stale || args.hide_ok;
+    let hide_stub = args.only_stale || args.hide_stub;
+
+    let dirs: Vec<PathBuf> = match args.dir {
+        Some(spec) => expand_dir_spec(&spec),
+        None => config
+            .scan_dirs
+            .iter()
+            .flat_map(|d| expand_dir_spec(d))
+            .collect(),
     };
 
     let mut binaries = Vec::new();
-    let mut any_dir = false;
+    let mut ignored: Vec<Ignored> = Vec::new();
+    let mut existing: Vec<PathBuf> = Vec::new();
+    let mut scanned_dirs: Vec<PathBuf> = Vec::new();
 
     #[cfg(windows)]
     let scan_start = std::time::SystemTime::now();
 
-    println!("{}", "─".repeat(60).dimmed());
-    println!("{}", "Scanning for stale binaries".cyan().bold());
-    println!("{}", "─".repeat(60).dimmed());
+    say!("{}", "─".repeat(60).dimmed());
+    say!("{}", "Scanning for stale binaries".cyan().bold());
+    say!("{}", "─".repeat(60).dimmed());
 
     for path in dirs {
         if !path.exists() {
@@ -49,15 +105,23 @@ pub fn run(
             );
             continue;
         }
-        any_dir = true;
+        // History compares paths across runs, so store them independent of the cwd.
+        scanned_dirs.push(std::path::absolute(&path).unwrap_or_else(|_| path.clone()));
         ui::print_mount_option_warning(&path);
-        println!(
-            "{} {} for files > {} days old",
+        say!(
+            "{} {} for files older than {}",
             "[*]".blue(),
             path.display(),
-            days
+            threshold::describe(threshold)
         );
-        binaries.extend(scan_directory(&path, config.windows_use_access_time));
+        existing.push(path);
+    }
+
+    let found = scan_directories(&existing, config);
+    for (path, found) in existing.iter().zip(found) {
+        let (kept, skipped) = ignore_rules.for_dir(path)?.partition(found);
+        binaries.extend(kept);
+        ignored.extend(skipped);
     }
 
     #[cfg(windows)]
@@ -73,149 +137,431 @@ pub fn run(
         );
     }
 
-    if !any_dir {
-        eprintln!("{} No valid directories found to scan.", "[ERROR]".red());
-        return Ok(());
+    if existing.is_empty() {
+        bail!("No valid directories found to scan");
     }
 
-    println!();
+    if args.inspect || args.use_cache {
+        inspect::annotate(
+            &mut binaries,
+            args.inspect,
+            args.use_cache,
+            config.scan_threads,
+        );
+    }
+
+    say!();
 
-    let mut default_rows: Vec<ui::DefaultRow> = Vec::new();
-    let mut verbose_rows: Vec<ui::VerboseRow> = Vec::new();
+    let mut rows: Vec<(BinaryInfo, BinaryStatus)> = Vec::new();
     let mut stale_count: u64 = 0;
     let mut stale_total_bytes: u64 = 0;
     let mut ok_count: u64 = 0;
     let mut stub_count: u64 = 0;
-
-    binaries.sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
+    let mut broken_count: u64 = 0;
+    let mut pinned_count: u64 = 0;
+    let mut referenced_count: u64 = 0;
+    let mut expired: Vec<&Pin> = Vec::new();
+    let mut history_entries: Vec<ScanEntry> = Vec::new();
 
     for bin in binaries {
-        if config.ignored_bins.iter().any(|b| b == &bin.name) {
-            continue;
-        }
         let is_probable_stub = bin.size == 0
             && bin
                 .path
                 .extension()
                 .is_some_and(|ext| ext.to_string_lossy().eq_ignore_ascii_case("exe"));
 
-        let is_stale = !is_probable_stub && is_dormant(bin.last_used, days);
-        if is_stale {
-            stale_count += 1;
-            stale_total_bytes = stale_total_bytes.saturating_add(bin.size);
+        let status = if bin.is_broken_link {
+            BinaryStatus::Broken
+        } else if is_probable_stub {
+            BinaryStatus::Stub
+        } else if pin_for(&bin.name).is_some() {
+            BinaryStatus::Pinned
+        } else if is_dormant(bin.last_used, threshold) {
+            // Rarely-run tools started by automation look stale by atime alone.
+            match references.find(&bin) {
+                Some(_) => BinaryStatus::Referenced,
+                None => BinaryStatus::Stale,
+            }
+        } else {
+            BinaryStatus::Ok
+        };
+
+        match status {
+            BinaryStatus::Stale => {
+                stale_count += 1;
+                stale_total_bytes = stale_total_bytes.saturating_add(bin.size);
+            }
+            BinaryStatus::Ok => ok_count += 1,
+            BinaryStatus::Stub => stub_count += 1,
+            BinaryStatus::Broken => broken_count += 1,
+            BinaryStatus::Pinned => pinned_count += 1,
+            BinaryStatus::Referenced => referenced_count += 1,
+        }
+
+        if let Some(tracker) = tracker.as_mut() {
+            match status {
+                BinaryStatus::Stale => {
+                    let streak = tracker.saw_stale(&bin.name, &bin.path).clone();
+                    let confirmed = tracker.is_eligible(&streak);
+                    streaks.insert(bin.path.clone(), (streak.stale_scans, confirmed));
+                }
+                BinaryStatus::Ok => tracker.saw_used(&bin.path),
+                _ => {}
+            }
+        }
+
+        if args.save_history {
+            history_entries.push(ScanEntry {
+                name: bin.name.clone(),
+                path: std::path::absolute(&bin.path).unwrap_or_else(|_| bin.path.clone()),
+                size: bin.size,
+                last_used: humantime::format_rfc3339_seconds(bin.last_used).to_string(),
+                status: status.as_str().to_string(),
+            });
         }
 
-        if is_probable_stub {
-            stub_count += 1;
-        } else if !is_stale {
-            ok_count += 1;
+        if let Some(pin) = expired_pins.iter().find(|p| p.name == bin.name) {
+            if status == BinaryStatus::Stale {
+                expired.push(pin);
+            }
         }
 
         // Visibility:
-        // - default: stale + stubs
+        // - default: stale + broken + stubs + pinned + referenced
         // - verbose: also includes OK
+        // - --only-stale hides pinned and referenced rows too
         // - flags can hide OK/stubs regardless of verbosity
-        let mut is_visible = is_stale || is_probable_stub || verbose;
-        if hide_stub && is_probable_stub {
-            is_visible = false;
-        }
-        if hide_ok && !is_probable_stub && !is_stale {
-            is_visible = false;
-        }
+        // - machine-readable output: every binary (filters still apply)
+        let is_visible = match status {
+            BinaryStatus::Stale | BinaryStatus::Broken => true,
+            BinaryStatus::Stub => !hide_stub,
+            BinaryStatus::Pinned | BinaryStatus::Referenced => !args.only_stale,
+            BinaryStatus::Ok => (verbose || output::is_machine()) && !hide_ok,
+        };
         if !is_visible {
             continue;
         }
 
-        // Keep status glyphs short for stable table alignment.
-        let status = if is_probable_stub {
-            "·" // stub
-        } else if is_stale {
-            "✗"
-        } else {
-            "✓"
-        };
+        rows.push((bin, status));
+    }
 
-        let accessed_str = ui::format_date_short(bin.accessed);
-        let modified_str = ui::format_date_short(bin.modified);
+    table::sort_rows(&mut rows, args.sort, args.reverse);
 
-        if verbose {
-            let src = match bin.last_used_source {
-                crate::models::LastUsedSource::Accessed => "A",
-                crate::models::LastUsedSource::Modified => "M",
-                crate::models::LastUsedSource::Unknown => "?",
-            };
-            verbose_rows.push(ui::VerboseRow {
-                st: status,
-                src,
-                name: bin.name,
-                size: ui::format_bytes(bin.size),
-                accessed: accessed_str,
-                modified: modified_str,
-                path: bin.path.display().to_string(),
-            });
-        } else {
-            default_rows.push(ui::DefaultRow {
-                st: status,
-                name: bin.name,
-                size: ui::format_bytes(bin.size),
-                accessed: accessed_str,
-                modified: modified_str,
-            });
+    if args.save_history {
+        // History is a convenience; never fail the scan over it.
+        if let Err(err) = history::record_scan(
+            &threshold::to_arg(threshold),
+            scanned_dirs,
+            config.max_depth,
+            history_entries,
+        ) {
+            eprintln!("{} Failed to save scan history: {:#}", "[!]".yellow(), err);
         }
     }
 
-    // Clean, minimal explanation (short + visible where it matters)
-    println!(
-        "{}",
-        "accessed(atime)=last read/execute (best-effort on Windows), modified(mtime)=last content change".dimmed()
-    );
-    println!("{}", "│".cyan());
+    if let Some(tracker) = tracker {
+        if let Err(err) = tracker.save() {
+            eprintln!("{} Failed to save stale streaks: {:#}", "[!]".yellow(), err);
+        }
+    }
 
-    if verbose {
-        if !verbose_rows.is_empty() {
-            let mut table = Table::new(verbose_rows);
-            table.with(Style::modern());
-            println!("{}", table);
-        } else {
-            println!("│ ✓ No matching binaries found.");
+    let project_uses: HashMap<String, Vec<ProjectUse>> = if args.projects {
+        let roots = projects::existing_roots(config);
+        say!(
+            "{} Looking for stale binaries in project files under {}",
+            "[*]".blue(),
+            config.project_roots.join(", ")
+        );
+        let stale: HashSet<&str> = rows
+            .iter()
+            .filter(|(_, status)| *status == BinaryStatus::Stale)
+            .map(|(bin, _)| bin.name.as_str())
+            .collect();
+        projects::find_uses(&roots, &stale)
+    } else {
+        HashMap::new()
+    };
+
+    ignored.sort_by_key(|i| i.bin.name.to_lowercase());
+
+    if output::is_machine() {
+        let mut records: Vec<ScanRecord> = rows
+            .iter()
+            .map(|(bin, status)| match status {
+                BinaryStatus::Pinned => match pin_for(&bin.name) {
+                    Some(pin) => ScanRecord::pinned(bin, pin),
+                    None => ScanRecord::new(bin, *status),
+                },
+                BinaryStatus::Referenced => match references.find(bin) {
+                    Some(reference) => ScanRecord::referenced(bin, reference),
+                    None => ScanRecord::new(bin, *status),
+                },
+                _ => ScanRecord {
+                    used_by_projects: project_uses.get(&bin.name).map(|uses| {
+                        uses.iter()
+                            .map(ProjectUse::to_string)
+                            .collect::<Vec<_>>()
+                            .join("; ")
+                    }),
+                    stale_scans: streaks.get(&bin.path).map(|(scans, _)| *scans),
+                    confirmed_stale: streaks.get(&bin.path).map(|(_, confirmed)| *confirmed),
+                    ..ScanRecord::new(bin, *status)
+                },
+            })
+            .collect();
+        if verbose {
+            records.extend(ignored.iter().map(ScanRecord::ignored));
         }
-    } else if !default_rows.is_empty() {
-        let mut table = Table::new(default_rows);
-        table.with(Style::markdown());
-        println!("{}", table);
+        output::emit("scan", &records)?;
     } else {
-        println!("│ ✓ No stale binaries found.");
+        let columns: Vec<Column> = match &args.columns {
+            Some(columns) => columns.clone(),
+            None if verbose => Column::VERBOSE.to_vec(),
+            None => Column::DEFAULT.to_vec(),
+        };
+        print_tables(&rows, &columns, verbose, args.group_by);
+        let pinned: Vec<(&str, &Pin)> = rows
+            .iter()
+            .filter(|(_, status)| *status == BinaryStatus::Pinned)
+            .filter_map(|(bin, _)| Some((bin.name.as_str(), pin_for(&bin.name)?)))
+            .collect();
+        print_pinned(&pinned);
+        let referenced: Vec<(&str, &Reference)> = rows
+            .iter()
+            .filter(|(_, status)| *status == BinaryStatus::Referenced)
+            .filter_map(|(bin, _)| Some((bin.name.as_str(), references.find(bin)?)))
+            .collect();
+        print_referenced(&referenced);
+        print_project_uses(&rows, &project_uses);
+        if verbose {
+            print_ignored(&ignored);
+        }
     }
 
-    println!("{}", "╰────".cyan());
-
     // Summary Section
-    println!();
+    say!();
     if stale_count > 0 {
-        println!(
+        say!(
             "{} Summary: {} stale items | {} total wastage",
             ">>>".bold(),
             stale_count.to_string().red().bold(),
             ui::format_bytes(stale_total_bytes).bold()
         );
-        println!();
-        println!(
+        say!();
+        say!(
             "Run {} to move these to {}.",
-            format!("bin-expire archive --days {}", days)
-                .cyan()
-                .underline(),
+            format!(
+                "bin-expire archive --older-than {}",
+                threshold::to_arg(threshold)
+            )
+            .cyan()
+            .underline(),
             config.archive_path.display().to_string().cyan()
         );
-
-        ui::print_scan_status_info(days, ok_count, stub_count, stale_count, hide_ok, hide_stub);
+        let unconfirmed = streaks.values().filter(|(_, confirmed)| !confirmed).count();
+        if unconfirmed > 0 {
+            say!(
+                "{} {} of them not confirmed yet; archive waits until a binary is stale in {}.",
+                "[i]".blue(),
+                unconfirmed,
+                policy.describe()
+            );
+        }
     } else {
-        println!(
+        say!(
             "{} No stale binaries found. Your system is clean!",
             "✓".green().bold()
         );
+    }
+    if broken_count > 0 {
+        say!(
+            "{} {} broken symlink(s) (!) point at missing targets.",
+            "[!]".yellow(),
+            broken_count
+        );
+    }
+
+    ui::print_scan_status_info(
+        threshold,
+        ui::StatusCounts {
+            ok: ok_count,
+            stub: stub_count,
+            stale: stale_count,
+            broken: broken_count,
+            pinned: pinned_count,
+            referenced: referenced_count,
+        },
+        hide_ok,
+        hide_stub,
+    );
+
+    for pin in &expired {
+        say!(
+            "{} Pin for '{}' expired on {}; it is a candidate again.",
+            "[!]".yellow(),
+            pin.name,
+            pin.until.as_deref().unwrap_or("?")
+        );
+    }
+
+    if !verbose && !ignored.is_empty() {
+        say!(
+            "{}",
+            format!(
+                "{} binaries ignored by ignore rules (use --verbose to list them).",
+                ignored.len()
+            )
+            .dimmed()
+        );
+    }
+
+    if args.fail_if_stale && stale_count + broken_count > 0 {
+        return Ok(ExitStatus::StaleFound);
+    }
+
+    Ok(ExitStatus::Success)
+}
+
+fn print_pinned(pinned: &[(&str, &Pin)]) {
+    if pinned.is_empty() {
+        return;
+    }
+    println!();
+    println!("{} ({}):", "Pinned".bold(), pinned.len());
+    let width = pinned.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+    for (name, pin) in pinned {
+        println!(
+            "  {:<width$}  {}",
+            name,
+            pin.describe().dimmed(),
+            width = width
+        );
+    }
+}
+
+fn print_referenced(referenced: &[(&str, &Reference)]) {
+    if referenced.is_empty() {
+        return;
+    }
+    println!();
+    println!("{} ({}):", "Referenced".bold(), referenced.len());
+    let width = referenced
+        .iter()
+        .map(|(name, _)| name.len())
+        .max()
+        .unwrap_or(0);
+    for (name, reference) in referenced {
+        println!(
+            "  {:<width$}  {}",
+            name,
+            reference.to_string().dimmed(),
+            width = width
+        );
+    }
+}
+
+fn print_project_uses(
+    rows: &[(BinaryInfo, BinaryStatus)],
+    project_uses: &HashMap<String, Vec<ProjectUse>>,
+) {
+    let used: Vec<(&str, &Vec<ProjectUse>)> = rows
+        .iter()
+        .filter_map(|(bin, _)| Some((bin.name.as_str(), project_uses.get(&bin.name)?)))
+        .collect();
+    if used.is_empty() {
+        return;
+    }
+    println!();
+    println!(
+        "{} ({}):",
+        "Stale but still used by projects".bold(),
+        used.len()
+    );
+    let width = used.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+    for (name, uses) in used {
+        for (i, u) in uses.iter().enumerate() {
+            let label = if i == 0 { name } else { "" };
+            println!(
+                "  {:<width$}  {}",
+                label,
+                u.to_string().dimmed(),
+                width = width
+            );
+        }
+    }
+}
+
+fn print_ignored(ignored: &[Ignored]) {
+    if ignored.is_empty() {
+        return;
+    }
+    println!();
+    println!("{} ({}):", "Ignored".bold(), ignored.len());
+    let width = ignored.iter().map(|i| i.bin.name.len()).max().unwrap_or(0);
+    for i in ignored {
+        println!(
+            "  {:<width$}  {}",
+            i.bin.name,
+            i.rule.dimmed(),
+            width = width
+        );
+    }
+}
+
+fn print_tables(
+    rows: &[(BinaryInfo, BinaryStatus)],
+    columns: &[Column],
+    verbose: bool,
+    group_by: Option<GroupBy>,
+) {
+    // Clean, minimal explanation (short + visible where it matters)
+    println!(
+        "{}",
+        "accessed(atime)=last read/execute (best-effort on Windows), modified(mtime)=last content change".dimmed()
+    );
+    println!("{}", "│".cyan());
+
+    if rows.is_empty() {
+        if verbose {
+            println!("│ ✓ No matching binaries found.");
+        } else {
+            println!("│ ✓ No stale binaries found.");
+        }
+    } else if let Some(group_by) = group_by {
+        // Keep rows in their sorted order within each group.
+        let mut groups: Vec<(String, Vec<(BinaryInfo, BinaryStatus)>)> = Vec::new();
+        for (bin, status) in rows {
+            let label = table::group_label(group_by, bin, *status);
+            match groups.iter_mut().find(|(l, _)| *l == label) {
+                Some((_, members)) => members.push((bin.clone(), *status)),
+                None => groups.push((label, vec![(bin.clone(), *status)])),
+            }
+        }
+        if group_by == GroupBy::Status {
+            groups.sort_by_key(|(_, members)| table::status_rank(members[0].1));
+        }
 
-        ui::print_scan_status_info(days, ok_count, stub_count, stale_count, hide_ok, hide_stub);
+        for (label, members) in &groups {
+            let total: u64 = members
+                .iter()
+                .fold(0u64, |acc, (bin, _)| acc.saturating_add(bin.size));
+            println!(
+                "{} {} {}",
+                "│".cyan(),
+                label.bold(),
+                format!(
+                    "({} binaries, {} total)",
+                    members.len(),
+                    ui::format_bytes(total)
+                )
+                .dimmed()
+            );
+            println!("{}", table::render(members, columns, verbose));
+            println!("{}", "│".cyan());
+        }
+    } else {
+        println!("{}", table::render(rows, columns, verbose));
     }
 
-    Ok(())
+    println!("{}", "╰────".cyan());
 }
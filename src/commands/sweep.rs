@@ -0,0 +1,230 @@
+use anyhow::{bail, Result};
+use colored::Colorize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tabled::settings::style::Style;
+use tabled::Table;
+
+use super::cleanup;
+use crate::analyzer::{dir_size, is_dormant};
+use crate::exit_status::ExitStatus;
+use crate::models::Config;
+use crate::output::{self, SweepRecord};
+use crate::pool;
+use crate::projects::{self, BuildDir};
+use crate::say;
+use crate::threshold;
+use crate::ui::{self, expand_tilde};
+
+pub struct SweepArgs {
+    /// Directories to search instead of `project_roots`.
+    pub dirs: Vec<String>,
+    /// Explicit threshold from the command line (falls back to the config default).
+    pub threshold: Option<Duration>,
+    /// Move build directories of stale projects into `archive_path/sweep`.
+    pub archive: bool,
+    /// Delete them instead.
+    pub remove: bool,
+    pub dry_run: bool,
+    /// Skip the confirmation prompt.
+    pub yes: bool,
+}
+
+/// A build directory with its size and when its project last changed.
+struct Measured {
+    dir: BuildDir,
+    size: u64,
+    touched: SystemTime,
+}
+
+pub fn run(args: SweepArgs, config: &Config) -> Result<ExitStatus> {
+    let threshold = threshold::resolve(args.threshold, config)?;
+    let roots: Vec<PathBuf> = if args.dirs.is_empty() {
+        projects::existing_roots(config)
+    } else {
+        let mut roots: Vec<PathBuf> = args.dirs.iter().map(|d| expand_tilde(d)).collect();
+        roots.retain(|root| {
+            if !root.is_dir() {
+                eprintln!(
+                    "{} Directory {} does not exist. Skipping.",
+                    "[!]".yellow(),
+                    root.display()
+                );
+            }
+            root.is_dir()
+        });
+        roots
+    };
+    if roots.is_empty() {
+        bail!("Nothing to sweep: pass --dir or set project_roots in config.toml");
+    }
+
+    say!("{}", "─".repeat(60).dimmed());
+    say!("{}", "Build output".cyan().bold());
+    say!("{}", "─".repeat(60).dimmed());
+    for root in &roots {
+        say!("{} {}", "[*]".blue(), root.display());
+    }
+    say!(
+        "{} Projects untouched for {} are stale",
+        "[*]".blue(),
+        threshold::describe(threshold)
+    );
+
+    let found = projects::find_build_dirs(&roots);
+    let measured: Vec<Measured> = pool::map(&found, config.scan_threads, |dir| Measured {
+        dir: dir.clone(),
+        size: dir_size(&dir.path),
+        touched: projects::last_touched(&dir.project),
+    });
+
+    let mut records: Vec<SweepRecord> = Vec::new();
+    let mut stale: Vec<usize> = Vec::new();
+    for m in &measured {
+        let status = if is_dormant(m.touched, threshold) {
+            stale.push(records.len());
+            "stale"
+        } else {
+            "ok"
+        };
+        records.push(SweepRecord {
+            name: name_of(&m.dir),
+            kind: m.dir.kind.as_str(),
+            status,
+            size: m.size,
+            project: m.dir.project.display().to_string(),
+            project_touched: humantime::format_rfc3339_seconds(m.touched).to_string(),
+            path: m.dir.path.display().to_string(),
+            action: None,
+            destination: None,
+            error: None,
+        });
+    }
+
+    print_table(&measured, &records);
+
+    let stale_size: u64 = stale.iter().map(|&i| measured[i].size).sum();
+    say!();
+    if measured.is_empty() {
+        say!(
+            "{} No Cargo target/ or node_modules/ directories found.",
+            "✓".green().bold()
+        );
+    } else if stale.is_empty() {
+        say!(
+            "{} {} build directories ({}); every project was touched recently.",
+            "✓".green().bold(),
+            measured.len(),
+            ui::format_bytes(measured.iter().map(|m| m.size).sum())
+        );
+    } else if !args.archive && !args.remove {
+        say!(
+            "{} {} of {} build directories belong to stale projects ({}). Reclaim them with {} or {}.",
+            "[i]".blue(),
+            stale.len(),
+            measured.len(),
+            ui::format_bytes(stale_size),
+            "bin-expire sweep --archive".cyan(),
+            "--remove".cyan()
+        );
+    }
+
+    if stale.is_empty() || (!args.archive && !args.remove) {
+        output::emit("sweep", &records)?;
+        return Ok(ExitStatus::Success);
+    }
+
+    let items: Vec<cleanup::Item> = stale
+        .iter()
+        .map(|&i| {
+            let dir = &measured[i].dir;
+            cleanup::Item {
+                name: records[i].name.clone(),
+                path: dir.path.clone(),
+                size: measured[i].size,
+                archive_dir: config.archive_path.join("sweep").join(project_of(dir)),
+            }
+        })
+        .collect();
+    if args.archive {
+        // Two roots can hold the same relative project path; `restore` couldn't tell them apart.
+        let mut seen: HashMap<&str, &Path> = HashMap::new();
+        for item in &items {
+            if let Some(other) = seen.insert(&item.name, &item.path) {
+                bail!(
+                    "Both {} and {} would be archived as '{}'; sweep those roots one at a time with --dir",
+                    other.display(),
+                    item.path.display(),
+                    item.name
+                );
+            }
+        }
+    }
+    let (outcomes, status) = cleanup::run(
+        &items,
+        &cleanup::Options {
+            remove: args.remove,
+            dry_run: args.dry_run,
+            yes: args.yes,
+            noun: "build directories",
+            archive_path: &config.archive_path,
+        },
+    )?;
+    for (&i, outcome) in stale.iter().zip(outcomes) {
+        records[i].action = outcome.action;
+        records[i].destination = outcome.destination;
+        records[i].error = outcome.error;
+    }
+    output::emit("sweep", &records)?;
+    status
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+/// The project's path below its sweep root, e.g. "work/my-app", so same-named projects in
+/// different directories stay apart. A root that is itself a project goes by its own name.
+fn project_of(dir: &BuildDir) -> PathBuf {
+    match dir.project.strip_prefix(&dir.root) {
+        Ok(rel) if !rel.as_os_str().is_empty() => rel.to_path_buf(),
+        _ => PathBuf::from(file_name(&dir.project)),
+    }
+}
+
+/// "<project>/target": what `restore` takes.
+fn name_of(dir: &BuildDir) -> String {
+    let project = project_of(dir)
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().to_string())
+        .collect::<Vec<_>>()
+        .join("/");
+    format!("{}/{}", project, file_name(&dir.path))
+}
+
+fn print_table(measured: &[Measured], records: &[SweepRecord]) {
+    if output::is_machine() || records.is_empty() {
+        return;
+    }
+    let rows: Vec<ui::SweepRow> = measured
+        .iter()
+        .zip(records)
+        .map(|(m, record)| ui::SweepRow {
+            st: if record.status == "stale" {
+                "✗"
+            } else {
+                "✓"
+            },
+            kind: record.kind,
+            path: record.path.clone(),
+            size: ui::format_bytes(m.size),
+            touched: ui::format_date_short(Some(m.touched)),
+        })
+        .collect();
+    let mut table = Table::new(rows);
+    table.with(Style::modern());
+    println!("{}", table);
+}
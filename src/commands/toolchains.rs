@@ -0,0 +1,172 @@
+use anyhow::{anyhow, bail, Context, Result};
+use colored::Colorize;
+use std::time::Duration;
+use tabled::settings::style::Style;
+use tabled::Table;
+
+use super::cleanup;
+use crate::analyzer::is_dormant;
+use crate::exit_status::{ErrorKind, ExitStatus};
+use crate::models::Config;
+use crate::output::{self, ToolchainRecord};
+use crate::pins;
+use crate::projects;
+use crate::say;
+use crate::threshold;
+use crate::toolchains::{self, Settings, Toolchain};
+use crate::ui;
+
+pub struct ToolchainsArgs {
+    /// Explicit threshold from the command line (falls back to the config default).
+    pub threshold: Option<Duration>,
+    /// Move stale, unreferenced toolchains into `archive_path/toolchains`.
+    pub archive: bool,
+    /// Delete them instead.
+    pub remove: bool,
+    pub dry_run: bool,
+    /// Skip the confirmation prompt.
+    pub yes: bool,
+}
+
+pub fn run(args: ToolchainsArgs, config: &Config) -> Result<ExitStatus> {
+    let threshold = threshold::resolve(args.threshold, config)?;
+    let home = toolchains::rustup_home()
+        .ok_or_else(|| anyhow!("Could not determine the rustup home; set RUSTUP_HOME"))?;
+    if !home.join("toolchains").is_dir() {
+        bail!(
+            "No rustup toolchains found in {} (set RUSTUP_HOME if rustup lives elsewhere)",
+            home.display()
+        );
+    }
+
+    say!("{}", "─".repeat(60).dimmed());
+    say!("{}", "Rust toolchains".cyan().bold());
+    say!("{}", "─".repeat(60).dimmed());
+    say!(
+        "{} {} (stale after {})",
+        "[*]".blue(),
+        home.join("toolchains").display(),
+        threshold::describe(threshold)
+    );
+
+    let settings = Settings::load(&home).context(ErrorKind::Config)?;
+    let roots = projects::existing_roots(config);
+    let files = projects::find_toolchain_files(&roots);
+    let active_pins = pins::active_pins()?;
+
+    let installed = toolchains::installed(&home, config)?;
+    let mut records: Vec<ToolchainRecord> = Vec::new();
+    let mut stale: Vec<usize> = Vec::new();
+    for toolchain in &installed {
+        let (status, kept_by) = if toolchain.linked {
+            ("linked", None)
+        } else if let Some(pin) = active_pins.iter().find(|p| p.name == toolchain.name) {
+            ("pinned", Some(pin.describe()))
+        } else if let Some(used) = toolchains::used_by(&toolchain.name, &settings, &files) {
+            (used.as_str(), Some(used.to_string()))
+        } else if is_dormant(toolchain.last_used, threshold) {
+            stale.push(records.len());
+            ("stale", None)
+        } else {
+            ("ok", None)
+        };
+        records.push(ToolchainRecord {
+            name: toolchain.name.clone(),
+            status,
+            size: toolchain.size,
+            last_used: humantime::format_rfc3339_seconds(toolchain.last_used).to_string(),
+            last_used_source: toolchain.last_used_source.as_str(),
+            kept_by,
+            path: toolchain.path.display().to_string(),
+            action: None,
+            destination: None,
+            error: None,
+        });
+    }
+
+    print_table(&installed, &records);
+
+    let stale_size: u64 = stale.iter().map(|&i| installed[i].size).sum();
+    say!();
+    if installed.is_empty() {
+        say!("{} No toolchains installed.", "✓".green().bold());
+    } else if stale.is_empty() {
+        say!(
+            "{} {} toolchains ({}); none are stale and unreferenced.",
+            "✓".green().bold(),
+            installed.len(),
+            ui::format_bytes(installed.iter().map(|t| t.size).sum())
+        );
+    } else if !args.archive && !args.remove {
+        say!(
+            "{} {} of {} toolchains are stale and unreferenced ({}). Move them aside with {}.",
+            "[i]".blue(),
+            stale.len(),
+            installed.len(),
+            ui::format_bytes(stale_size),
+            "bin-expire toolchains --archive".cyan()
+        );
+    }
+
+    if stale.is_empty() || (!args.archive && !args.remove) {
+        output::emit("toolchains", &records)?;
+        return Ok(ExitStatus::Success);
+    }
+
+    let items: Vec<cleanup::Item> = stale
+        .iter()
+        .map(|&i| cleanup::Item {
+            name: installed[i].name.clone(),
+            path: installed[i].path.clone(),
+            size: installed[i].size,
+            archive_dir: config.archive_path.join("toolchains"),
+        })
+        .collect();
+    let (outcomes, status) = cleanup::run(
+        &items,
+        &cleanup::Options {
+            remove: args.remove,
+            dry_run: args.dry_run,
+            yes: args.yes,
+            noun: "toolchains",
+            archive_path: &config.archive_path,
+        },
+    )?;
+    for (&i, outcome) in stale.iter().zip(outcomes) {
+        records[i].action = outcome.action;
+        records[i].destination = outcome.destination;
+        records[i].error = outcome.error;
+    }
+    output::emit("toolchains", &records)?;
+    status
+}
+
+fn print_table(installed: &[Toolchain], records: &[ToolchainRecord]) {
+    if output::is_machine() || records.is_empty() {
+        return;
+    }
+    let rows: Vec<ui::ToolchainRow> = installed
+        .iter()
+        .zip(records)
+        .map(|(toolchain, record)| ui::ToolchainRow {
+            st: match record.status {
+                "stale" => "✗",
+                "ok" => "✓",
+                "pinned" => "P",
+                "linked" => "L",
+                _ => "R",
+            },
+            name: record.name.clone(),
+            size: if toolchain.linked {
+                "-".to_string()
+            } else {
+                ui::format_bytes(toolchain.size)
+            },
+            last_used: ui::format_date_short(Some(toolchain.last_used)),
+            kept_by: record.kept_by.clone().unwrap_or_default(),
+        })
+        .collect();
+    let mut table = Table::new(rows);
+    table.with(Style::modern());
+    println!("{}", table);
+}
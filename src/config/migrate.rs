@@ -0,0 +1,107 @@
+use anyhow::{bail, Result};
+use toml_edit::{value, Array, DocumentMut};
+
+/// Current `config_version`. Bump it together with a new step in `STEPS`.
+pub const CONFIG_VERSION: i64 = 1;
+
+/// One migration, from `version - 1` to `version`. Steps edit the parsed document in place,
+/// so comments and formatting of untouched keys survive.
+struct Step {
+    version: i64,
+    describe: &'static str,
+    apply: fn(&mut DocumentMut, &MigrationDefaults),
+}
+
+/// Values the migrations write for keys they add.
+pub struct MigrationDefaults {
+    pub archive_path: String,
+}
+
+const STEPS: &[Step] = &[Step {
+    version: 1,
+    describe: "write defaults for keys missing from pre-versioned configs",
+    apply: v0_to_v1,
+}];
+
+/// Configs written before `config_version` existed (version 0) could be missing any of the
+/// newer keys. Add them with their defaults so the effective behavior is visible in the file
+/// (especially `windows_use_access_time` on Windows). Keys are looked up in the parsed
+/// document, so a commented-out key still counts as missing.
+fn v0_to_v1(doc: &mut DocumentMut, defaults: &MigrationDefaults) {
+    if !doc.contains_key("ignored_bins") {
+        doc["ignored_bins"] = value(Array::new());
+    }
+    if !doc.contains_key("default_threshold_days") && !doc.contains_key("default_threshold") {
+        doc["default_threshold_days"] = value(90);
+    }
+    if !doc.contains_key("archive_path") {
+        doc["archive_path"] = value(defaults.archive_path.clone());
+    }
+    if !doc.contains_key("windows_use_access_time") {
+        doc["windows_use_access_time"] = value(true);
+    }
+}
+
+/// The document's `config_version` (0 when absent).
+pub fn version_of(doc: &DocumentMut) -> Result<i64> {
+    match doc.get("config_version") {
+        None => Ok(0),
+        Some(item) => match item.as_integer() {
+            Some(v) if v >= 0 => Ok(v),
+            _ => bail!("config_version must be a non-negative integer"),
+        },
+    }
+}
+
+/// Run every step newer than the document's version, in order. Returns the descriptions of
+/// the steps that ran (empty if the config was already current).
+pub fn migrate(doc: &mut DocumentMut, defaults: &MigrationDefaults) -> Result<Vec<String>> {
+    let from = version_of(doc)?;
+    if from > CONFIG_VERSION {
+        bail!(
+            "config_version {} is newer than this bin-expire supports ({}); please upgrade bin-expire",
+            from,
+            CONFIG_VERSION
+        );
+    }
+
+    let mut applied = Vec::new();
+    for step in STEPS.iter().filter(|s| s.version > from) {
+        (step.apply)(doc, defaults);
+        doc["config_version"] = value(step.version);
+        applied.push(format!(
+            "v{} -> v{}: {}",
+            step.version - 1,
+            step.version,
+            step.describe
+        ));
+    }
+    Ok(applied)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn migrates_v0_and_keeps_comments() {
+        let raw =
+            "# my tools\nignored_bins = [\"cargo\"] # keep\n# windows_use_access_time = false\n";
+        let mut doc: DocumentMut = raw.parse().unwrap();
+        let defaults = MigrationDefaults {
+            archive_path: "/tmp/archive".to_string(),
+        };
+
+        let applied = migrate(&mut doc, &defaults).unwrap();
+        assert_eq!(applied.len(), 1);
+        let out = doc.to_string();
+        assert!(out.contains("# my tools"));
+        assert!(out.contains("ignored_bins = [\"cargo\"] # keep"));
+        assert!(out.contains("# windows_use_access_time = false"));
+        assert!(out.contains("\nwindows_use_access_time = true"));
+        assert_eq!(version_of(&doc).unwrap(), CONFIG_VERSION);
+
+        // Already current: nothing to do.
+        assert!(migrate(&mut doc, &defaults).unwrap().is_empty());
+    }
+}
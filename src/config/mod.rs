@@ -1,11 +1,54 @@
-use anyhow::Context;
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
 use std::env;
+use std::fmt;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use toml_edit::DocumentMut;
 
+use crate::fs_util::replace_file;
 use crate::models::Config;
 
+pub mod migrate;
+
+use migrate::MigrationDefaults;
+
+/// Every key `config.toml` understands, in the order `config show` lists them.
+pub const KEYS: &[&str] = &[
+    "config_version",
+    "default_profile",
+    "scan_dirs",
+    "max_depth",
+    "one_file_system",
+    "project_roots",
+    "ignored_bins",
+    "default_threshold",
+    "default_threshold_days",
+    "min_threshold",
+    "confirm_stale_scans",
+    "confirm_stale_period",
+    "archive_path",
+    "scan_threads",
+    "windows_use_access_time",
+];
+
+/// Environment variables that override config files (below `--config`).
+pub const ENV_THRESHOLD: &str = "BIN_EXPIRE_THRESHOLD";
+pub const ENV_ARCHIVE_PATH: &str = "BIN_EXPIRE_ARCHIVE_PATH";
+/// Selects a `[profile.<name>]` section (below `--profile`).
+pub const ENV_PROFILE: &str = "BIN_EXPIRE_PROFILE";
+
+/// Keys that only make sense at the top level of a file, not inside `[profile.<name>]`.
+const NOT_IN_PROFILE: &[&str] = &["config_version", "default_profile", "profile"];
+
+/// Command-line flags that affect how the config is loaded.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Overrides<'a> {
+    /// `--config <file>`
+    pub file: Option<&'a Path>,
+    /// `--profile <name>`
+    pub profile: Option<&'a str>,
+}
+
 fn base_config_dir() -> PathBuf {
     if let Some(dir) = env::var_os("BIN_EXPIRE_CONFIG_DIR") {
         return PathBuf::from(dir);
@@ -13,66 +56,463 @@ fn base_config_dir() -> PathBuf {
     dirs::config_dir().unwrap_or_else(|| PathBuf::from("."))
 }
 
-fn config_file_path() -> PathBuf {
+pub fn config_file_path() -> PathBuf {
     base_config_dir().join("bin-expire").join("config.toml")
 }
 
+/// Organization-wide defaults shipped by an admin. `BIN_EXPIRE_SYSTEM_CONFIG` points elsewhere
+/// (packaging, tests); set it to an empty string to skip the system layer.
+pub fn system_config_path() -> Option<PathBuf> {
+    if let Some(path) = env::var_os("BIN_EXPIRE_SYSTEM_CONFIG") {
+        return (!path.is_empty()).then(|| PathBuf::from(path));
+    }
+    if cfg!(windows) {
+        env::var_os("PROGRAMDATA").map(|d| PathBuf::from(d).join("bin-expire").join("config.toml"))
+    } else {
+        Some(PathBuf::from("/etc/bin-expire/config.toml"))
+    }
+}
+
 pub fn manifest_file_path() -> PathBuf {
     base_config_dir().join("bin-expire").join("archive.json")
 }
 
+pub fn pins_file_path() -> PathBuf {
+    base_config_dir().join("bin-expire").join("pins.json")
+}
+
+pub fn history_file_path() -> PathBuf {
+    base_config_dir().join("bin-expire").join("history.jsonl")
+}
+
+pub fn eligibility_file_path() -> PathBuf {
+    base_config_dir()
+        .join("bin-expire")
+        .join("eligibility.json")
+}
+
+pub fn scan_cache_file_path() -> PathBuf {
+    base_config_dir().join("bin-expire").join("scan-cache.json")
+}
+
 fn default_archive_path() -> PathBuf {
     let home = dirs::home_dir().unwrap_or_else(|| PathBuf::from("."));
     home.join(".bin-expire").join("archive")
 }
 
-pub fn load_config() -> Result<Config> {
-    let path = config_file_path();
-
-    if path.exists() {
-        let raw = fs::read_to_string(&path)
-            .with_context(|| format!("Failed to read config file: {}", path.display()))?;
-        let mut cfg: Config = toml::from_str(&raw)
-            .with_context(|| format!("Failed to parse config TOML: {}", path.display()))?;
+/// Where an effective config value came from (shown by `config show`).
+/// Layers apply in this order; later ones override earlier ones.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Origin {
+    Default,
+    System(PathBuf),
+    User(PathBuf),
+    Profile(String),
+    Env(&'static str),
+    Flag(PathBuf),
+}
 
-        // Preserve the existing behavior: if archive_path wasn't set (older config), use the default.
-        // With serde defaults, this will already be set, but keep it explicit/defensive.
-        if cfg.archive_path.as_os_str().is_empty() {
-            cfg.archive_path = default_archive_path();
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Origin::Default => write!(f, "default"),
+            Origin::System(path) => write!(f, "system: {}", path.display()),
+            Origin::User(path) => write!(f, "user: {}", path.display()),
+            Origin::Profile(name) => write!(f, "profile: {}", name),
+            Origin::Env(var) => write!(f, "env: {}", var),
+            Origin::Flag(path) => write!(f, "--config: {}", path.display()),
         }
+    }
+}
 
-        // If this is an older config without newer keys, write it back with defaults filled in.
-        // This makes the effective behavior explicit to the user (especially on Windows).
-        let missing_windows_key = !raw.contains("windows_use_access_time");
-        let missing_threshold_key = !raw.contains("default_threshold_days");
-        let missing_archive_key = !raw.contains("archive_path");
-        let missing_ignored_key = !raw.contains("ignored_bins");
-        if missing_windows_key
-            || missing_threshold_key
-            || missing_archive_key
-            || missing_ignored_key
-        {
-            let updated =
-                toml::to_string_pretty(&cfg).context("Failed to serialize updated config")?;
-            fs::write(&path, updated)
-                .with_context(|| format!("Failed to update config file: {}", path.display()))?;
-        }
-        return Ok(cfg);
+/// The effective config plus the origin(s) of each key. Merged lists can have several.
+pub struct LoadedConfig {
+    pub config: Config,
+    pub origins: Vec<(&'static str, Vec<Origin>)>,
+    /// The active profile and what selected it (`--profile`, the environment or `default_profile`).
+    pub profile: Option<(String, String)>,
+}
+
+pub fn read_document(path: &Path) -> Result<DocumentMut> {
+    let raw = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read config file: {}", path.display()))?;
+    raw.parse::<DocumentMut>()
+        .with_context(|| format!("Failed to parse config TOML: {}", path.display()))
+}
+
+pub fn write_document(path: &Path, doc: &DocumentMut) -> Result<()> {
+    replace_file(path, doc.to_string())
+        .with_context(|| format!("Failed to update config file: {}", path.display()))
+}
+
+/// Deserialize a single (migrated) file and check every value, without touching it.
+pub fn parse_document(doc: &DocumentMut, path: &Path) -> Result<Config> {
+    let table: toml::Table = toml::from_str(&doc.to_string())
+        .with_context(|| format!("Failed to parse config TOML: {}", path.display()))?;
+    let cfg: Config = table
+        .clone()
+        .try_into()
+        .with_context(|| format!("Failed to parse config TOML: {}", path.display()))?;
+    validate(&cfg).with_context(|| format!("Invalid config: {}", path.display()))?;
+
+    for (name, profile) in
+        profiles_of(&table).with_context(|| format!("Invalid config: {}", path.display()))?
+    {
+        validate_profile(&name, &profile)
+            .with_context(|| format!("Invalid config: {}", path.display()))?;
     }
+    Ok(cfg)
+}
 
-    let cfg = Config {
-        archive_path: default_archive_path(),
-        ..Config::default()
+/// The `[profile.<name>]` tables of one file.
+fn profiles_of(table: &toml::Table) -> Result<Vec<(String, toml::Table)>> {
+    let Some(profiles) = table.get("profile") else {
+        return Ok(Vec::new());
+    };
+    let Some(profiles) = profiles.as_table() else {
+        bail!("`profile` must be a table of [profile.<name>] sections");
     };
+    profiles
+        .iter()
+        .map(|(name, value)| match value.as_table() {
+            Some(t) => Ok((name.clone(), t.clone())),
+            None => bail!("profile.{} must be a table", name),
+        })
+        .collect()
+}
 
+fn validate_profile(name: &str, profile: &toml::Table) -> Result<()> {
+    for key in profile.keys() {
+        if NOT_IN_PROFILE.contains(&key.as_str()) {
+            bail!("profile.{}: `{}` can't be set inside a profile", name, key);
+        }
+    }
+    let cfg: Config = profile
+        .clone()
+        .try_into()
+        .with_context(|| format!("Invalid profile.{}", name))?;
+    validate(&cfg).with_context(|| format!("Invalid profile.{}", name))
+}
+
+/// Value checks shared by loading, `config set` and `config validate`.
+pub fn validate(cfg: &Config) -> Result<()> {
+    crate::threshold::configured_default(cfg)?;
+    crate::threshold::configured_minimum(cfg)?;
+    crate::eligibility::Policy::from_config(cfg)?;
+    if cfg.max_depth == 0 {
+        bail!("max_depth must be at least 1 (1 = only files directly in each scan dir)");
+    }
+    crate::ignore::IgnoreRules::from_config(cfg)?;
+    Ok(())
+}
+
+/// Top-level keys in the document that bin-expire doesn't know (likely typos).
+pub fn unknown_keys(doc: &DocumentMut) -> Vec<String> {
+    doc.iter()
+        .map(|(k, _)| k.to_string())
+        .filter(|k| !KEYS.contains(&k.as_str()) && k != "profile")
+        .collect()
+}
+
+fn migration_defaults() -> MigrationDefaults {
+    MigrationDefaults {
+        archive_path: default_archive_path().display().to_string(),
+    }
+}
+
+/// First run: write a commented template rather than every default, so values from the
+/// system config aren't shadowed by a user file nobody edited.
+pub fn write_default_config(path: &Path) -> Result<()> {
     if let Some(parent) = path.parent() {
         fs::create_dir_all(parent)
             .with_context(|| format!("Failed to create config directory: {}", parent.display()))?;
     }
 
-    let raw = toml::to_string_pretty(&cfg).context("Failed to serialize default config")?;
-    fs::write(&path, raw)
-        .with_context(|| format!("Failed to write default config: {}", path.display()))?;
+    let defaults = Config::default();
+    let raw = format!(
+        "# bin-expire user configuration. Uncomment a key to override its default.\n\
+         # Overrides the system config; BIN_EXPIRE_THRESHOLD / BIN_EXPIRE_ARCHIVE_PATH and\n\
+         # --config <file> override this file. See `bin-expire config show`.\n\
+         config_version = {}\n\
+         # scan_dirs = [\"~/.cargo/bin\", \"~/go/bin\", \"~/.local/share/*/bin\"]\n\
+         # max_depth = 1\n\
+         # project_roots = [\"~/src\"]\n\
+         # ignored_bins = [\"cargo\", \"rustc\"]\n\
+         # default_threshold = \"90d\"\n\
+         # Archive only binaries seen stale in 3 runs, the first at least a week ago.\n\
+         # confirm_stale_scans = 3\n\
+         # confirm_stale_period = \"7d\"\n\
+         # archive_path = {:?}\n\
+         # scan_threads = 0  # 0 = one per CPU\n\
+         # windows_use_access_time = {}\n\
+         #\n\
+         # Profiles override any key above; pick one with --profile, BIN_EXPIRE_PROFILE\n\
+         # or default_profile.\n\
+         # default_profile = \"nightly\"\n\
+         # [profile.nightly]\n\
+         # default_threshold = \"180d\"\n",
+        defaults.config_version,
+        default_archive_path().display().to_string(),
+        defaults.windows_use_access_time
+    );
+    fs::write(path, raw)
+        .with_context(|| format!("Failed to write default config: {}", path.display()))
+}
 
-    Ok(cfg)
+/// Read a config file that bin-expire doesn't own (system or --config): check its version
+/// and values, but never rewrite it.
+fn read_layer(path: &Path) -> Result<toml::Table> {
+    let doc = read_document(path)?;
+    let version =
+        migrate::version_of(&doc).with_context(|| format!("Invalid config: {}", path.display()))?;
+    if version > migrate::CONFIG_VERSION {
+        bail!(
+            "{}: config_version {} is newer than this bin-expire supports ({})",
+            path.display(),
+            version,
+            migrate::CONFIG_VERSION
+        );
+    }
+    parse_document(&doc, path)?;
+    toml::from_str(&doc.to_string())
+        .with_context(|| format!("Failed to parse config TOML: {}", path.display()))
+}
+
+/// The user's own config.toml, migrated. With `persist`, a missing file is created and migrations
+/// are written back; without it nothing on disk changes (a missing file is empty).
+fn read_user_layer(path: &Path, persist: bool) -> Result<toml::Table> {
+    if !path.exists() {
+        if !persist {
+            return Ok(toml::Table::new());
+        }
+        write_default_config(path)?;
+    }
+
+    let mut doc = read_document(path)?;
+    let applied = migrate::migrate(&mut doc, &migration_defaults())
+        .with_context(|| format!("Failed to migrate config: {}", path.display()))?;
+    parse_document(&doc, path)?;
+    if persist && !applied.is_empty() {
+        write_document(path, &doc)?;
+    }
+    toml::from_str(&doc.to_string())
+        .with_context(|| format!("Failed to parse config TOML: {}", path.display()))
+}
+
+fn set_origin(
+    origins: &mut [(&'static str, Vec<Origin>)],
+    key: &str,
+    origin: Option<&Origin>,
+    append: bool,
+) {
+    if let Some((_, list)) = origins.iter_mut().find(|(k, _)| *k == key) {
+        if !append {
+            list.clear();
+        }
+        list.extend(origin.cloned());
+    }
+}
+
+/// Fold one layer into the merged table.
+/// - `ignored_bins` accumulates across layers (an admin's protected tools stay protected).
+/// - `min_threshold` keeps the strictest (longest) value, so a lower layer's floor can't be undone.
+/// - `default_threshold` / `default_threshold_days` are one setting: a layer setting either
+///   replaces both from lower layers.
+/// - everything else: the later layer wins.
+fn merge_layer(
+    merged: &mut toml::Table,
+    origins: &mut [(&'static str, Vec<Origin>)],
+    layer: toml::Table,
+    origin: &Origin,
+) {
+    // A file may set both threshold keys (default_threshold wins inside it, as before).
+    let sets_both =
+        layer.contains_key("default_threshold") && layer.contains_key("default_threshold_days");
+    for (key, value) in layer {
+        match key.as_str() {
+            // Describes the file's own layout, not a setting; profiles are applied separately.
+            "config_version" | "profile" => continue,
+            "ignored_bins" => {
+                if let (Some(toml::Value::Array(existing)), toml::Value::Array(more)) =
+                    (merged.get_mut("ignored_bins"), &value)
+                {
+                    existing.extend(more.iter().cloned());
+                    set_origin(origins, "ignored_bins", Some(origin), true);
+                    continue;
+                }
+            }
+            "min_threshold" => {
+                let duration = |v: &toml::Value| {
+                    v.as_str()
+                        .and_then(|s| crate::threshold::parse_threshold(s).ok())
+                };
+                if merged
+                    .get("min_threshold")
+                    .is_some_and(|existing| duration(existing) >= duration(&value))
+                {
+                    continue;
+                }
+            }
+            "default_threshold" if !sets_both => {
+                merged.remove("default_threshold_days");
+                set_origin(origins, "default_threshold_days", None, false);
+            }
+            "default_threshold_days" if !sets_both => {
+                merged.remove("default_threshold");
+                set_origin(origins, "default_threshold", None, false);
+            }
+            _ => {}
+        }
+        set_origin(origins, &key, Some(origin), false);
+        merged.insert(key, value);
+    }
+}
+
+/// Pick the active profile: `--profile`, then `BIN_EXPIRE_PROFILE`, then `default_profile`.
+fn select_profile(overrides: Overrides, merged: &toml::Table) -> Option<(String, String)> {
+    if let Some(name) = overrides.profile {
+        return Some((name.to_string(), "--profile".to_string()));
+    }
+    if let Ok(name) = env::var(ENV_PROFILE) {
+        if !name.is_empty() {
+            return Some((name, format!("env: {}", ENV_PROFILE)));
+        }
+    }
+    merged
+        .get("default_profile")
+        .and_then(|v| v.as_str())
+        .map(|name| (name.to_string(), "default_profile".to_string()))
+}
+
+/// Load the effective config from every layer, lowest precedence first:
+/// built-in defaults, system config, user config.toml, the selected `[profile.<name>]`,
+/// environment, `--config <file>`.
+/// The user file is created on first run and migrated in place (keeping comments).
+pub fn load(overrides: Overrides) -> Result<LoadedConfig> {
+    load_layers(overrides, true)
+}
+
+/// What `load` would produce, without creating or migrating the user file
+/// (`config validate`).
+pub fn load_without_writing(overrides: Overrides) -> Result<LoadedConfig> {
+    load_layers(overrides, false)
+}
+
+fn load_layers(overrides: Overrides, persist: bool) -> Result<LoadedConfig> {
+    let user_path = config_file_path();
+    let mut merged = toml::Table::new();
+    let mut origins: Vec<(&'static str, Vec<Origin>)> =
+        KEYS.iter().map(|k| (*k, Vec::new())).collect();
+
+    let system = match system_config_path().filter(|p| p.exists()) {
+        Some(path) => Some((read_layer(&path)?, path)),
+        None => None,
+    };
+    let user = read_user_layer(&user_path, persist)?;
+    let flag = match overrides.file {
+        Some(path) if !path.is_file() => bail!("--config file not found: {}", path.display()),
+        Some(path) => Some(read_layer(path)?),
+        None => None,
+    };
+
+    // Profiles can be defined in any file; a later file's keys win within the same profile.
+    let mut profiles: Vec<(String, toml::Table)> = Vec::new();
+    for layer in [system.as_ref().map(|(t, _)| t), Some(&user), flag.as_ref()]
+        .into_iter()
+        .flatten()
+    {
+        for (name, table) in profiles_of(layer)? {
+            match profiles.iter_mut().find(|(n, _)| *n == name) {
+                Some((_, existing)) => existing.extend(table),
+                None => profiles.push((name, table)),
+            }
+        }
+    }
+
+    if let Some((layer, path)) = system {
+        merge_layer(&mut merged, &mut origins, layer, &Origin::System(path));
+    }
+    merge_layer(
+        &mut merged,
+        &mut origins,
+        user,
+        &Origin::User(user_path.clone()),
+    );
+
+    // default_profile may come from --config too, so look at it before choosing.
+    let mut selection_source = merged.clone();
+    if let Some(v) = flag.as_ref().and_then(|f| f.get("default_profile")) {
+        selection_source.insert("default_profile".into(), v.clone());
+    }
+    let profile = select_profile(overrides, &selection_source);
+    if let Some((name, source)) = &profile {
+        let Some((_, table)) = profiles.iter().find(|(n, _)| n == name) else {
+            let known: Vec<&str> = profiles.iter().map(|(n, _)| n.as_str()).collect();
+            bail!(
+                "Unknown profile '{}' (selected by {}); defined profiles: {}",
+                name,
+                source,
+                if known.is_empty() {
+                    "none".to_string()
+                } else {
+                    known.join(", ")
+                }
+            );
+        };
+        merge_layer(
+            &mut merged,
+            &mut origins,
+            table.clone(),
+            &Origin::Profile(name.clone()),
+        );
+    }
+
+    for (var, key) in [
+        (ENV_THRESHOLD, "default_threshold"),
+        (ENV_ARCHIVE_PATH, "archive_path"),
+    ] {
+        if let Ok(value) = env::var(var) {
+            let mut layer = toml::Table::new();
+            layer.insert(key.to_string(), toml::Value::String(value));
+            merge_layer(&mut merged, &mut origins, layer, &Origin::Env(var));
+        }
+    }
+
+    if let (Some(layer), Some(path)) = (flag, overrides.file) {
+        merge_layer(
+            &mut merged,
+            &mut origins,
+            layer,
+            &Origin::Flag(path.to_path_buf()),
+        );
+    }
+
+    let mut config: Config = merged
+        .try_into()
+        .context("Failed to combine configuration layers")?;
+
+    // No layer set archive_path (or it was left empty): use the platform default.
+    if config.archive_path.as_os_str().is_empty() || origins_of(&origins, "archive_path").is_empty()
+    {
+        config.archive_path = default_archive_path();
+    }
+    validate(&config).context("Invalid configuration (after applying environment overrides)")?;
+
+    Ok(LoadedConfig {
+        config,
+        origins,
+        profile,
+    })
+}
+
+fn origins_of<'a>(origins: &'a [(&'static str, Vec<Origin>)], key: &str) -> &'a [Origin] {
+    origins
+        .iter()
+        .find(|(k, _)| *k == key)
+        .map(|(_, list)| list.as_slice())
+        .unwrap_or_default()
+}
+
+pub fn load_config(overrides: Overrides) -> Result<Config> {
+    Ok(load(overrides)?.config)
 }
@@ -0,0 +1,237 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+use crate::config::eligibility_file_path;
+use crate::fs_util::replace_file;
+use crate::models::Config;
+use crate::threshold;
+
+/// How much evidence `archive` needs before it moves a stale binary: seen stale in `scans`
+/// separate runs, the first of them at least `period` ago.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Policy {
+    pub scans: u32,
+    pub period: Duration,
+}
+
+impl Policy {
+    pub fn from_config(config: &Config) -> Result<Self> {
+        let period = config
+            .confirm_stale_period
+            .as_deref()
+            .map(|raw| {
+                threshold::parse_threshold(raw).context("Invalid confirm_stale_period in config")
+            })
+            .transpose()?
+            .unwrap_or_default();
+        Ok(Policy {
+            scans: config.confirm_stale_scans.max(1),
+            period,
+        })
+    }
+
+    /// With the defaults (one scan, no period) a single stale check is enough, as before.
+    pub fn is_enabled(&self) -> bool {
+        self.scans > 1 || !self.period.is_zero()
+    }
+
+    /// "3 scans over 7 days"
+    pub fn describe(&self) -> String {
+        let scans = if self.scans == 1 {
+            "1 scan".to_string()
+        } else {
+            format!("{} scans", self.scans)
+        };
+        if self.period.is_zero() {
+            scans
+        } else {
+            format!("{} over {}", scans, threshold::describe(self.period))
+        }
+    }
+}
+
+/// Consecutive stale observations of one binary. Dropped as soon as it's seen in use.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Streak {
+    pub name: String,
+    /// RFC 3339 time of the first run that saw it stale.
+    pub first_stale: String,
+    pub last_stale: String,
+    pub stale_scans: u32,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Store {
+    /// Keyed by absolute path.
+    streaks: BTreeMap<String, Streak>,
+}
+
+/// Stale streaks kept between runs in eligibility.json.
+pub struct Tracker {
+    policy: Policy,
+    store: Store,
+    now: SystemTime,
+    dirty: bool,
+}
+
+fn key(path: &Path) -> String {
+    std::path::absolute(path)
+        .unwrap_or_else(|_| path.to_path_buf())
+        .display()
+        .to_string()
+}
+
+fn parse_time(rfc3339: &str) -> Option<SystemTime> {
+    humantime::parse_rfc3339(rfc3339).ok()
+}
+
+impl Tracker {
+    pub fn load(policy: Policy) -> Result<Self> {
+        let path = eligibility_file_path();
+        let store = if path.exists() {
+            let raw = fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read eligibility file: {}", path.display()))?;
+            serde_json::from_str(&raw)
+                .with_context(|| format!("Failed to parse eligibility JSON: {}", path.display()))?
+        } else {
+            Store::default()
+        };
+        Ok(Tracker {
+            policy,
+            store,
+            now: SystemTime::now(),
+            dirty: false,
+        })
+    }
+
+    /// Count this run as one more stale observation of `path`.
+    pub fn saw_stale(&mut self, name: &str, path: &Path) -> &Streak {
+        let now = humantime::format_rfc3339_seconds(self.now).to_string();
+        self.dirty = true;
+        let streak = self
+            .store
+            .streaks
+            .entry(key(path))
+            .or_insert_with(|| Streak {
+                name: name.to_string(),
+                first_stale: now.clone(),
+                last_stale: now.clone(),
+                stale_scans: 0,
+            });
+        streak.last_stale = now;
+        streak.stale_scans = streak.stale_scans.saturating_add(1);
+        streak
+    }
+
+    /// `path` was seen in use: any streak starts over.
+    pub fn saw_used(&mut self, path: &Path) {
+        if self.store.streaks.remove(&key(path)).is_some() {
+            self.dirty = true;
+        }
+    }
+
+    /// Whether `streak` meets the policy. A first observation dated in the future (the clock
+    /// went backwards) never counts as old enough.
+    pub fn is_eligible(&self, streak: &Streak) -> bool {
+        let waited = parse_time(&streak.first_stale)
+            .and_then(|first| self.now.duration_since(first).ok())
+            .unwrap_or_default();
+        streak.stale_scans >= self.policy.scans && waited >= self.policy.period
+    }
+
+    /// "stale in 1 of 3 scans, for 2 days of 7 days"
+    pub fn progress(&self, streak: &Streak) -> String {
+        let mut text = format!(
+            "stale in {} of {} scans",
+            streak.stale_scans.min(self.policy.scans),
+            self.policy.scans
+        );
+        if !self.policy.period.is_zero() {
+            let waited = parse_time(&streak.first_stale)
+                .and_then(|first| self.now.duration_since(first).ok())
+                .unwrap_or_default();
+            // Whole days are enough precision here.
+            let waited = Duration::from_secs(waited.as_secs() / 86_400 * 86_400);
+            text.push_str(&format!(
+                ", for {} of {}",
+                threshold::describe(waited),
+                threshold::describe(self.policy.period)
+            ));
+        }
+        text
+    }
+
+    /// Write the store back if anything changed. Streaks of binaries that no longer exist
+    /// are dropped.
+    pub fn save(mut self) -> Result<()> {
+        let before = self.store.streaks.len();
+        self.store
+            .streaks
+            .retain(|path, _| Path::new(path).exists());
+        if !self.dirty && self.store.streaks.len() == before {
+            return Ok(());
+        }
+
+        let path = eligibility_file_path();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).with_context(|| {
+                format!(
+                    "Failed to create eligibility directory: {}",
+                    parent.display()
+                )
+            })?;
+        }
+        let raw =
+            serde_json::to_string_pretty(&self.store).context("Failed to serialize eligibility")?;
+        replace_file(&path, raw)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn needs_scans_and_period() {
+        let policy = Policy {
+            scans: 3,
+            period: Duration::from_secs(7 * 86_400),
+        };
+        let now = SystemTime::now();
+        let tracker = Tracker {
+            policy,
+            store: Store::default(),
+            now,
+            dirty: false,
+        };
+        let streak = |scans, days_ago: u64| Streak {
+            name: "tool".to_string(),
+            first_stale: humantime::format_rfc3339_seconds(
+                now - Duration::from_secs(days_ago * 86_400),
+            )
+            .to_string(),
+            last_stale: String::new(),
+            stale_scans: scans,
+        };
+        assert!(!tracker.is_eligible(&streak(2, 30)));
+        assert!(!tracker.is_eligible(&streak(5, 3)));
+        assert!(tracker.is_eligible(&streak(3, 7)));
+        assert_eq!(
+            tracker.progress(&streak(1, 2)),
+            "stale in 1 of 3 scans, for 2 days of 7 days"
+        );
+
+        // Clock moved backwards since the first observation.
+        let future = Streak {
+            first_stale: humantime::format_rfc3339_seconds(now + Duration::from_secs(86_400 * 30))
+                .to_string(),
+            ..streak(9, 0)
+        };
+        assert!(!tracker.is_eligible(&future));
+        assert_eq!(policy.describe(), "3 scans over 7 days");
+    }
+}
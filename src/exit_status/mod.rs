@@ -0,0 +1,72 @@
+use std::fmt;
+
+/// Process exit codes. These are part of the CLI contract (documented in `--help` and README),
+/// so existing values must never be renumbered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitStatus {
+    /// Everything worked (and, for `check` / `--fail-if-stale`, nothing stale or broken was found).
+    Success,
+    /// Unexpected error, or nothing could be done (e.g. none of the directories exist).
+    /// (Exit code 2 is reserved for invalid command-line usage, reported by the argument parser.)
+    Error,
+    /// `check` / `scan --fail-if-stale` found stale or broken binaries.
+    StaleFound,
+    /// Some items were processed but at least one failed.
+    PartialFailure,
+    /// The configuration could not be read, parsed or validated.
+    ConfigError,
+    /// The archive manifest could not be read, parsed or written.
+    ManifestError,
+    /// Stopped by Ctrl-C / SIGINT.
+    Interrupted,
+}
+
+impl ExitStatus {
+    pub fn code(self) -> u8 {
+        match self {
+            ExitStatus::Success => 0,
+            ExitStatus::Error => 1,
+            ExitStatus::StaleFound => 3,
+            ExitStatus::PartialFailure => 4,
+            ExitStatus::ConfigError => 5,
+            ExitStatus::ManifestError => 6,
+            ExitStatus::Interrupted => 130,
+        }
+    }
+
+    /// Pick the exit status for an error, based on the `ErrorKind` attached to it (if any).
+    pub fn for_error(err: &anyhow::Error) -> Self {
+        match err.downcast_ref::<ErrorKind>() {
+            Some(ErrorKind::Config) => ExitStatus::ConfigError,
+            Some(ErrorKind::Manifest) => ExitStatus::ManifestError,
+            Some(ErrorKind::Interrupted) => ExitStatus::Interrupted,
+            None => ExitStatus::Error,
+        }
+    }
+}
+
+impl From<ExitStatus> for std::process::ExitCode {
+    fn from(status: ExitStatus) -> Self {
+        std::process::ExitCode::from(status.code())
+    }
+}
+
+/// Attach to an error with `.context(ErrorKind::...)` to select its exit code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    Config,
+    Manifest,
+    Interrupted,
+}
+
+impl std::error::Error for ErrorKind {}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ErrorKind::Config => write!(f, "configuration error"),
+            ErrorKind::Manifest => write!(f, "archive manifest error"),
+            ErrorKind::Interrupted => write!(f, "interrupted"),
+        }
+    }
+}
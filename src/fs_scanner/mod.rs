@@ -1,45 +1,203 @@
-use crate::analyzer::{get_file_info, select_last_used_time};
-use crate::models::BinaryInfo;
-use walkdir::WalkDir;
+use crate::analyzer::{get_file_info, select_last_used_time, FileInfo, FileTimes};
+use crate::ignore::IGNORE_FILE_NAME;
+use crate::models::{BinaryInfo, Config};
+use crate::pool;
+use crate::ui;
+use colored::Colorize;
+use globset::Glob;
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::{DirEntry, WalkDir};
 
-pub fn scan_directory(dir: &std::path::Path, windows_use_access_time: bool) -> Vec<BinaryInfo> {
-    let mut binaries = Vec::new();
+fn link_info(path: &Path) -> Option<FileInfo> {
+    let metadata = std::fs::symlink_metadata(path).ok()?;
+    Some(FileInfo {
+        size: metadata.len(),
+        times: FileTimes {
+            accessed: metadata.accessed().ok(),
+            modified: metadata.modified().ok(),
+        },
+    })
+}
 
-    for entry in WalkDir::new(dir)
-        .max_depth(1)
-        .into_iter()
-        .filter_map(|e| e.ok())
-    {
-        let path = entry.path();
+/// One directory entry to look at: which scanned directory it came from, and its path.
+struct Candidate {
+    dir: usize,
+    path: PathBuf,
+    is_symlink: bool,
+}
 
-        if path.is_dir() {
-            continue;
+fn binary_info(candidate: &Candidate, windows_use_access_time: bool) -> Option<BinaryInfo> {
+    let path = candidate.path.as_path();
+    let mut is_broken_link = false;
+
+    let info = match get_file_info(path) {
+        Some(i) => i,
+        // A dangling symlink can't be followed; report it from the link's own metadata.
+        None if candidate.is_symlink && !path.exists() => {
+            is_broken_link = true;
+            link_info(path)?
         }
+        None => return None,
+    };
+
+    let times = info.times;
+    let (last_used, last_used_source) = select_last_used_time(times, windows_use_access_time);
+    let name = path
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+
+    Some(BinaryInfo {
+        name,
+        path: path.to_path_buf(),
+        size: info.size,
+        accessed: times.accessed,
+        modified: times.modified,
+        last_used,
+        last_used_source,
+        _is_symlink: candidate.is_symlink,
+        is_broken_link,
+        details: None,
+    })
+}
 
-        let info = match get_file_info(path) {
-            Some(i) => i,
-            None => continue,
+fn has_wildcard(text: &str) -> bool {
+    text.contains(['*', '?', '['])
+}
+
+/// Expand a directory spec: `~`, then `*`, `?` and `[...]` in any component, e.g.
+/// `~/.local/share/*/bin` or `/opt/*/bin`. Specs without wildcards come back as they are, even if
+/// missing, so callers can warn about them; a pattern that matches nothing gives a warning here.
+pub fn expand_dir_spec(spec: &str) -> Vec<PathBuf> {
+    let path = ui::expand_tilde(spec);
+    if !has_wildcard(&path.to_string_lossy()) {
+        return vec![path];
+    }
+
+    let mut matches = vec![PathBuf::new()];
+    for component in path.components() {
+        let text = component.as_os_str().to_string_lossy();
+        if !has_wildcard(&text) {
+            for m in &mut matches {
+                m.push(component);
+            }
+            continue;
+        }
+        let matcher = match Glob::new(&text) {
+            Ok(glob) => glob.compile_matcher(),
+            Err(err) => {
+                eprintln!(
+                    "{} Invalid directory pattern '{}': {}",
+                    "[!]".yellow(),
+                    spec,
+                    err
+                );
+                return Vec::new();
+            }
         };
+        let mut next = Vec::new();
+        for base in &matches {
+            let Ok(entries) = fs::read_dir(base) else {
+                continue;
+            };
+            let mut found: Vec<PathBuf> = entries
+                .flatten()
+                .filter(|e| matcher.is_match(e.file_name()))
+                .map(|e| e.path())
+                .collect();
+            found.sort();
+            next.extend(found);
+        }
+        matches = next;
+    }
+
+    matches.retain(|m| m.is_dir());
+    if matches.is_empty() {
+        eprintln!(
+            "{} No directories match {}. Skipping.",
+            "[!]".yellow(),
+            spec
+        );
+    }
+    matches
+}
+
+/// Only executables count below the top level, so recursing into e.g. ~/.local/share doesn't
+/// list every data file. (Windows has no executable bit; everything counts there.)
+fn is_nested_binary(entry: &DirEntry) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        entry
+            .metadata()
+            .is_ok_and(|m| m.permissions().mode() & 0o111 != 0)
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = entry;
+        true
+    }
+}
 
-        let times = info.times;
-        let (last_used, last_used_source) = select_last_used_time(times, windows_use_access_time);
-        let name = path
-            .file_name()
-            .unwrap_or_default()
-            .to_string_lossy()
-            .to_string();
-
-        binaries.push(BinaryInfo {
-            name,
-            path: path.to_path_buf(),
-            size: info.size,
-            accessed: times.accessed,
-            modified: times.modified,
-            last_used,
-            last_used_source,
-            _is_symlink: entry.file_type().is_symlink(),
-        });
+/// List the files in each of `dirs` (down to `config.max_depth` levels, following symlinked
+/// directories but never around a loop) and read their metadata on `config.scan_threads`
+/// workers. Returns one list per directory, in directory listing order.
+pub fn scan_directories(dirs: &[PathBuf], config: &Config) -> Vec<Vec<BinaryInfo>> {
+    let mut candidates = Vec::new();
+    for (i, dir) in dirs.iter().enumerate() {
+        let walker = WalkDir::new(dir)
+            .max_depth(config.max_depth.max(1))
+            .follow_links(true)
+            .same_file_system(config.one_file_system);
+        for entry in walker {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(err) => {
+                    if let Some(ancestor) = err.loop_ancestor() {
+                        eprintln!(
+                            "{} Not following {}: symlink loop back to {}.",
+                            "[!]".yellow(),
+                            err.path().unwrap_or(dir).display(),
+                            ancestor.display()
+                        );
+                    } else if let Some(path) = err.path().filter(|_| err.depth() > 0) {
+                        // A dangling symlink can't be followed; it is still reported (as broken).
+                        if fs::symlink_metadata(path).is_ok_and(|m| m.file_type().is_symlink()) {
+                            candidates.push(Candidate {
+                                dir: i,
+                                path: path.to_path_buf(),
+                                is_symlink: true,
+                            });
+                        }
+                    }
+                    continue;
+                }
+            };
+            if entry.file_type().is_dir() || entry.file_name() == IGNORE_FILE_NAME {
+                continue;
+            }
+            if entry.depth() > 1 && !is_nested_binary(&entry) {
+                continue;
+            }
+            candidates.push(Candidate {
+                dir: i,
+                path: entry.path().to_path_buf(),
+                is_symlink: entry.path_is_symlink(),
+            });
+        }
     }
 
+    let infos = pool::map(&candidates, config.scan_threads, |c| {
+        binary_info(c, config.windows_use_access_time)
+    });
+
+    let mut binaries: Vec<Vec<BinaryInfo>> = dirs.iter().map(|_| Vec::new()).collect();
+    for (candidate, info) in candidates.iter().zip(infos) {
+        if let Some(info) = info {
+            binaries[candidate.dir].push(info);
+        }
+    }
     binaries
 }
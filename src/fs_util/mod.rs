@@ -0,0 +1,18 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Replace `path` with `contents` without ever leaving a half-written file: write
+/// `<path>.tmp`, then rename it over `path`.
+pub fn replace_file(path: &Path, contents: impl AsRef<[u8]>) -> Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+    fs::write(&tmp, contents).with_context(|| format!("Failed to write {}", tmp.display()))?;
+
+    // Windows doesn't allow rename over an existing file.
+    if cfg!(windows) && path.exists() {
+        let _ = fs::remove_file(path);
+    }
+    fs::rename(&tmp, path).with_context(|| format!("Failed to replace {}", path.display()))
+}
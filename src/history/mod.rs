@@ -0,0 +1,356 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use crate::config::history_file_path;
+use crate::fs_util::replace_file;
+
+/// Oldest scans are dropped once the history grows past this many.
+const MAX_SCANS: usize = 1000;
+
+/// One binary as seen by one scan.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScanEntry {
+    pub name: String,
+    pub path: PathBuf,
+    pub size: u64,
+    pub last_used: String,
+    /// `BinaryStatus::as_str` at the time of the scan.
+    pub status: String,
+}
+
+/// Everything one `scan` saw. Stored one per line in history.jsonl.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScanSnapshot {
+    pub id: u64,
+    pub taken_at: String,
+    /// Threshold the statuses were computed with, e.g. "90d".
+    pub threshold: String,
+    /// The scanned roots.
+    pub dirs: Vec<PathBuf>,
+    /// `--max-depth` of the scan: how many levels below each root it looked.
+    #[serde(default = "default_max_depth")]
+    pub max_depth: usize,
+    pub entries: Vec<ScanEntry>,
+}
+
+/// Scans recorded before `--max-depth` only looked at the top of each directory.
+fn default_max_depth() -> usize {
+    1
+}
+
+impl ScanSnapshot {
+    /// The deepest scanned root that `path` is within reach of. Scans of other directories, or
+    /// shallower scans, say nothing about it (it wasn't missing, just not looked at).
+    pub fn root_of(&self, path: &Path) -> Option<&PathBuf> {
+        self.dirs
+            .iter()
+            .filter(|dir| {
+                path.strip_prefix(dir)
+                    .is_ok_and(|rel| rel.components().count() <= self.max_depth)
+            })
+            .max_by_key(|dir| dir.components().count())
+    }
+
+    /// Whether this scan looked where `path` lives.
+    pub fn covers(&self, path: &Path) -> bool {
+        self.root_of(path).is_some()
+    }
+
+    /// Entries keyed by path, for lookups across many paths.
+    pub fn by_path(&self) -> HashMap<&Path, &ScanEntry> {
+        self.entries.iter().map(|e| (e.path.as_path(), e)).collect()
+    }
+}
+
+/// "2026-10-18 14:03" in local time, for tables.
+pub fn short_time(rfc3339: &str) -> String {
+    DateTime::parse_from_rfc3339(rfc3339)
+        .map(|t| t.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_else(|_| rfc3339.to_string())
+}
+
+fn read_scans(path: &Path) -> Result<Vec<ScanSnapshot>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let raw = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read scan history: {}", path.display()))?;
+    let mut scans = Vec::new();
+    for (i, line) in raw.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str::<ScanSnapshot>(line) {
+            Ok(scan) => scans.push(scan),
+            // A line cut short by a crash mid-append shouldn't make the whole history unreadable.
+            Err(err) => eprintln!(
+                "[!] {}:{}: skipping unreadable scan record ({})",
+                path.display(),
+                i + 1,
+                err
+            ),
+        }
+    }
+    Ok(scans)
+}
+
+fn rewrite_scans(path: &Path, scans: &[ScanSnapshot]) -> Result<()> {
+    let mut raw = String::new();
+    for scan in scans {
+        raw.push_str(&serde_json::to_string(scan).context("Failed to serialize scan")?);
+        raw.push('\n');
+    }
+    replace_file(path, raw)
+}
+
+#[derive(Deserialize)]
+struct IdOnly {
+    id: u64,
+}
+
+fn id_of(line: &str) -> Option<u64> {
+    serde_json::from_str::<IdOnly>(line).ok().map(|s| s.id)
+}
+
+/// The first non-empty line of `file`.
+fn first_line(file: &mut File) -> std::io::Result<Option<String>> {
+    for line in BufReader::new(file).lines() {
+        let line = line?;
+        if !line.trim().is_empty() {
+            return Ok(Some(line));
+        }
+    }
+    Ok(None)
+}
+
+/// The last non-empty line of `file`, read backwards from the end.
+fn last_line(file: &mut File) -> std::io::Result<Option<String>> {
+    let mut pos = file.metadata()?.len();
+    let mut tail: Vec<u8> = Vec::new();
+    let mut chunk = 8 * 1024u64;
+    loop {
+        let end = tail
+            .iter()
+            .rposition(|b| !b.is_ascii_whitespace())
+            .map_or(0, |i| i + 1);
+        let start = tail[..end].iter().rposition(|&b| b == b'\n');
+        if start.is_some() || pos == 0 {
+            let line = &tail[start.map_or(0, |i| i + 1)..end];
+            return Ok((!line.is_empty()).then(|| String::from_utf8_lossy(line).into_owned()));
+        }
+        let step = chunk.min(pos);
+        pos -= step;
+        let mut buf = vec![0; step as usize];
+        file.seek(SeekFrom::Start(pos))?;
+        file.read_exact(&mut buf)?;
+        buf.extend_from_slice(&tail);
+        tail = buf;
+        // Snapshots can be large; growing the chunk keeps this linear.
+        chunk *= 2;
+    }
+}
+
+/// Ids of the first and last scan, or `None` if there are none or either line is unreadable.
+fn id_bounds(path: &Path) -> Result<Option<(u64, u64)>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let mut file =
+        File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let (first, last) = first_line(&mut file)
+        .and_then(|first| Ok((first, last_line(&mut file)?)))
+        .with_context(|| format!("Failed to read scan history: {}", path.display()))?;
+    Ok(first
+        .as_deref()
+        .and_then(id_of)
+        .zip(last.as_deref().and_then(id_of)))
+}
+
+/// All recorded scans, oldest first.
+pub fn all_scans() -> Result<Vec<ScanSnapshot>> {
+    read_scans(&history_file_path())
+}
+
+/// Append one scan to the history (and trim it to `MAX_SCANS`).
+pub fn record_scan(
+    threshold: &str,
+    dirs: Vec<PathBuf>,
+    max_depth: usize,
+    entries: Vec<ScanEntry>,
+) -> Result<()> {
+    let path = history_file_path();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create history directory: {}", parent.display()))?;
+    }
+
+    // Only the first and last line are parsed for the next id; ids are consecutive, so they also
+    // give the count. Everything is read only to trim, or when an end line is unreadable.
+    let mut loaded = None;
+    let (next_id, count) = match id_bounds(&path)? {
+        Some((first, last)) => (last + 1, (last + 1).saturating_sub(first) as usize),
+        None => {
+            let scans = loaded.insert(read_scans(&path)?);
+            (scans.last().map_or(1, |s| s.id + 1), scans.len())
+        }
+    };
+    let scan = ScanSnapshot {
+        id: next_id,
+        taken_at: humantime::format_rfc3339_seconds(std::time::SystemTime::now()).to_string(),
+        threshold: threshold.to_string(),
+        dirs,
+        max_depth,
+        entries,
+    };
+
+    if count >= MAX_SCANS {
+        let mut scans = match loaded {
+            Some(scans) => scans,
+            None => read_scans(&path)?,
+        };
+        scans.push(scan);
+        let excess = scans.len() - MAX_SCANS;
+        scans.drain(..excess);
+        return rewrite_scans(&path, &scans);
+    }
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    let line = serde_json::to_string(&scan).context("Failed to serialize scan")?;
+    writeln!(file, "{}", line).with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(())
+}
+
+/// How one binary changed between two scans.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change {
+    /// Present in both; stale now but not before.
+    WentStale,
+    /// Stale before; used since (newer last_used) and no longer stale.
+    UsedAgain,
+    Added,
+    Removed,
+}
+
+impl Change {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Change::WentStale => "went_stale",
+            Change::UsedAgain => "used_again",
+            Change::Added => "added",
+            Change::Removed => "removed",
+        }
+    }
+}
+
+/// Compare two scans. Only directories both scans looked at are compared.
+pub fn diff<'a>(
+    from: &'a ScanSnapshot,
+    to: &'a ScanSnapshot,
+) -> Vec<(Change, Option<&'a ScanEntry>, Option<&'a ScanEntry>)> {
+    let (before_by_path, after_by_path) = (from.by_path(), to.by_path());
+    let mut changes = Vec::new();
+    for after in to.entries.iter().filter(|e| from.covers(&e.path)) {
+        match before_by_path.get(after.path.as_path()).copied() {
+            None => changes.push((Change::Added, None, Some(after))),
+            Some(before) => {
+                let was_stale = before.status == "stale";
+                let is_stale = after.status == "stale";
+                if is_stale && !was_stale {
+                    changes.push((Change::WentStale, Some(before), Some(after)));
+                } else if was_stale && !is_stale && after.last_used > before.last_used {
+                    changes.push((Change::UsedAgain, Some(before), Some(after)));
+                }
+            }
+        }
+    }
+    for before in from.entries.iter().filter(|e| to.covers(&e.path)) {
+        if !after_by_path.contains_key(before.path.as_path()) {
+            changes.push((Change::Removed, Some(before), None));
+        }
+    }
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(name: &str, status: &str, last_used: &str) -> ScanEntry {
+        ScanEntry {
+            name: name.to_string(),
+            path: PathBuf::from("/bin").join(name),
+            size: 1,
+            last_used: last_used.to_string(),
+            status: status.to_string(),
+        }
+    }
+
+    #[test]
+    fn id_bounds_reads_only_the_ends() {
+        let path =
+            std::env::temp_dir().join(format!("bin-expire-history-{}.jsonl", std::process::id()));
+        let line = |id: u64, padding: usize| {
+            format!("{{\"id\":{},\"pad\":\"{}\"}}\n", id, "x".repeat(padding))
+        };
+        // The last line is longer than one read chunk.
+        let raw = format!("\n{}{}{}\n", line(3, 10), line(4, 10), line(5, 50_000));
+        fs::write(&path, raw).unwrap();
+        assert_eq!(id_bounds(&path).unwrap(), Some((3, 5)));
+
+        // Cut short by a crash mid-append: fall back to reading everything.
+        fs::write(&path, format!("{}{{\"id\":4,\"ent", line(3, 10))).unwrap();
+        assert_eq!(id_bounds(&path).unwrap(), None);
+
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn diff_reports_stale_used_added_removed() {
+        let scan = |id, entries| ScanSnapshot {
+            id,
+            taken_at: String::new(),
+            threshold: "90d".to_string(),
+            dirs: vec![PathBuf::from("/bin")],
+            max_depth: 1,
+            entries,
+        };
+        let from = scan(
+            1,
+            vec![
+                entry("a", "ok", "2026-01-01T00:00:00Z"),
+                entry("b", "stale", "2025-01-01T00:00:00Z"),
+                entry("gone", "ok", "2026-01-01T00:00:00Z"),
+            ],
+        );
+        let to = scan(
+            2,
+            vec![
+                entry("a", "stale", "2026-01-01T00:00:00Z"),
+                entry("b", "ok", "2026-10-01T00:00:00Z"),
+                entry("new", "ok", "2026-10-01T00:00:00Z"),
+            ],
+        );
+        let changes: Vec<(Change, String)> = diff(&from, &to)
+            .into_iter()
+            .map(|(c, b, a)| (c, a.or(b).unwrap().name.clone()))
+            .collect();
+        assert_eq!(
+            changes,
+            [
+                (Change::WentStale, "a".to_string()),
+                (Change::UsedAgain, "b".to_string()),
+                (Change::Added, "new".to_string()),
+                (Change::Removed, "gone".to_string()),
+            ]
+        );
+    }
+}
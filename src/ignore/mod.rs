@@ -0,0 +1,209 @@
+use anyhow::{anyhow, Context, Result};
+use globset::{Glob, GlobMatcher};
+use regex::Regex;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::exit_status::ErrorKind;
+use crate::models::{BinaryInfo, Config, IgnoreEntry};
+use crate::ui;
+
+/// Per-directory ignore file, read from each scanned directory (the root given to `-p` or
+/// `scan_dirs`; files in nested directories of a `--max-depth` scan are not read).
+pub const IGNORE_FILE_NAME: &str = ".bin-expire-ignore";
+
+#[derive(Clone)]
+enum Matcher {
+    Glob(GlobMatcher),
+    Regex(Regex),
+}
+
+impl Matcher {
+    /// `re:<regex>` is a regular expression; anything else is a glob (a plain name matches exactly).
+    fn parse(pattern: &str) -> Result<Self> {
+        if let Some(re) = pattern.strip_prefix("re:") {
+            let re = Regex::new(re).with_context(|| format!("Invalid regex in '{}'", pattern))?;
+            return Ok(Matcher::Regex(re));
+        }
+        let glob = Glob::new(pattern).with_context(|| format!("Invalid glob '{}'", pattern))?;
+        Ok(Matcher::Glob(glob.compile_matcher()))
+    }
+
+    fn is_match(&self, name: &str) -> bool {
+        match self {
+            Matcher::Glob(g) => g.is_match(name),
+            Matcher::Regex(r) => r.is_match(name),
+        }
+    }
+}
+
+#[derive(Clone)]
+struct Rule {
+    matcher: Matcher,
+    /// Only applies to scans of this directory, including binaries found below it with
+    /// `--max-depth`.
+    dir: Option<PathBuf>,
+    /// `!pattern` in an ignore file: un-ignore binaries matched by an earlier rule.
+    negate: bool,
+    /// Where the rule came from, shown when a binary is ignored.
+    origin: String,
+}
+
+/// A binary that was left out of a scan or archive run, with the rule that matched it.
+#[derive(Debug, Clone)]
+pub struct Ignored {
+    pub bin: BinaryInfo,
+    pub rule: String,
+}
+
+/// Compiled ignore rules: `ignored_bins` from the config, plus (via `for_dir`) the
+/// `.bin-expire-ignore` file of one scanned directory. Like `.gitignore`, the last matching rule
+/// wins. Patterns match file names, whatever depth the binary was found at.
+pub struct IgnoreRules {
+    rules: Vec<Rule>,
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+impl IgnoreRules {
+    pub fn from_config(config: &Config) -> Result<Self> {
+        let rules = config
+            .ignored_bins
+            .iter()
+            .map(|entry| {
+                let (pattern, dir) = match entry {
+                    IgnoreEntry::Pattern(p) => (p.as_str(), None),
+                    IgnoreEntry::Scoped { pattern, dir } => (pattern.as_str(), Some(dir.as_str())),
+                };
+                let origin = match dir {
+                    Some(d) => format!("ignored_bins: \"{}\" in {}", pattern, d),
+                    None => format!("ignored_bins: \"{}\"", pattern),
+                };
+                Ok(Rule {
+                    matcher: Matcher::parse(pattern).context("Invalid ignored_bins entry")?,
+                    dir: dir.map(|d| canonical(&ui::expand_tilde(d))),
+                    negate: false,
+                    origin,
+                })
+            })
+            .collect::<Result<Vec<Rule>>>()
+            .context(ErrorKind::Config)?;
+        Ok(Self { rules })
+    }
+
+    /// Rules that apply to a scan of `dir`: the config rules scoped to it (or unscoped), followed
+    /// by the directory's `.bin-expire-ignore` file if it has one. They cover everything the scan
+    /// finds below `dir` too.
+    pub fn for_dir(&self, dir: &Path) -> Result<Self> {
+        let dir = canonical(dir);
+        let mut rules: Vec<Rule> = self
+            .rules
+            .iter()
+            .filter(|r| r.dir.as_ref().is_none_or(|d| *d == dir))
+            .cloned()
+            .collect();
+
+        let file = dir.join(IGNORE_FILE_NAME);
+        if file.is_file() {
+            rules.extend(parse_ignore_file(&file).context(ErrorKind::Config)?);
+        }
+        Ok(Self { rules })
+    }
+
+    /// The rule that ignores `name`, or `None` if no rule matches (or the last match is a `!` rule).
+    pub fn matching_rule(&self, name: &str) -> Option<&str> {
+        let rule = self.rules.iter().rev().find(|r| r.matcher.is_match(name))?;
+        (!rule.negate).then_some(rule.origin.as_str())
+    }
+
+    /// Split the binaries scanned from one directory into kept and ignored.
+    pub fn partition(&self, binaries: Vec<BinaryInfo>) -> (Vec<BinaryInfo>, Vec<Ignored>) {
+        let mut kept = Vec::new();
+        let mut ignored = Vec::new();
+        for bin in binaries {
+            match self.matching_rule(&bin.name) {
+                Some(rule) => ignored.push(Ignored {
+                    rule: rule.to_string(),
+                    bin,
+                }),
+                None => kept.push(bin),
+            }
+        }
+        (kept, ignored)
+    }
+}
+
+/// Read a `.bin-expire-ignore` file: one pattern per line, `#` comments, blank lines skipped,
+/// `!pattern` re-includes, and `\#` / `\!` escape a leading `#` or `!`.
+fn parse_ignore_file(path: &Path) -> Result<Vec<Rule>> {
+    let raw = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read ignore file: {}", path.display()))?;
+
+    let mut rules = Vec::new();
+    for (i, line) in raw.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (negate, pattern) = match line.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        let pattern = pattern.strip_prefix('\\').unwrap_or(pattern);
+        // Patterns match file names only, so a leading "/" anchor can't restrict a rule to the
+        // top level; it is accepted and ignored, and the rule matches at any depth.
+        let pattern = pattern.strip_prefix('/').unwrap_or(pattern);
+        if pattern.is_empty() {
+            continue;
+        }
+
+        let matcher = Matcher::parse(pattern)
+            .map_err(|err| anyhow!("{}:{}: {:#}", path.display(), i + 1, err))?;
+        rules.push(Rule {
+            matcher,
+            dir: None,
+            negate,
+            origin: format!("{}:{}: {}", path.display(), i + 1, line),
+        });
+    }
+    Ok(rules)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(patterns: &[&str]) -> IgnoreRules {
+        let config = Config {
+            ignored_bins: patterns
+                .iter()
+                .map(|p| IgnoreEntry::Pattern(p.to_string()))
+                .collect(),
+            ..Config::default()
+        };
+        IgnoreRules::from_config(&config).unwrap()
+    }
+
+    #[test]
+    fn matches_names_globs_and_regexes() {
+        let rules = rules(&["cargo", "cargo-*", "re:^go(pls|imports)$"]);
+        assert!(rules.matching_rule("cargo").is_some());
+        assert!(rules.matching_rule("cargo-watch").is_some());
+        assert!(rules.matching_rule("gopls").is_some());
+        assert!(rules.matching_rule("goimports").is_some());
+        assert!(rules.matching_rule("gofmt").is_none());
+        assert!(rules.matching_rule("rustc").is_none());
+    }
+
+    #[test]
+    fn invalid_patterns_are_config_errors() {
+        let config = Config {
+            ignored_bins: vec![IgnoreEntry::Pattern("re:(".to_string())],
+            ..Config::default()
+        };
+        let err = IgnoreRules::from_config(&config).err().unwrap();
+        assert_eq!(err.downcast_ref::<ErrorKind>(), Some(&ErrorKind::Config));
+    }
+}
@@ -0,0 +1,154 @@
+use std::fmt;
+use std::path::Path;
+
+/// A process that is executing or has mapped a file we were about to move.
+#[derive(Debug, Clone)]
+pub struct User {
+    pub pid: u32,
+    pub command: String,
+    /// `true` if the file is the process's executable, `false` if it is only mapped
+    /// (e.g. a shared library or a binary loaded by another program).
+    pub is_exe: bool,
+}
+
+impl fmt::Display for User {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let how = if self.is_exe {
+            "running as"
+        } else {
+            "mapped by"
+        };
+        write!(f, "{} PID {} ({})", how, self.pid, self.command)
+    }
+}
+
+/// Snapshot of the files processes are running or mapping right now.
+/// Identifies files by device and inode, so renamed or replaced paths don't confuse it.
+pub struct InUse {
+    #[cfg(target_os = "linux")]
+    files: std::collections::HashMap<(u64, u64), User>,
+}
+
+impl InUse {
+    /// Read /proc once. Processes we aren't allowed to inspect (other users' without root)
+    /// are skipped silently. On platforms without /proc nothing is reported.
+    pub fn snapshot() -> Self {
+        #[cfg(target_os = "linux")]
+        {
+            InUse {
+                files: linux::scan_proc(),
+            }
+        }
+        #[cfg(not(target_os = "linux"))]
+        {
+            InUse {}
+        }
+    }
+
+    /// The process using `path` (following symlinks), if any.
+    pub fn user_of(&self, path: &Path) -> Option<&User> {
+        #[cfg(target_os = "linux")]
+        {
+            use std::os::unix::fs::MetadataExt;
+            let meta = std::fs::metadata(path).ok()?;
+            self.files.get(&(meta.dev(), meta.ino()))
+        }
+        #[cfg(not(target_os = "linux"))]
+        {
+            let _ = path;
+            None
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use super::User;
+    use std::collections::HashMap;
+    use std::fs;
+    use std::os::unix::fs::MetadataExt;
+
+    pub fn scan_proc() -> HashMap<(u64, u64), User> {
+        let mut files = HashMap::new();
+        let Ok(entries) = fs::read_dir("/proc") else {
+            return files;
+        };
+        let own_pid = std::process::id();
+
+        for entry in entries.flatten() {
+            let Some(pid) = entry
+                .file_name()
+                .to_str()
+                .and_then(|s| s.parse::<u32>().ok())
+            else {
+                continue;
+            };
+            if pid == own_pid {
+                continue;
+            }
+            let dir = entry.path();
+            let command = fs::read_to_string(dir.join("comm"))
+                .map(|s| s.trim().to_string())
+                .unwrap_or_else(|_| "?".to_string());
+
+            // /proc/<pid>/exe resolves to the running file even if it was deleted or renamed.
+            if let Ok(meta) = fs::metadata(dir.join("exe")) {
+                files.insert(
+                    (meta.dev(), meta.ino()),
+                    User {
+                        pid,
+                        command: command.clone(),
+                        is_exe: true,
+                    },
+                );
+            }
+
+            let Ok(maps) = fs::read_to_string(dir.join("maps")) else {
+                continue;
+            };
+            for key in maps.lines().filter_map(parse_maps_line) {
+                files.entry(key).or_insert_with(|| User {
+                    pid,
+                    command: command.clone(),
+                    is_exe: false,
+                });
+            }
+        }
+        files
+    }
+
+    /// `address perms offset dev inode path` -> (st_dev, inode) for file-backed mappings.
+    pub(super) fn parse_maps_line(line: &str) -> Option<(u64, u64)> {
+        let mut fields = line.split_whitespace();
+        let dev = fields.nth(3)?;
+        let inode: u64 = fields.next()?.parse().ok()?;
+        if inode == 0 {
+            return None;
+        }
+        let (major, minor) = dev.split_once(':')?;
+        let major = u64::from_str_radix(major, 16).ok()?;
+        let minor = u64::from_str_radix(minor, 16).ok()?;
+        Some((makedev(major, minor), inode))
+    }
+
+    /// glibc's `makedev`, so map entries compare equal to `st_dev`.
+    fn makedev(major: u64, minor: u64) -> u64 {
+        ((major & 0xffff_f000) << 32)
+            | ((major & 0x0000_0fff) << 8)
+            | ((minor & 0xffff_ff00) << 12)
+            | (minor & 0x0000_00ff)
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::linux::parse_maps_line;
+
+    #[test]
+    fn parses_file_backed_mappings() {
+        let line = "7f2c1a000000-7f2c1a022000 r--p 00000000 fd:01 1314106    /usr/lib/libc.so.6";
+        assert_eq!(parse_maps_line(line), Some((0xfd01, 1314106)));
+        let anon = "7ffd5e1d3000-7ffd5e1f4000 rw-p 00000000 00:00 0          [stack]";
+        assert_eq!(parse_maps_line(anon), None);
+    }
+}
@@ -0,0 +1,146 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use super::Details;
+use crate::config::scan_cache_file_path;
+use crate::fs_util::replace_file;
+
+/// Bump when `Details` changes meaning, so old entries are recomputed rather than trusted.
+const CACHE_VERSION: u32 = 1;
+
+/// Identifies one version of a file's contents without reading it. Any write, rename over,
+/// chmod or replacement changes ctime (or the inode), while running a binary only touches
+/// atime and leaves the key alone.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct Key {
+    dev: u64,
+    ino: u64,
+    size: u64,
+    ctime: i64,
+    ctime_nsec: i64,
+}
+
+impl Key {
+    #[cfg(unix)]
+    fn of(path: &Path) -> Option<Key> {
+        use std::os::unix::fs::MetadataExt;
+        let meta = fs::metadata(path).ok()?;
+        Some(Key {
+            dev: meta.dev(),
+            ino: meta.ino(),
+            size: meta.len(),
+            ctime: meta.ctime(),
+            ctime_nsec: meta.ctime_nsec(),
+        })
+    }
+
+    /// No inode or change time in std on Windows; size plus creation and modification time
+    /// is the closest equivalent.
+    #[cfg(not(unix))]
+    fn of(path: &Path) -> Option<Key> {
+        let meta = fs::metadata(path).ok()?;
+        let nanos = |t: std::io::Result<std::time::SystemTime>| {
+            t.ok()
+                .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+                .map_or(0, |d| d.as_nanos() as i64)
+        };
+        Some(Key {
+            dev: 0,
+            ino: 0,
+            size: meta.len(),
+            ctime: nanos(meta.created()),
+            ctime_nsec: nanos(meta.modified()),
+        })
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Entry {
+    key: Key,
+    details: Details,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Store {
+    version: u32,
+    /// Keyed by path as scanned.
+    files: BTreeMap<String, Entry>,
+}
+
+/// Per-file `inspect` results from earlier scans, in scan-cache.json.
+pub struct ScanCache {
+    store: Store,
+    /// Paths inspected in this run.
+    inspected: Vec<String>,
+}
+
+impl ScanCache {
+    /// An unreadable, corrupt or outdated cache is treated as empty.
+    pub fn load() -> Self {
+        let store = fs::read_to_string(scan_cache_file_path())
+            .ok()
+            .and_then(|raw| serde_json::from_str::<Store>(&raw).ok())
+            .filter(|store| store.version == CACHE_VERSION)
+            .unwrap_or(Store {
+                version: CACHE_VERSION,
+                files: BTreeMap::new(),
+            });
+        ScanCache {
+            store,
+            inspected: Vec::new(),
+        }
+    }
+
+    /// Details from an earlier scan, if `path` hasn't changed since.
+    pub fn cached(&self, path: &Path) -> Option<Details> {
+        let key = Key::of(path)?;
+        self.store
+            .files
+            .get(&path.display().to_string())
+            .filter(|e| e.key == key)
+            .map(|e| e.details.clone())
+    }
+
+    /// Remember freshly inspected details for `path`.
+    pub fn insert(&mut self, path: &Path, details: Details) {
+        let Some(key) = Key::of(path) else {
+            return;
+        };
+        let name = path.display().to_string();
+        self.store
+            .files
+            .insert(name.clone(), Entry { key, details });
+        self.inspected.push(name);
+    }
+
+    /// Write the cache back if anything changed, dropping entries for files that are gone.
+    pub fn save(mut self) -> Result<()> {
+        let before = self.store.files.len();
+        self.store.files.retain(|path, _| Path::new(path).exists());
+        if self.inspected.is_empty() && self.store.files.len() == before {
+            return Ok(());
+        }
+
+        // Putting atime back after a read changes ctime, for every hard link to the file (rustup
+        // proxies are all one inode). Key what was inspected on the files as this run left them.
+        for name in &self.inspected {
+            if let (Some(entry), Some(key)) =
+                (self.store.files.get_mut(name), Key::of(Path::new(name)))
+            {
+                entry.key = key;
+            }
+        }
+
+        let path = scan_cache_file_path();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).with_context(|| {
+                format!("Failed to create cache directory: {}", parent.display())
+            })?;
+        }
+        let raw = serde_json::to_string(&self.store).context("Failed to serialize scan cache")?;
+        replace_file(&path, raw)
+    }
+}
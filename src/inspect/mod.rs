@@ -0,0 +1,309 @@
+pub mod cache;
+
+use anyhow::{Context, Result};
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+use std::time::SystemTime;
+
+use crate::models::BinaryInfo;
+use crate::pool;
+use crate::say;
+
+/// Start of the section the Go linker writes module and toolchain info into.
+const GO_BUILDINFO_MAGIC: &[u8] = b"\xff Go buildinf:";
+const GO_BUILDINFO_HEADER: usize = 32;
+/// The toolchain version and module info follow the header; this is plenty for both.
+const GO_BUILDINFO_MAX: usize = 64 * 1024;
+
+/// What reading the whole file tells us. Expensive for big binaries, hence `cache`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Details {
+    /// "elf", "mach-o", "pe", "script", "empty" or "other" (from the first bytes).
+    pub kind: String,
+    /// Hex SHA-256 of the contents.
+    pub sha256: String,
+    /// Toolchain of a Go binary, e.g. "go1.22.1".
+    pub go_version: Option<String>,
+    /// Main module of a Go binary, e.g. "golang.org/x/tools/gopls".
+    pub go_module: Option<String>,
+}
+
+impl Details {
+    /// "elf", or "elf (go1.22.1)" for Go binaries; used in the KIND column.
+    pub fn label(&self) -> String {
+        match &self.go_version {
+            Some(version) => format!("{} ({})", self.kind, version),
+            None => self.kind.clone(),
+        }
+    }
+}
+
+fn kind_of(head: &[u8]) -> &'static str {
+    match head {
+        [] => "empty",
+        [0x7f, b'E', b'L', b'F', ..] => "elf",
+        [0xfe, 0xed, 0xfa, 0xce | 0xcf, ..]
+        | [0xce | 0xcf, 0xfa, 0xed, 0xfe, ..]
+        | [0xca, 0xfe, 0xba, 0xbe, ..] => "mach-o",
+        [b'M', b'Z', ..] => "pe",
+        [b'#', b'!', ..] => "script",
+        _ => "other",
+    }
+}
+
+/// Unsigned LEB128, as used by Go's buildinfo strings.
+fn read_uvarint(data: &[u8]) -> Option<(usize, &[u8])> {
+    let mut value: usize = 0;
+    for (i, byte) in data.iter().enumerate().take(9) {
+        value |= ((byte & 0x7f) as usize) << (7 * i);
+        if byte & 0x80 == 0 {
+            return Some((value, &data[i + 1..]));
+        }
+    }
+    None
+}
+
+fn read_bytes(data: &[u8]) -> Option<(&[u8], &[u8])> {
+    let (len, rest) = read_uvarint(data)?;
+    Some((rest.get(..len)?, rest.get(len..)?))
+}
+
+/// Parse a Go 1.18+ buildinfo blob (starting at the magic): toolchain version and main module.
+/// Older binaries store pointers instead of inline strings and are not decoded.
+fn parse_go_buildinfo(data: &[u8]) -> Option<(String, Option<String>)> {
+    let flags = *data.get(15)?;
+    if flags & 0x2 == 0 {
+        return None;
+    }
+    let (version, rest) = read_bytes(data.get(GO_BUILDINFO_HEADER..)?)?;
+    let version = String::from_utf8_lossy(version).into_owned();
+    if !version.starts_with("go") {
+        return None;
+    }
+    // Module info is wrapped in two 16-byte sentinels.
+    let module = read_bytes(rest)
+        .and_then(|(modinfo, _)| modinfo.get(16..modinfo.len().checked_sub(16)?))
+        .and_then(|modinfo| {
+            String::from_utf8_lossy(modinfo)
+                .lines()
+                .find_map(|line| line.strip_prefix("path\t"))
+                .map(str::to_string)
+        });
+    Some((version, module))
+}
+
+/// Open `path` without moving its atime: O_NOATIME, which the kernel only allows for the
+/// file's owner (or root). Other users' files are not opened at all (`None`). The second value
+/// is an atime to put back after reading; never needed here.
+#[cfg(target_os = "linux")]
+fn open_preserving_atime(path: &Path) -> Result<Option<(File, Option<SystemTime>)>> {
+    use std::os::unix::fs::OpenOptionsExt;
+    match fs::OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_NOATIME)
+        .open(path)
+    {
+        Ok(file) => Ok(Some((file, None))),
+        Err(err) if err.raw_os_error() == Some(libc::EPERM) => Ok(None),
+        Err(err) => Err(err).with_context(|| format!("Failed to open {}", path.display())),
+    }
+}
+
+/// Without O_NOATIME the atime has to be put back after reading, which only the file's owner
+/// (or root) may do, so other users' files are not opened at all (`None`). The second value is
+/// the atime to put back.
+#[cfg(not(target_os = "linux"))]
+fn open_preserving_atime(path: &Path) -> Result<Option<(File, Option<SystemTime>)>> {
+    let meta = fs::metadata(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        // SAFETY: geteuid has no preconditions and cannot fail.
+        let euid = unsafe { libc::geteuid() };
+        if euid != 0 && meta.uid() != euid {
+            return Ok(None);
+        }
+    }
+    let accessed = meta
+        .accessed()
+        .with_context(|| format!("Failed to read the access time of {}", path.display()))?;
+    let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    Ok(Some((file, Some(accessed))))
+}
+
+/// Read `path` once: sniff its kind, hash it and decode Go build info.
+///
+/// Reading a file would update its atime, which is what the stale check relies on, so it is
+/// opened with `open_preserving_atime`. `None` means it was left unread because its atime
+/// couldn't be kept. Where the atime has to be put back by hand, a use of the file during the
+/// read is overwritten too; that is why reading is opt-in (`scan --inspect`).
+pub fn inspect(path: &Path) -> Result<Option<Details>> {
+    let Some((mut file, restore_atime)) = open_preserving_atime(path)? else {
+        return Ok(None);
+    };
+
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 64 * 1024];
+    let mut head: Vec<u8> = Vec::new();
+    // The magic can straddle two reads; keep the previous read's tail.
+    let mut tail: Vec<u8> = Vec::new();
+    let mut offset: u64 = 0;
+    // The linker aligns the section to 16 bytes; other copies of the magic (e.g. in Go's own
+    // debug/buildinfo package) usually aren't.
+    let mut buildinfo_at: Vec<u64> = Vec::new();
+
+    loop {
+        let n = file
+            .read(&mut buf)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        if n == 0 {
+            break;
+        }
+        let chunk = &buf[..n];
+        hasher.update(chunk);
+        if head.len() < 4 {
+            head.extend(chunk.iter().take(4 - head.len()));
+        }
+
+        let window_start = offset - tail.len() as u64;
+        tail.extend_from_slice(chunk);
+        for (pos, _) in tail
+            .windows(GO_BUILDINFO_MAGIC.len())
+            .enumerate()
+            .filter(|(_, w)| *w == GO_BUILDINFO_MAGIC)
+        {
+            let at = window_start + pos as u64;
+            if at.is_multiple_of(16) && !buildinfo_at.contains(&at) {
+                buildinfo_at.push(at);
+            }
+        }
+        let keep = GO_BUILDINFO_MAGIC.len() - 1;
+        tail.drain(..tail.len().saturating_sub(keep));
+        offset += n as u64;
+    }
+
+    let mut go = None;
+    for at in buildinfo_at {
+        let mut blob = Vec::new();
+        if file.seek(SeekFrom::Start(at)).is_err()
+            || (&mut file)
+                .take((GO_BUILDINFO_HEADER + GO_BUILDINFO_MAX) as u64)
+                .read_to_end(&mut blob)
+                .is_err()
+        {
+            continue;
+        }
+        go = parse_go_buildinfo(&blob);
+        if go.is_some() {
+            break;
+        }
+    }
+    drop(file);
+
+    // Only if the read moved it: setting atime also changes ctime.
+    if let Some(accessed) = restore_atime {
+        let accessed_now = fs::metadata(path).and_then(|m| m.accessed()).ok();
+        if accessed_now != Some(accessed) {
+            filetime::set_file_atime(path, filetime::FileTime::from_system_time(accessed))
+                .with_context(|| {
+                    format!(
+                        "Read {} but failed to put its access time back; it will look recently used",
+                        path.display()
+                    )
+                })?;
+        }
+    }
+
+    let (go_version, go_module) = match go {
+        Some((version, module)) => (Some(version), module),
+        None => (None, None),
+    };
+    Ok(Some(Details {
+        kind: kind_of(&head).to_string(),
+        sha256: format!("{:x}", hasher.finalize()),
+        go_version,
+        go_module,
+    }))
+}
+
+/// Fill in `details` for every binary from the cache where the file hasn't changed since it
+/// was last inspected. With `read`, the rest are read on up to `threads` workers; without it
+/// nothing is opened.
+pub fn annotate(binaries: &mut [BinaryInfo], read: bool, use_cache: bool, threads: usize) {
+    let mut cache = use_cache.then(cache::ScanCache::load);
+
+    let mut todo = Vec::new();
+    for (i, bin) in binaries.iter_mut().enumerate() {
+        if bin.is_broken_link {
+            continue;
+        }
+        match cache.as_ref().and_then(|c| c.cached(&bin.path)) {
+            Some(details) => bin.details = Some(details),
+            None if read => todo.push(i),
+            None => {}
+        }
+    }
+
+    let paths: Vec<&Path> = todo.iter().map(|&i| binaries[i].path.as_path()).collect();
+    let found = pool::map(&paths, threads, |path| inspect(path));
+    let mut not_owned = 0;
+    for (i, result) in todo.into_iter().zip(found) {
+        match result {
+            Ok(Some(details)) => {
+                if let Some(cache) = cache.as_mut() {
+                    cache.insert(&binaries[i].path, details.clone());
+                }
+                binaries[i].details = Some(details);
+            }
+            Ok(None) => not_owned += 1,
+            Err(err) => eprintln!("{} {:#}", "[!]".yellow(), err),
+        }
+    }
+    if not_owned > 0 {
+        say!(
+            "{} Not inspecting {} binaries owned by other users: reading them would mark them as used.",
+            "[i]".blue(),
+            not_owned
+        );
+    }
+
+    if let Some(cache) = cache {
+        // The cache only saves time; never fail the scan over it.
+        if let Err(err) = cache.save() {
+            eprintln!("{} Failed to save scan cache: {:#}", "[!]".yellow(), err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_go_buildinfo() {
+        let mut blob = GO_BUILDINFO_MAGIC.to_vec();
+        blob.extend([8, 0x2]);
+        blob.resize(GO_BUILDINFO_HEADER, 0);
+        let version = b"go1.22.1";
+        blob.push(version.len() as u8);
+        blob.extend(version);
+        let modinfo = "0123456789abcdefpath\tgolang.org/x/tools/gopls\nmod\tgolang.org/x/tools/gopls\tv0.15.0\t\n0123456789abcdef";
+        blob.push(modinfo.len() as u8);
+        blob.extend(modinfo.as_bytes());
+
+        assert_eq!(
+            parse_go_buildinfo(&blob),
+            Some((
+                "go1.22.1".to_string(),
+                Some("golang.org/x/tools/gopls".to_string())
+            ))
+        );
+        assert_eq!(kind_of(b"\x7fELF\x02"), "elf");
+        assert_eq!(kind_of(b"#!/bin/sh"), "script");
+        assert_eq!(kind_of(b""), "empty");
+    }
+}
@@ -0,0 +1,20 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Once;
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+static INSTALL: Once = Once::new();
+
+/// Replace the default SIGINT/Ctrl-C behavior (terminate immediately) with a flag that
+/// long-running operations poll between items, so a file is never left half-moved.
+pub fn install_handler() {
+    INSTALL.call_once(|| {
+        // Best-effort: if a handler can't be installed, Ctrl-C keeps its default behavior.
+        let _ = ctrlc::set_handler(|| {
+            INTERRUPTED.store(true, Ordering::SeqCst);
+        });
+    });
+}
+
+pub fn requested() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
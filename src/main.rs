@@ -1,58 +1,271 @@
 mod analyzer;
 mod archive_manifest;
+mod archive_plan;
 mod archiver;
+mod caches;
 mod cli;
 mod commands;
 mod config;
+mod eligibility;
+mod exit_status;
 mod fs_scanner;
+mod fs_util;
+mod history;
+mod ignore;
+mod in_use;
+mod inspect;
+mod interrupt;
 mod models;
+mod output;
+mod pins;
+mod pool;
+mod projects;
+mod references;
+mod runtimes;
+mod threshold;
+mod toolchains;
 mod ui;
 
-use crate::config::load_config;
-use anyhow::Result;
+use crate::config::{load_config, Overrides};
+use anyhow::{Context, Result};
 use clap::Parser;
+use std::process::ExitCode;
 
-use crate::cli::{Cli, Commands};
+use crate::cli::{Cli, Commands, ConfigAction};
+use crate::exit_status::{ErrorKind, ExitStatus};
+use crate::ui::table::SortKey;
 
-fn main() -> Result<()> {
+fn main() -> ExitCode {
     let cli = Cli::parse();
+    output::init(cli.output);
+
+    match run(&cli) {
+        Ok(status) => status.into(),
+        Err(err) => {
+            eprintln!("Error: {:?}", err);
+            ExitStatus::for_error(&err).into()
+        }
+    }
+}
+
+fn run(cli: &Cli) -> Result<ExitStatus> {
+    // `config` must work even when config.toml is broken, so it loads (or not) on its own.
+    let overrides = Overrides {
+        file: cli.config.as_deref(),
+        profile: cli.profile.as_deref(),
+    };
+    if let Commands::Config { action } = &cli.command {
+        return match action {
+            ConfigAction::Show => commands::config::show(overrides),
+            ConfigAction::Get { key } => commands::config::get(key, overrides),
+            ConfigAction::Set { key, value } => commands::config::set(key, value, overrides),
+            ConfigAction::Edit => commands::config::edit(overrides),
+            ConfigAction::Validate => commands::config::validate(overrides),
+            ConfigAction::Path => commands::config::path(overrides),
+        };
+    }
 
     // Load configuration (uses 'dirs' crate internally)
-    let config = load_config()?;
+    let mut config = load_config(overrides).context(ErrorKind::Config)?;
+    if cli.single_threaded {
+        config.scan_threads = 1;
+    } else if let Some(threads) = cli.threads {
+        config.scan_threads = threads;
+    }
+    if let Some(depth) = cli.max_depth {
+        config.max_depth = depth as usize;
+    }
+    if cli.one_file_system {
+        config.one_file_system = true;
+    }
 
     #[cfg(windows)]
     {
         ui::print_windows_notice(config.windows_use_access_time);
     }
 
-    match &cli.command {
+    let status = match &cli.command {
         Commands::Scan {
             dir,
             days,
+            older_than,
             verbose,
             only_stale,
             hide_ok,
             hide_stub,
-        } => {
-            commands::scan::run(
-                dir.clone(),
-                *days,
-                *verbose,
-                *only_stale,
-                *hide_ok,
-                *hide_stub,
-                &config,
-            )?;
-        }
+            fail_if_stale,
+            projects,
+            inspect,
+            no_cache,
+            sort,
+            reverse,
+            group_by,
+            columns,
+        } => commands::scan::run(
+            commands::scan::ScanArgs {
+                dir: dir.clone(),
+                threshold: threshold::explicit(*older_than, *days)?,
+                verbose: *verbose,
+                only_stale: *only_stale,
+                hide_ok: *hide_ok,
+                hide_stub: *hide_stub,
+                fail_if_stale: *fail_if_stale,
+                projects: *projects,
+                save_history: true,
+                inspect: *inspect,
+                use_cache: !*no_cache,
+                sort: *sort,
+                reverse: *reverse,
+                group_by: *group_by,
+                columns: columns.clone(),
+            },
+            &config,
+        )?,
 
-        Commands::Archive { dir, days } => {
-            commands::archive::run(dir.clone(), *days, &config)?;
-        }
+        Commands::Check {
+            dir,
+            days,
+            older_than,
+        } => commands::scan::run(
+            commands::scan::ScanArgs {
+                dir: dir.clone(),
+                threshold: threshold::explicit(*older_than, *days)?,
+                verbose: false,
+                only_stale: true,
+                hide_ok: true,
+                hide_stub: true,
+                fail_if_stale: true,
+                projects: false,
+                save_history: false,
+                inspect: false,
+                use_cache: false,
+                sort: SortKey::Name,
+                reverse: false,
+                group_by: None,
+                columns: None,
+            },
+            &config,
+        )?,
 
-        Commands::Restore { name } => {
-            commands::restore::run(name, &config)?;
-        }
-    }
+        Commands::Archive {
+            targets,
+            dir,
+            days,
+            older_than,
+            atomic,
+            dry_run,
+            plan,
+            apply,
+            yes,
+            interactive,
+        } => commands::archive::run(
+            commands::archive::ArchiveArgs {
+                targets: targets.clone(),
+                dir: dir.clone(),
+                threshold: threshold::explicit(*older_than, *days)?,
+                atomic: *atomic,
+                dry_run: *dry_run,
+                plan: plan.clone(),
+                apply: apply.clone(),
+                yes: *yes,
+                interactive: *interactive,
+            },
+            &config,
+        )?,
+
+        Commands::Toolchains {
+            days,
+            older_than,
+            archive,
+            remove,
+            dry_run,
+            yes,
+        } => commands::toolchains::run(
+            commands::toolchains::ToolchainsArgs {
+                threshold: threshold::explicit(*older_than, *days)?,
+                archive: *archive,
+                remove: *remove,
+                dry_run: *dry_run,
+                yes: *yes,
+            },
+            &config,
+        )?,
+
+        Commands::Runtimes {
+            days,
+            older_than,
+            archive,
+            dry_run,
+            yes,
+        } => commands::runtimes::run(
+            commands::runtimes::RuntimesArgs {
+                threshold: threshold::explicit(*older_than, *days)?,
+                archive: *archive,
+                dry_run: *dry_run,
+                yes: *yes,
+            },
+            &config,
+        )?,
+
+        Commands::Caches {
+            days,
+            older_than,
+            verbose,
+            delete,
+            dry_run,
+            yes,
+        } => commands::caches::run(
+            commands::caches::CachesArgs {
+                threshold: threshold::explicit(*older_than, *days)?,
+                verbose: *verbose,
+                delete: *delete,
+                dry_run: *dry_run,
+                yes: *yes,
+            },
+            &config,
+        )?,
+
+        Commands::Sweep {
+            dirs,
+            days,
+            older_than,
+            archive,
+            remove,
+            dry_run,
+            yes,
+        } => commands::sweep::run(
+            commands::sweep::SweepArgs {
+                dirs: dirs.clone(),
+                threshold: threshold::explicit(*older_than, *days)?,
+                archive: *archive,
+                remove: *remove,
+                dry_run: *dry_run,
+                yes: *yes,
+            },
+            &config,
+        )?,
+
+        Commands::List => commands::list::run()?,
+
+        Commands::Restore { name } => commands::restore::run(name, &config)?,
+
+        Commands::Pin {
+            name,
+            reason,
+            until,
+        } => match name {
+            Some(name) => commands::pin::pin(name, reason.clone(), *until)?,
+            None => commands::pin::list()?,
+        },
+
+        Commands::Unpin { name } => commands::pin::unpin(name)?,
+
+        Commands::History { name, sizes } => commands::history::history(name.as_deref(), *sizes)?,
+
+        Commands::Diff { from, to } => commands::history::diff(*from, *to)?,
+
+        Commands::Config { .. } => unreachable!("handled before loading the config"),
+    };
 
-    Ok(())
+    Ok(status)
 }
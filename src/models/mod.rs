@@ -8,6 +8,56 @@ pub enum LastUsedSource {
     Unknown,
 }
 
+impl LastUsedSource {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LastUsedSource::Accessed => "atime",
+            LastUsedSource::Modified => "mtime",
+            LastUsedSource::Unknown => "unknown",
+        }
+    }
+}
+
+/// How a scanned binary was classified.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryStatus {
+    Ok,
+    Stale,
+    /// A 0-byte .exe App Execution Alias placeholder; never archived.
+    Stub,
+    /// A symlink whose target no longer exists.
+    Broken,
+    /// Protected by `bin-expire pin` (never archived while the pin is in effect).
+    Pinned,
+    /// Stale by last_used, but run from a unit file, crontab, shell rc, git or ssh config.
+    Referenced,
+}
+
+impl BinaryStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            BinaryStatus::Ok => "ok",
+            BinaryStatus::Stale => "stale",
+            BinaryStatus::Stub => "stub",
+            BinaryStatus::Broken => "broken",
+            BinaryStatus::Pinned => "pinned",
+            BinaryStatus::Referenced => "referenced",
+        }
+    }
+
+    /// Short glyph used in scan tables (kept to one column for stable alignment).
+    pub fn glyph(self) -> &'static str {
+        match self {
+            BinaryStatus::Ok => "✓",
+            BinaryStatus::Stale => "✗",
+            BinaryStatus::Stub => "·",
+            BinaryStatus::Broken => "!",
+            BinaryStatus::Pinned => "P",
+            BinaryStatus::Referenced => "R",
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct BinaryInfo {
     pub name: String,
@@ -18,14 +68,57 @@ pub struct BinaryInfo {
     pub last_used: SystemTime,
     pub last_used_source: LastUsedSource,
     pub _is_symlink: bool,
+    /// A symlink whose target is missing (times/size come from the link itself).
+    pub is_broken_link: bool,
+    /// Kind, hash and build info; only filled in by `scan`.
+    pub details: Option<crate::inspect::Details>,
+}
+
+/// One `ignored_bins` entry: a name, glob (`cargo-*`) or regex (`re:^gopls$`), optionally
+/// scoped to a single directory with `{ pattern = "...", dir = "~/go/bin" }`.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(untagged)]
+pub enum IgnoreEntry {
+    Pattern(String),
+    Scoped { pattern: String, dir: String },
 }
 
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
 #[serde(default)]
 pub struct Config {
-    pub ignored_bins: Vec<String>,
+    /// Schema version of config.toml; older files are migrated step by step on load.
+    pub config_version: i64,
+    /// Profile used when neither `--profile` nor `BIN_EXPIRE_PROFILE` is given.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_profile: Option<String>,
+    /// Directories scanned when `-p/--path` is not given. Entries may contain wildcards
+    /// (`~/.local/share/*/bin`).
+    pub scan_dirs: Vec<String>,
+    /// How deep to look inside each scan dir: 1 = only the files directly in it.
+    pub max_depth: usize,
+    /// Don't descend into directories on another filesystem than the scan dir.
+    pub one_file_system: bool,
+    /// Source trees searched by `scan --projects` (e.g. ["~/src"]).
+    pub project_roots: Vec<String>,
+    pub ignored_bins: Vec<IgnoreEntry>,
+    /// Default stale threshold as a duration string ("90d", "2w", "6months").
+    /// Takes precedence over `default_threshold_days` when set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_threshold: Option<String>,
+    /// Legacy whole-day threshold, used when `default_threshold` is not set.
     pub default_threshold_days: i64,
+    /// Floor for every threshold (config, environment or command line), e.g. set by an admin
+    /// in the system config. Shorter thresholds are raised to it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_threshold: Option<String>,
+    /// `archive` only moves a binary after this many runs (`scan` or `archive`) saw it stale.
+    pub confirm_stale_scans: u32,
+    /// ... and the first of those runs was at least this long ago ("7d").
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub confirm_stale_period: Option<String>,
     pub archive_path: PathBuf,
+    /// Worker threads for reading file metadata and contents during a scan; 0 = one per CPU.
+    pub scan_threads: usize,
     /// On Windows, prefer `atime` (last access time) over `mtime` when selecting `last_used`.
     /// This can reduce false positives for frequently-run tools, but depends on NTFS last access updates.
     pub windows_use_access_time: bool,
@@ -34,9 +127,20 @@ pub struct Config {
 impl Default for Config {
     fn default() -> Self {
         Self {
+            config_version: crate::config::migrate::CONFIG_VERSION,
+            default_profile: None,
+            scan_dirs: vec!["~/.cargo/bin".to_string(), "~/go/bin".to_string()],
+            project_roots: Vec::new(),
+            max_depth: 1,
+            one_file_system: false,
             ignored_bins: vec![],
+            default_threshold: None,
             default_threshold_days: 90,
+            min_threshold: None,
+            confirm_stale_scans: 1,
+            confirm_stale_period: None,
             archive_path: PathBuf::from(".bin-expire/archive"),
+            scan_threads: 0,
             windows_use_access_time: true,
         }
     }
@@ -0,0 +1,395 @@
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use serde::Serialize;
+use std::io::{self, Write};
+use std::sync::OnceLock;
+
+/// Version of the machine-readable record layout. Bump when fields are renamed or removed
+/// (adding fields is backwards compatible).
+pub const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum OutputFormat {
+    /// Human-readable tables and messages
+    #[default]
+    Table,
+    /// A single JSON document: {"schema_version", "command", "records": [...]}
+    Json,
+    /// One JSON record per line (each includes "schema_version" and "command")
+    Ndjson,
+    /// Comma-separated values with a header row
+    Csv,
+}
+
+static FORMAT: OnceLock<OutputFormat> = OnceLock::new();
+
+pub fn init(format: OutputFormat) {
+    let _ = FORMAT.set(format);
+}
+
+pub fn format() -> OutputFormat {
+    FORMAT.get().copied().unwrap_or_default()
+}
+
+/// True when stdout is reserved for records, so human-readable chatter must go to stderr.
+pub fn is_machine() -> bool {
+    format() != OutputFormat::Table
+}
+
+/// Where human-readable messages go: stdout for tables, stderr for machine-readable output.
+pub fn human() -> Box<dyn Write> {
+    if is_machine() {
+        Box::new(io::stderr())
+    } else {
+        Box::new(io::stdout())
+    }
+}
+
+/// `println!` for human-readable chatter. Goes to stderr when `--output` is machine-readable,
+/// so stdout can be piped into tools like jq.
+#[macro_export]
+macro_rules! say {
+    ($($arg:tt)*) => {
+        if $crate::output::is_machine() {
+            eprintln!($($arg)*);
+        } else {
+            println!($($arg)*);
+        }
+    };
+}
+
+#[derive(Serialize)]
+struct Document<'a, T: Serialize> {
+    schema_version: u32,
+    command: &'a str,
+    records: &'a [T],
+}
+
+#[derive(Serialize)]
+struct Line<'a, T: Serialize> {
+    schema_version: u32,
+    command: &'a str,
+    #[serde(flatten)]
+    record: &'a T,
+}
+
+/// Write `records` to stdout in the selected machine-readable format.
+/// Does nothing for `--output table` (commands render their own tables).
+pub fn emit<T: Serialize>(command: &str, records: &[T]) -> Result<()> {
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+
+    match format() {
+        OutputFormat::Table => {}
+        OutputFormat::Json => {
+            let doc = Document {
+                schema_version: SCHEMA_VERSION,
+                command,
+                records,
+            };
+            serde_json::to_writer_pretty(&mut out, &doc).context("Failed to write JSON")?;
+            writeln!(out)?;
+        }
+        OutputFormat::Ndjson => {
+            for record in records {
+                let line = Line {
+                    schema_version: SCHEMA_VERSION,
+                    command,
+                    record,
+                };
+                serde_json::to_writer(&mut out, &line).context("Failed to write NDJSON")?;
+                writeln!(out)?;
+            }
+        }
+        OutputFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(out);
+            for record in records {
+                writer.serialize(record).context("Failed to write CSV")?;
+            }
+            writer.flush()?;
+        }
+    }
+
+    Ok(())
+}
+
+fn format_time(t: Option<std::time::SystemTime>) -> Option<String> {
+    t.map(|t| humantime::format_rfc3339_seconds(t).to_string())
+}
+
+/// One row of `scan` output.
+#[derive(Debug, Serialize)]
+pub struct ScanRecord {
+    pub name: String,
+    pub status: &'static str,
+    pub size: u64,
+    pub last_used: String,
+    /// Where `last_used` came from: "atime", "mtime" or "unknown".
+    pub last_used_source: &'static str,
+    pub accessed: Option<String>,
+    pub modified: Option<String>,
+    pub path: String,
+    /// The ignore rule that matched (only for status "ignored", listed with --verbose).
+    pub ignored_by: Option<String>,
+    /// Only for status "pinned".
+    pub pin_reason: Option<String>,
+    pub pinned_until: Option<String>,
+    /// Only for status "referenced": "file:line (kind)" of the first mention.
+    pub referenced_by: Option<String>,
+    /// With `scan --projects`: projects whose build or CI files use this stale binary,
+    /// "project (file:line)" separated by "; ".
+    pub used_by_projects: Option<String>,
+    /// Only for status "stale" when confirm_stale_scans / confirm_stale_period are set: runs
+    /// that have seen it stale in a row, and whether that is enough for `archive`.
+    pub stale_scans: Option<u32>,
+    pub confirmed_stale: Option<bool>,
+    /// From reading the file (scan only): "elf", "mach-o", "pe", "script", "empty" or "other".
+    pub kind: Option<String>,
+    pub sha256: Option<String>,
+    /// Go binaries only: toolchain version and main module from the embedded build info.
+    pub go_version: Option<String>,
+    pub go_module: Option<String>,
+}
+
+impl ScanRecord {
+    pub fn new(bin: &crate::models::BinaryInfo, status: crate::models::BinaryStatus) -> Self {
+        Self {
+            name: bin.name.clone(),
+            status: status.as_str(),
+            size: bin.size,
+            last_used: humantime::format_rfc3339_seconds(bin.last_used).to_string(),
+            last_used_source: bin.last_used_source.as_str(),
+            accessed: format_time(bin.accessed),
+            modified: format_time(bin.modified),
+            path: bin.path.display().to_string(),
+            ignored_by: None,
+            pin_reason: None,
+            pinned_until: None,
+            referenced_by: None,
+            used_by_projects: None,
+            stale_scans: None,
+            confirmed_stale: None,
+            kind: bin.details.as_ref().map(|d| d.kind.clone()),
+            sha256: bin.details.as_ref().map(|d| d.sha256.clone()),
+            go_version: bin.details.as_ref().and_then(|d| d.go_version.clone()),
+            go_module: bin.details.as_ref().and_then(|d| d.go_module.clone()),
+        }
+    }
+
+    pub fn pinned(bin: &crate::models::BinaryInfo, pin: &crate::pins::Pin) -> Self {
+        Self {
+            pin_reason: pin.reason.clone(),
+            pinned_until: pin.until.clone(),
+            ..Self::new(bin, crate::models::BinaryStatus::Pinned)
+        }
+    }
+
+    pub fn referenced(
+        bin: &crate::models::BinaryInfo,
+        reference: &crate::references::Reference,
+    ) -> Self {
+        Self {
+            referenced_by: Some(reference.to_string()),
+            ..Self::new(bin, crate::models::BinaryStatus::Referenced)
+        }
+    }
+
+    pub fn ignored(ignored: &crate::ignore::Ignored) -> Self {
+        Self {
+            status: "ignored",
+            ignored_by: Some(ignored.rule.clone()),
+            ..Self::new(&ignored.bin, crate::models::BinaryStatus::Ok)
+        }
+    }
+}
+
+/// One item of `archive` output.
+#[derive(Debug, Serialize)]
+pub struct ArchiveRecord {
+    pub name: String,
+    /// "planned" (dry run / --plan), "moved", "skipped" (in use), "failed" or "rolled_back".
+    pub result: &'static str,
+    pub source: String,
+    pub destination: String,
+    pub size: u64,
+    pub error: Option<String>,
+}
+
+/// One item of `restore` output.
+#[derive(Debug, Serialize)]
+pub struct RestoreRecord {
+    pub name: String,
+    /// "restored" or "failed".
+    pub result: &'static str,
+    pub archived_path: String,
+    pub original_path: String,
+    pub error: Option<String>,
+}
+
+/// One config key (`config show` / `config get` output).
+#[derive(Debug, Serialize)]
+pub struct ConfigRecord {
+    pub key: String,
+    /// The effective value as TOML text; `None` when unset.
+    pub value: Option<String>,
+    /// "default" or the file the value was read from.
+    pub origin: String,
+}
+
+/// One pin (`pin` / `unpin` output).
+#[derive(Debug, Serialize)]
+pub struct PinRecord {
+    pub name: String,
+    pub reason: Option<String>,
+    pub until: Option<String>,
+    pub pinned_at: String,
+    pub expired: bool,
+}
+
+/// One binary across all recorded scans (`history` output).
+#[derive(Debug, Serialize)]
+pub struct HistoryRecord {
+    pub name: String,
+    pub path: String,
+    /// Status in the latest scan that covered its directory, or "gone".
+    pub status: String,
+    pub first_seen: String,
+    /// Start of the current stale stretch (only while status is "stale").
+    pub went_stale: Option<String>,
+    pub last_seen: String,
+    pub size: u64,
+    pub scans: usize,
+}
+
+/// Total size of one directory in one recorded scan (`history --sizes` output).
+#[derive(Debug, Serialize)]
+pub struct ScanSizeRecord {
+    pub scan: u64,
+    pub taken_at: String,
+    pub dir: String,
+    pub binaries: usize,
+    pub total_size: u64,
+    /// Change since the previous scan of the same directory (`None` for the first).
+    pub change: Option<i64>,
+}
+
+/// One binary in one recorded scan (`history <name>` output).
+#[derive(Debug, Serialize)]
+pub struct TimelineRecord {
+    pub scan: u64,
+    pub taken_at: String,
+    pub name: String,
+    pub path: String,
+    pub status: String,
+    pub size: u64,
+    pub last_used: String,
+}
+
+/// One change between two recorded scans (`diff` output).
+#[derive(Debug, Serialize)]
+pub struct DiffRecord {
+    /// "went_stale", "used_again", "added" or "removed".
+    pub change: &'static str,
+    pub name: String,
+    pub path: String,
+    pub status_before: Option<String>,
+    pub status_after: Option<String>,
+    pub last_used_before: Option<String>,
+    pub last_used_after: Option<String>,
+}
+
+/// One archived entry from the manifest (`list` output).
+#[derive(Debug, Serialize)]
+pub struct ListRecord {
+    pub name: String,
+    pub moved_at: String,
+    pub original_path: String,
+    pub archived_path: String,
+    /// Whether the archived file is still present on disk.
+    pub archived_exists: bool,
+}
+
+/// One installed toolchain in `toolchains` output.
+#[derive(Debug, Serialize)]
+pub struct ToolchainRecord {
+    pub name: String,
+    /// "default", "override", "project", "pinned", "linked", "stale" or "ok".
+    pub status: &'static str,
+    pub size: u64,
+    pub last_used: String,
+    /// Where `last_used` came from: "atime", "mtime" or "unknown".
+    pub last_used_source: &'static str,
+    /// What keeps it: the override directory, the project's rust-toolchain file or the pin.
+    pub kept_by: Option<String>,
+    pub path: String,
+    /// With --archive / --remove, for stale toolchains: "planned" (--dry-run), "archived",
+    /// "removed", "skipped" (in use) or "failed".
+    pub action: Option<&'static str>,
+    /// Where an archived toolchain went.
+    pub destination: Option<String>,
+    pub error: Option<String>,
+}
+
+/// One installed runtime version in `runtimes` output.
+#[derive(Debug, Serialize)]
+pub struct RuntimeRecord {
+    /// "pyenv/python@3.12.1"; what `pin` and `restore` take.
+    pub name: String,
+    /// "nvm", "pyenv", "rbenv", "asdf" or "mise".
+    pub manager: &'static str,
+    pub tool: String,
+    pub version: String,
+    /// "default", "project", "pinned", "stale" or "ok".
+    pub status: &'static str,
+    pub size: u64,
+    pub last_used: String,
+    /// Where `last_used` came from: "atime", "mtime" or "unknown".
+    pub last_used_source: &'static str,
+    /// The default or project version file that keeps it, or the pin.
+    pub kept_by: Option<String>,
+    pub path: String,
+    /// With --archive, for stale versions: "planned" (--dry-run), "archived", "skipped" (in use)
+    /// or "failed".
+    pub action: Option<&'static str>,
+    pub destination: Option<String>,
+    pub error: Option<String>,
+}
+
+/// One stale package cache entry in `caches` output.
+#[derive(Debug, Serialize)]
+pub struct CacheRecord {
+    /// "cargo-crates", "cargo-sources", "cargo-git" or "go-modules".
+    pub ecosystem: &'static str,
+    /// Relative to the cache directory, e.g. "github.com/spf13/cobra@v1.8.0".
+    pub name: String,
+    pub size: u64,
+    pub last_used: String,
+    /// Where `last_used` came from: "atime", "mtime" or "unknown".
+    pub last_used_source: &'static str,
+    pub path: String,
+    /// With --delete: "planned" (--dry-run), "removed" or "failed".
+    pub action: Option<&'static str>,
+    pub error: Option<String>,
+}
+
+/// One build output directory in `sweep` output.
+#[derive(Debug, Serialize)]
+pub struct SweepRecord {
+    /// "<project>/target" or "<project>/node_modules", with the project's path below its sweep
+    /// root; what `restore` takes.
+    pub name: String,
+    /// "cargo" or "node".
+    pub kind: &'static str,
+    /// "stale" (the project wasn't touched within the threshold) or "ok".
+    pub status: &'static str,
+    pub size: u64,
+    pub project: String,
+    /// Newest mtime of the project's own files.
+    pub project_touched: String,
+    pub path: String,
+    /// With --archive / --remove, for stale projects: "planned" (--dry-run), "archived",
+    /// "removed", "skipped" (in use) or "failed".
+    pub action: Option<&'static str>,
+    pub destination: Option<String>,
+    pub error: Option<String>,
+}
@@ -0,0 +1,159 @@
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::{Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+use crate::config::pins_file_path;
+use crate::fs_util::replace_file;
+use crate::threshold;
+
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// A binary protected from archiving, with why and (optionally) until when.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Pin {
+    pub name: String,
+    pub reason: Option<String>,
+    /// Last day (YYYY-MM-DD, local time) the pin is in effect; `None` pins forever.
+    pub until: Option<String>,
+    pub pinned_at: String,
+}
+
+impl Pin {
+    /// Expired pins stay in pins.json (so `pin` can show them) but no longer protect anything.
+    pub fn is_expired(&self, today: NaiveDate) -> bool {
+        self.until
+            .as_deref()
+            .and_then(|d| NaiveDate::parse_from_str(d, DATE_FORMAT).ok())
+            .is_some_and(|until| today > until)
+    }
+
+    /// "on-call runbook (until 2027-01-01)" — used in scan output and skip messages.
+    pub fn describe(&self) -> String {
+        let reason = self.reason.as_deref().unwrap_or("no reason given");
+        match &self.until {
+            Some(until) => format!("{} (until {})", reason, until),
+            None => reason.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct PinStore {
+    pub pins: Vec<Pin>,
+}
+
+pub fn today() -> NaiveDate {
+    Local::now().date_naive()
+}
+
+/// Parse `--until`: a date (2027-01-01) or a duration from today (90d, 6months).
+pub fn parse_until(input: &str) -> Result<NaiveDate> {
+    if let Ok(date) = NaiveDate::parse_from_str(input.trim(), DATE_FORMAT) {
+        return Ok(date);
+    }
+    let duration = threshold::parse_threshold(input).map_err(|_| {
+        anyhow!(
+            "invalid --until '{}': expected a date (YYYY-MM-DD) or a duration (90d, 6months)",
+            input
+        )
+    })?;
+    let days = chrono::Days::new(duration.as_secs().div_ceil(86_400));
+    today()
+        .checked_add_days(days)
+        .ok_or_else(|| anyhow!("--until '{}' is too far in the future", input))
+}
+
+fn load_pins(path: &Path) -> Result<PinStore> {
+    if !path.exists() {
+        return Ok(PinStore::default());
+    }
+    let raw = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read pins file: {}", path.display()))?;
+    serde_json::from_str(&raw)
+        .with_context(|| format!("Failed to parse pins JSON: {}", path.display()))
+}
+
+fn save_pins(path: &Path, store: &PinStore) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create pins directory: {}", parent.display()))?;
+    }
+
+    let raw = serde_json::to_string_pretty(store).context("Failed to serialize pins")?;
+    replace_file(path, raw)
+}
+
+pub fn all_pins() -> Result<Vec<Pin>> {
+    Ok(load_pins(&pins_file_path())?.pins)
+}
+
+/// Pins that are currently in effect (not expired).
+pub fn active_pins() -> Result<Vec<Pin>> {
+    let today = today();
+    Ok(all_pins()?
+        .into_iter()
+        .filter(|p| !p.is_expired(today))
+        .collect())
+}
+
+/// Add a pin, replacing any existing pin for the same name.
+pub fn add_pin(name: &str, reason: Option<String>, until: Option<NaiveDate>) -> Result<Pin> {
+    let path = pins_file_path();
+    let mut store = load_pins(&path)?;
+
+    if let Some(until) = until {
+        if until < today() {
+            bail!(
+                "--until {} is already in the past",
+                until.format(DATE_FORMAT)
+            );
+        }
+    }
+
+    let pin = Pin {
+        name: name.to_string(),
+        reason,
+        until: until.map(|d| d.format(DATE_FORMAT).to_string()),
+        pinned_at: humantime::format_rfc3339_seconds(std::time::SystemTime::now()).to_string(),
+    };
+    store.pins.retain(|p| p.name != name);
+    store.pins.push(pin.clone());
+    save_pins(&path, &store)?;
+    Ok(pin)
+}
+
+pub fn remove_pin(name: &str) -> Result<Pin> {
+    let path = pins_file_path();
+    let mut store = load_pins(&path)?;
+
+    let idx = store
+        .pins
+        .iter()
+        .position(|p| p.name == name)
+        .ok_or_else(|| anyhow!("'{}' is not pinned", name))?;
+    let pin = store.pins.remove(idx);
+    save_pins(&path, &store)?;
+    Ok(pin)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pins_expire_after_their_last_day() {
+        let pin = Pin {
+            name: "rg".to_string(),
+            reason: None,
+            until: Some("2027-01-01".to_string()),
+            pinned_at: String::new(),
+        };
+        let day = |s| NaiveDate::parse_from_str(s, DATE_FORMAT).unwrap();
+        assert!(!pin.is_expired(day("2026-12-31")));
+        assert!(!pin.is_expired(day("2027-01-01")));
+        assert!(pin.is_expired(day("2027-01-02")));
+        assert_eq!(pin.describe(), "no reason given (until 2027-01-01)");
+    }
+}
@@ -0,0 +1,80 @@
+use std::num::NonZeroUsize;
+use std::panic;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+/// Worker count for a `scan_threads` setting: 0 means one per CPU.
+pub fn worker_count(threads: usize) -> usize {
+    if threads > 0 {
+        return threads;
+    }
+    thread::available_parallelism().map_or(1, NonZeroUsize::get)
+}
+
+/// `items.iter().map(f).collect()`, spread over up to `threads` workers (see `worker_count`).
+/// Results keep the order of `items`, so output never depends on which worker finished first.
+/// With one worker (or one item) everything runs on the calling thread.
+pub fn map<T, R, F>(items: &[T], threads: usize, f: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+{
+    let workers = worker_count(threads).min(items.len());
+    if workers <= 1 {
+        return items.iter().map(f).collect();
+    }
+
+    // Workers take the next unclaimed item, so one slow file doesn't hold up a whole chunk.
+    let next = AtomicUsize::new(0);
+    let mut results: Vec<Option<R>> = items.iter().map(|_| None).collect();
+    thread::scope(|scope| {
+        let handles: Vec<_> = (0..workers)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut done = Vec::new();
+                    loop {
+                        let i = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(item) = items.get(i) else {
+                            break;
+                        };
+                        done.push((i, f(item)));
+                    }
+                    done
+                })
+            })
+            .collect();
+        for handle in handles {
+            let done = handle
+                .join()
+                .unwrap_or_else(|err| panic::resume_unwind(err));
+            for (i, result) in done {
+                results[i] = Some(result);
+            }
+        }
+    });
+    results
+        .into_iter()
+        .map(|r| r.expect("every item is claimed by a worker"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_input_order() {
+        let items: Vec<u64> = (0..500).collect();
+        let slow_first = |n: &u64| {
+            if *n < 4 {
+                thread::sleep(std::time::Duration::from_millis(20));
+            }
+            n * 2
+        };
+        let expected: Vec<u64> = items.iter().map(|n| n * 2).collect();
+        assert_eq!(map(&items, 4, slow_first), expected);
+        assert_eq!(map(&items, 1, slow_first), expected);
+        assert!(map(&[] as &[u64], 8, slow_first).is_empty());
+    }
+}
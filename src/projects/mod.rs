@@ -0,0 +1,427 @@
+use colored::Colorize;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use walkdir::{DirEntry, WalkDir};
+
+use crate::models::Config;
+use crate::references::words;
+use crate::ui::expand_tilde;
+
+/// Directories that hold dependencies or build output rather than a project's own files.
+const SKIP_DIRS: &[&str] = &[
+    ".git",
+    "node_modules",
+    "target",
+    "vendor",
+    ".venv",
+    "venv",
+    "__pycache__",
+    ".cache",
+];
+
+/// Deep enough for ~/src/<org>/<repo>/<crate>/build.rs, shallow enough to stay quick.
+const MAX_DEPTH: usize = 8;
+
+/// A project file line that mentions a binary.
+#[derive(Debug, Clone)]
+pub struct ProjectUse {
+    /// The enclosing repository (nearest directory with `.git`), else the file's directory.
+    pub project: PathBuf,
+    pub file: PathBuf,
+    pub line: usize,
+}
+
+impl fmt::Display for ProjectUse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let file = self.file.strip_prefix(&self.project).unwrap_or(&self.file);
+        write!(
+            f,
+            "{} ({}:{})",
+            self.project.display(),
+            file.display(),
+            self.line
+        )
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Kind {
+    /// Makefiles, justfiles, CI workflows, pre-commit config: every non-comment line.
+    Script,
+    /// package.json: only lines inside `"scripts": { ... }`.
+    PackageJson,
+    /// build.rs: every line that isn't a `//` comment.
+    BuildRs,
+}
+
+fn kind_of(path: &Path) -> Option<Kind> {
+    let name = path.file_name()?.to_str()?;
+    match name {
+        "Makefile"
+        | "makefile"
+        | "GNUmakefile"
+        | "justfile"
+        | "Justfile"
+        | ".justfile"
+        | ".pre-commit-config.yaml" => Some(Kind::Script),
+        "package.json" => Some(Kind::PackageJson),
+        "build.rs" => Some(Kind::BuildRs),
+        _ if name.ends_with(".mk") => Some(Kind::Script),
+        _ if (name.ends_with(".yml") || name.ends_with(".yaml"))
+            && path
+                .parent()
+                .is_some_and(|p| p.ends_with(".github/workflows")) =>
+        {
+            Some(Kind::Script)
+        }
+        _ => None,
+    }
+}
+
+fn walk_into(entry: &DirEntry) -> bool {
+    !(entry.file_type().is_dir()
+        && entry
+            .file_name()
+            .to_str()
+            .is_some_and(|n| SKIP_DIRS.contains(&n)))
+}
+
+/// Nearest ancestor of `file` (up to `root`) that is a git checkout.
+fn project_of(file: &Path, root: &Path) -> PathBuf {
+    let dir = file.parent().unwrap_or(root);
+    for ancestor in dir.ancestors() {
+        if ancestor.join(".git").exists() {
+            return ancestor.to_path_buf();
+        }
+        if ancestor == root {
+            break;
+        }
+    }
+    // .github/workflows/ci.yml belongs to the directory above .github.
+    if dir.ends_with(".github/workflows") {
+        if let Some(project) = dir.parent().and_then(Path::parent) {
+            return project.to_path_buf();
+        }
+    }
+    dir.to_path_buf()
+}
+
+/// Lines of `text` that count for `kind`, with 1-based line numbers.
+fn relevant_lines(text: &str, kind: Kind) -> Vec<(usize, &str)> {
+    let mut lines = Vec::new();
+    let mut in_scripts = false;
+    for (i, line) in text.lines().enumerate() {
+        let trimmed = line.trim();
+        let keep = match kind {
+            Kind::Script => !trimmed.starts_with('#'),
+            Kind::BuildRs => !trimmed.starts_with("//"),
+            Kind::PackageJson => {
+                if trimmed.starts_with("\"scripts\"") && trimmed.ends_with('{') {
+                    in_scripts = true;
+                    false
+                } else if in_scripts && trimmed.starts_with('}') {
+                    in_scripts = false;
+                    false
+                } else {
+                    in_scripts
+                }
+            }
+        };
+        if keep {
+            lines.push((i + 1, line));
+        }
+    }
+    lines
+}
+
+/// `project_roots` from the config with `~` expanded, warning about (and dropping) any that
+/// don't exist.
+pub fn existing_roots(config: &Config) -> Vec<PathBuf> {
+    let mut roots: Vec<PathBuf> = config
+        .project_roots
+        .iter()
+        .map(|r| expand_tilde(r))
+        .collect();
+    roots.retain(|root| {
+        if !root.is_dir() {
+            eprintln!(
+                "{} Project root {} does not exist. Skipping.",
+                "[!]".yellow(),
+                root.display()
+            );
+        }
+        root.is_dir()
+    });
+    roots
+}
+
+/// Walk `roots` and report, for each of `names`, the projects whose build or CI files mention
+/// it (one entry per project, the first mention).
+pub fn find_uses(roots: &[PathBuf], names: &HashSet<&str>) -> HashMap<String, Vec<ProjectUse>> {
+    let mut uses: HashMap<String, Vec<ProjectUse>> = HashMap::new();
+    if names.is_empty() {
+        return uses;
+    }
+
+    for root in roots {
+        for entry in WalkDir::new(root)
+            .max_depth(MAX_DEPTH)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_entry(walk_into)
+            .filter_map(|e| e.ok())
+        {
+            let path = entry.path();
+            if !entry.file_type().is_file() {
+                continue;
+            }
+            let Some(kind) = kind_of(path) else {
+                continue;
+            };
+            let Ok(text) = fs::read_to_string(path) else {
+                continue;
+            };
+
+            for (line_no, line) in relevant_lines(&text, kind) {
+                for word in words(line) {
+                    // A path like ./bin/tool or ~/.cargo/bin/tool still names `tool`.
+                    let name = word.rsplit('/').next().unwrap_or(&word);
+                    if !names.contains(name) {
+                        continue;
+                    }
+                    let project = project_of(path, root);
+                    let found = uses.entry(name.to_string()).or_default();
+                    if !found.iter().any(|u| u.project == project) {
+                        found.push(ProjectUse {
+                            project,
+                            file: path.to_path_buf(),
+                            line: line_no,
+                        });
+                    }
+                }
+            }
+        }
+    }
+    uses
+}
+
+/// A `rust-toolchain.toml` (or legacy `rust-toolchain`) file and the channel it asks for.
+#[derive(Debug, Clone)]
+pub struct ToolchainFile {
+    pub project: PathBuf,
+    pub file: PathBuf,
+    /// e.g. "stable", "1.75.0" or "nightly-2024-01-01"
+    pub channel: String,
+}
+
+impl fmt::Display for ToolchainFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let file = self.file.strip_prefix(&self.project).unwrap_or(&self.file);
+        write!(f, "{} ({})", self.project.display(), file.display())
+    }
+}
+
+/// The channel from a toolchain file: `[toolchain] channel = ...`, or the whole (one-line)
+/// contents of a legacy `rust-toolchain` file.
+fn toolchain_channel(text: &str) -> Option<String> {
+    if let Ok(doc) = toml::from_str::<toml::Table>(text) {
+        return doc
+            .get("toolchain")?
+            .get("channel")?
+            .as_str()
+            .map(str::to_string);
+    }
+    let line = text.trim();
+    (!line.is_empty() && !line.contains(char::is_whitespace)).then(|| line.to_string())
+}
+
+/// Files named one of `names` under `roots`, each with its project (see `ProjectUse::project`).
+pub fn find_named_files(roots: &[PathBuf], names: &[&str]) -> Vec<(PathBuf, PathBuf)> {
+    let mut found = Vec::new();
+    for root in roots {
+        for entry in WalkDir::new(root)
+            .max_depth(MAX_DEPTH)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_entry(walk_into)
+            .filter_map(|e| e.ok())
+        {
+            if entry.file_type().is_file()
+                && entry
+                    .file_name()
+                    .to_str()
+                    .is_some_and(|n| names.contains(&n))
+            {
+                let path = entry.path();
+                found.push((project_of(path, root), path.to_path_buf()));
+            }
+        }
+    }
+    found
+}
+
+/// Walk `roots` for rust-toolchain files (the toolchains those projects still build with).
+pub fn find_toolchain_files(roots: &[PathBuf]) -> Vec<ToolchainFile> {
+    find_named_files(roots, &["rust-toolchain.toml", "rust-toolchain"])
+        .into_iter()
+        .filter_map(|(project, file)| {
+            let channel = toolchain_channel(&fs::read_to_string(&file).ok()?)?;
+            Some(ToolchainFile {
+                project,
+                file,
+                channel,
+            })
+        })
+        .collect()
+}
+
+/// What produced a build output directory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuildKind {
+    /// A Cargo `target/` directory.
+    Cargo,
+    /// An npm, yarn or pnpm `node_modules/` directory.
+    Node,
+}
+
+impl BuildKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            BuildKind::Cargo => "cargo",
+            BuildKind::Node => "node",
+        }
+    }
+}
+
+/// A build output directory and the project it belongs to (its parent).
+#[derive(Debug, Clone)]
+pub struct BuildDir {
+    pub kind: BuildKind,
+    /// The root it was found under.
+    pub root: PathBuf,
+    pub project: PathBuf,
+    pub path: PathBuf,
+}
+
+/// `target` counts when Cargo tagged it (CACHEDIR.TAG) or it sits next to a Cargo.toml.
+fn build_kind(dir: &Path) -> Option<BuildKind> {
+    match dir.file_name()?.to_str()? {
+        "node_modules" => Some(BuildKind::Node),
+        "target"
+            if dir.join("CACHEDIR.TAG").is_file()
+                || dir.parent().is_some_and(|p| p.join("Cargo.toml").is_file()) =>
+        {
+            Some(BuildKind::Cargo)
+        }
+        _ => None,
+    }
+}
+
+/// Walk `roots` for Cargo `target/` and `node_modules/` directories. Nothing inside one is
+/// looked at, so nested `node_modules` are part of the outermost.
+pub fn find_build_dirs(roots: &[PathBuf]) -> Vec<BuildDir> {
+    let mut found = Vec::new();
+    for root in roots {
+        let mut walker = WalkDir::new(root)
+            .max_depth(MAX_DEPTH)
+            .sort_by_file_name()
+            .into_iter();
+        while let Some(entry) = walker.next() {
+            let Ok(entry) = entry else { continue };
+            if !entry.file_type().is_dir() || entry.depth() == 0 {
+                continue;
+            }
+            if let Some(kind) = build_kind(entry.path()) {
+                found.push(BuildDir {
+                    kind,
+                    root: root.clone(),
+                    project: entry.path().parent().unwrap_or(root).to_path_buf(),
+                    path: entry.path().to_path_buf(),
+                });
+                walker.skip_current_dir();
+            } else if !walk_into(&entry) {
+                walker.skip_current_dir();
+            }
+        }
+    }
+    found
+}
+
+/// When anything in `project` last changed: the newest mtime of its own files (build output,
+/// dependencies and `.git` don't count), else the directory's own mtime.
+pub fn last_touched(project: &Path) -> SystemTime {
+    WalkDir::new(project)
+        .max_depth(MAX_DEPTH)
+        .into_iter()
+        .filter_entry(walk_into)
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter_map(|e| e.metadata().ok()?.modified().ok())
+        .max()
+        .or_else(|| fs::metadata(project).ok()?.modified().ok())
+        .unwrap_or_else(SystemTime::now)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_scripts_count_in_package_json() {
+        let text = "{\n  \"name\": \"protoc\",\n  \"scripts\": {\n    \"gen\": \"protoc --ts_out=.\"\n  },\n  \"devDependencies\": {}\n}\n";
+        let lines = relevant_lines(text, Kind::PackageJson);
+        assert_eq!(lines, [(4, "    \"gen\": \"protoc --ts_out=.\"")]);
+        assert!(kind_of(Path::new("/src/app/.github/workflows/ci.yml")).is_some());
+        assert!(kind_of(Path::new("/src/app/config.yml")).is_none());
+    }
+
+    #[test]
+    fn reads_toolchain_channel_from_both_formats() {
+        assert_eq!(
+            toolchain_channel("[toolchain]\nchannel = \"1.75.0\"\ncomponents = [\"clippy\"]\n"),
+            Some("1.75.0".to_string())
+        );
+        assert_eq!(
+            toolchain_channel("nightly-2024-01-01\n"),
+            Some("nightly-2024-01-01".to_string())
+        );
+    }
+
+    #[test]
+    fn finds_cargo_and_node_build_dirs() {
+        let root = std::env::temp_dir().join(format!("bin-expire-sweep-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let tool = root.join("tool");
+        fs::create_dir_all(tool.join("target").join("debug")).unwrap();
+        fs::write(tool.join("Cargo.toml"), "[package]\n").unwrap();
+        let web = root.join("web");
+        fs::create_dir_all(web.join("node_modules").join("a").join("node_modules")).unwrap();
+        // Not Cargo's: no CACHEDIR.TAG and no Cargo.toml next to it.
+        fs::create_dir_all(root.join("docs").join("target")).unwrap();
+        let out = root.join("elsewhere").join("target");
+        fs::create_dir_all(&out).unwrap();
+        fs::write(
+            out.join("CACHEDIR.TAG"),
+            "Signature: 8a477f597d28d172789f06886806bc55\n",
+        )
+        .unwrap();
+
+        let found: Vec<(BuildKind, PathBuf)> = find_build_dirs(std::slice::from_ref(&root))
+            .into_iter()
+            .map(|b| (b.kind, b.path))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (BuildKind::Cargo, out),
+                (BuildKind::Cargo, tool.join("target")),
+                (BuildKind::Node, web.join("node_modules")),
+            ]
+        );
+
+        let _ = fs::remove_dir_all(&root);
+    }
+}
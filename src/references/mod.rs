@@ -0,0 +1,490 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::models::BinaryInfo;
+
+/// Where a binary is mentioned: a config file line that runs it from automation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reference {
+    /// File path, or `crontab -l` for the user's crontab.
+    pub source: String,
+    /// 1-based line number.
+    pub line: usize,
+    /// What kind of entry mentions it, e.g. "ExecStart", "credential.helper", "ProxyCommand".
+    pub what: String,
+}
+
+impl fmt::Display for Reference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{} ({})", self.source, self.line, self.what)
+    }
+}
+
+/// Binaries mentioned by systemd user units, crontabs, shell rc files, git config, ssh config
+/// and `.tool-versions`. Such tools often run rarely and only from automation, so atime makes
+/// them look stale even though removing them breaks something.
+#[derive(Debug, Default)]
+pub struct References {
+    by_name: HashMap<String, Reference>,
+    by_path: HashMap<PathBuf, Reference>,
+}
+
+impl References {
+    /// Read every known source. Missing or unreadable files are skipped.
+    pub fn collect() -> Self {
+        let mut refs = References::default();
+        let Some(home) = dirs::home_dir() else {
+            return refs;
+        };
+
+        for rc in [".bashrc", ".zshrc", ".profile"] {
+            refs.scan_file(&home.join(rc), &home, |line| shell_line(line, "shell"));
+        }
+
+        let config_home = std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .filter(|p| p.is_absolute())
+            .unwrap_or_else(|| home.join(".config"));
+        if let Ok(entries) = fs::read_dir(config_home.join("systemd/user")) {
+            let mut units: Vec<PathBuf> = entries
+                .flatten()
+                .map(|e| e.path())
+                .filter(|p| p.is_file())
+                .collect();
+            units.sort();
+            for unit in units {
+                refs.scan_file(&unit, &home, systemd_line);
+            }
+        }
+
+        if let Ok(out) = Command::new("crontab").arg("-l").output() {
+            if out.status.success() {
+                let text = String::from_utf8_lossy(&out.stdout);
+                refs.scan_text("crontab -l", &text, &home, |line| crontab_line(line, false));
+            }
+        }
+        refs.scan_file(Path::new("/etc/crontab"), &home, |line| {
+            crontab_line(line, true)
+        });
+        if let Ok(entries) = fs::read_dir("/etc/cron.d") {
+            let mut files: Vec<PathBuf> = entries.flatten().map(|e| e.path()).collect();
+            files.sort();
+            for file in files {
+                refs.scan_file(&file, &home, |line| crontab_line(line, true));
+            }
+        }
+
+        let mut section = String::new();
+        for gitconfig in [home.join(".gitconfig"), config_home.join("git/config")] {
+            section.clear();
+            refs.scan_file(&gitconfig, &home, |line| git_line(line, &mut section));
+        }
+
+        refs.scan_file(&home.join(".ssh/config"), &home, ssh_line);
+        refs.scan_file(&home.join(".tool-versions"), &home, tool_versions_line);
+
+        refs
+    }
+
+    /// The first reference to `bin`, by exact path or by name.
+    pub fn find(&self, bin: &BinaryInfo) -> Option<&Reference> {
+        self.find_path(&bin.name, &bin.path)
+    }
+
+    /// Like `find`, for a binary known only by name and path (e.g. a plan item).
+    pub fn find_path(&self, name: &str, path: &Path) -> Option<&Reference> {
+        if let Some(r) = self.by_path.get(path) {
+            return Some(r);
+        }
+        if let Some(r) = fs::canonicalize(path)
+            .ok()
+            .and_then(|p| self.by_path.get(&p))
+        {
+            return Some(r);
+        }
+        self.by_name.get(name).or_else(|| {
+            // "rg.exe" is referenced as "rg".
+            let stem = Path::new(name).file_stem()?.to_str()?;
+            self.by_name.get(stem)
+        })
+    }
+
+    fn scan_file<F>(&mut self, path: &Path, home: &Path, parse: F)
+    where
+        F: FnMut(&str) -> Option<(String, Vec<String>)>,
+    {
+        if let Ok(text) = fs::read_to_string(path) {
+            self.scan_text(&path.display().to_string(), &text, home, parse);
+        }
+    }
+
+    fn scan_text<F>(&mut self, source: &str, text: &str, home: &Path, mut parse: F)
+    where
+        F: FnMut(&str) -> Option<(String, Vec<String>)>,
+    {
+        for (i, line) in text.lines().enumerate() {
+            let Some((what, words)) = parse(line) else {
+                continue;
+            };
+            let reference = Reference {
+                source: source.to_string(),
+                line: i + 1,
+                what,
+            };
+            for word in words {
+                self.add(&word, home, &reference);
+            }
+        }
+    }
+
+    fn add(&mut self, word: &str, home: &Path, reference: &Reference) {
+        if word.contains('/') {
+            let path = expand_home(word, home);
+            if !path.is_absolute() {
+                return;
+            }
+            let path = fs::canonicalize(&path).unwrap_or(path);
+            self.by_path
+                .entry(path)
+                .or_insert_with(|| reference.clone());
+        } else if is_command_word(word) {
+            self.by_name
+                .entry(word.to_string())
+                .or_insert_with(|| reference.clone());
+        }
+    }
+}
+
+fn expand_home(word: &str, home: &Path) -> PathBuf {
+    for prefix in ["~/", "$HOME/", "${HOME}/"] {
+        if let Some(rest) = word.strip_prefix(prefix) {
+            return home.join(rest);
+        }
+    }
+    PathBuf::from(word)
+}
+
+fn is_command_word(word: &str) -> bool {
+    !word.is_empty()
+        && !word.starts_with('-')
+        && word
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | '.' | '+'))
+        && word.chars().any(|c| c.is_alphabetic())
+}
+
+/// Split shell-ish text into the words that could name a program.
+pub fn words(text: &str) -> Vec<String> {
+    text.split(|c: char| {
+        c.is_whitespace()
+            || matches!(
+                c,
+                '|' | ';' | '&' | '(' | ')' | '<' | '>' | '\'' | '"' | '`' | '=' | ','
+            )
+    })
+    // Keep "$HOME/..." and "${HOME}/..." intact; other $VARS aren't program names.
+    .filter(|w| !w.is_empty() && (!w.starts_with('$') || w.contains('/')))
+    .map(str::to_string)
+    .collect()
+}
+
+/// Words that run the word after them, so that one is in command position too.
+const COMMAND_PREFIXES: &[&str] = &[
+    "eval", "exec", "command", "builtin", "nohup", "time", "if", "then", "else", "elif", "do",
+    "while", "until", "!",
+];
+
+/// The words of a shell command line that run a program: the first word of each command, i.e.
+/// the start of the line and whatever follows `|`, `;`, `&&`, `||`, `$(` or `eval`. Arguments
+/// are not programs, so `echo jq` or `export EDITOR=hx` doesn't reference `jq` or `hx`.
+fn command_words(text: &str) -> Vec<String> {
+    let mut found = Vec::new();
+    let mut word = String::new();
+    let mut at_command = true;
+    // Open quotes and `$(`/`(` groups, innermost last.
+    let mut nesting: Vec<char> = Vec::new();
+    let mut prev = ' ';
+    let mut chars = text.chars().peekable();
+
+    let mut take = |word: &mut String, at_command: &mut bool| {
+        if word.is_empty() {
+            return;
+        }
+        let w = std::mem::take(word);
+        if !*at_command {
+            return;
+        }
+        if is_assignment(&w) {
+            // `FOO=1 cmd`: the command comes after the assignments.
+            return;
+        }
+        *at_command = COMMAND_PREFIXES.contains(&w.as_str());
+        // Keep "$HOME/..." and "${HOME}/..." intact; other $VARS aren't program names.
+        if !*at_command && (!w.starts_with('$') || w.contains('/')) {
+            found.push(w);
+        }
+    };
+
+    while let Some(c) = chars.next() {
+        let quote = nesting.last().copied().filter(|q| matches!(q, '\'' | '"'));
+        if quote == Some('\'') {
+            if c == '\'' {
+                nesting.pop();
+            } else {
+                word.push(c);
+            }
+        } else if c == '$' && chars.peek() == Some(&'(') {
+            chars.next();
+            take(&mut word, &mut at_command);
+            nesting.push('(');
+            at_command = true;
+        } else if c == '`' {
+            take(&mut word, &mut at_command);
+            at_command = true;
+        } else if quote == Some('"') {
+            if c == '"' {
+                nesting.pop();
+            } else {
+                word.push(c);
+            }
+        } else if matches!(c, '\'' | '"') {
+            nesting.push(c);
+        } else if c == '#' && word.is_empty() {
+            break;
+        } else if c.is_whitespace() || matches!(c, '<' | '>') {
+            take(&mut word, &mut at_command);
+        } else if c == ')' {
+            take(&mut word, &mut at_command);
+            if nesting.last() == Some(&'(') {
+                nesting.pop();
+            }
+            at_command = false;
+        } else if matches!(c, '|' | ';' | '(') {
+            take(&mut word, &mut at_command);
+            if c == '(' {
+                nesting.push('(');
+            }
+            at_command = true;
+        } else if c == '&' {
+            take(&mut word, &mut at_command);
+            // `2>&1` and `>&2` redirect; they don't start a command.
+            if !matches!(prev, '<' | '>') {
+                at_command = true;
+            }
+        } else {
+            word.push(c);
+        }
+        prev = c;
+    }
+    take(&mut word, &mut at_command);
+    found
+}
+
+fn is_assignment(word: &str) -> bool {
+    word.split_once('=').is_some_and(|(name, _)| {
+        !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '_')
+    })
+}
+
+fn shell_line(line: &str, what: &str) -> Option<(String, Vec<String>)> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+    Some((what.to_string(), command_words(line)))
+}
+
+/// `<schedule> [user] <command>`; system crontabs have the user field. `NAME=value` lines set
+/// the environment and run nothing.
+fn crontab_line(line: &str, has_user: bool) -> Option<(String, Vec<String>)> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') || is_assignment(line) {
+        return None;
+    }
+    // "@daily cmd" or "*/5 * * * * cmd".
+    let mut fields = if line.starts_with('@') { 1 } else { 5 };
+    if has_user {
+        fields += 1;
+    }
+    let mut rest = line;
+    for _ in 0..fields {
+        rest = rest.trim_start();
+        rest = &rest[rest.find(char::is_whitespace)?..];
+    }
+    // An unescaped % ends the command; what follows is its stdin.
+    let command = rest.split('%').next().unwrap_or_default();
+    shell_line(command, "crontab")
+}
+
+/// `ExecStart=`, `ExecStartPre=`, `ExecStop=`, ... (with an optional `-`/`@`/`+` prefix).
+fn systemd_line(line: &str) -> Option<(String, Vec<String>)> {
+    let (key, value) = line.trim().split_once('=')?;
+    let key = key.trim();
+    if !key.starts_with("Exec") {
+        return None;
+    }
+    let value = value.trim_start_matches(['-', '@', '+', '!', ':', ' ']);
+    Some((key.to_string(), command_words(value)))
+}
+
+/// `core.pager` and `credential.helper`. `section` carries the current `[section]` across lines.
+fn git_line(line: &str, section: &mut String) -> Option<(String, Vec<String>)> {
+    let line = line.trim();
+    if let Some(header) = line.strip_prefix('[') {
+        // [credential "https://example.com"] is still the credential section.
+        *section = header
+            .trim_end_matches(']')
+            .split(|c: char| c.is_whitespace() || c == '"')
+            .next()
+            .unwrap_or_default()
+            .to_lowercase();
+        return None;
+    }
+    let (key, value) = line.split_once('=')?;
+    let key = key.trim().to_lowercase();
+    let value = value.trim().trim_matches('"');
+    match (section.as_str(), key.as_str()) {
+        ("core", "pager") => Some(("core.pager".to_string(), command_words(value))),
+        ("credential", "helper") => {
+            // "!cmd args" is a shell snippet; "name" means git-credential-name.
+            if let Some(snippet) = value.strip_prefix('!') {
+                return Some(("credential.helper".to_string(), command_words(snippet)));
+            }
+            let mut found = command_words(value);
+            if let Some(first) = found.first().filter(|w| !w.contains('/')).cloned() {
+                found.push(format!("git-credential-{}", first));
+            }
+            Some(("credential.helper".to_string(), found))
+        }
+        _ => None,
+    }
+}
+
+fn ssh_line(line: &str) -> Option<(String, Vec<String>)> {
+    let line = line.trim();
+    let split = line.find(|c: char| c.is_whitespace() || c == '=')?;
+    let (key, value) = line.split_at(split);
+    if !key.eq_ignore_ascii_case("ProxyCommand") {
+        return None;
+    }
+    Some(("ProxyCommand".to_string(), command_words(value)))
+}
+
+/// `<plugin> <version>`: the binaries asdf/mise put on PATH for the plugin.
+fn tool_versions_line(line: &str) -> Option<(String, Vec<String>)> {
+    let line = line.trim();
+    if line.starts_with('#') {
+        return None;
+    }
+    let plugin = line.split_whitespace().next()?;
+    let binaries = plugin_binaries(plugin)
+        .iter()
+        .map(|b| b.to_string())
+        .collect();
+    Some((".tool-versions".to_string(), binaries))
+}
+
+/// Plugins whose binaries aren't named after them. Any other plugin (jq, terraform, kubectl, ...)
+/// installs a binary of its own name.
+fn plugin_binaries(plugin: &str) -> Vec<&str> {
+    match plugin {
+        "nodejs" => vec!["node", "npm", "npx", "corepack"],
+        "golang" => vec!["go", "gofmt"],
+        "python" => vec!["python", "python3", "pip", "pip3"],
+        "ruby" => vec!["ruby", "gem", "bundle", "irb"],
+        "rust" => vec!["cargo", "rustc", "rustdoc", "rustfmt"],
+        "java" => vec!["java", "javac", "jar"],
+        "erlang" => vec!["erl", "erlc", "escript"],
+        "elixir" => vec!["elixir", "elixirc", "iex", "mix"],
+        "php" => vec!["php", "composer"],
+        "perl" => vec!["perl", "cpan"],
+        "lua" => vec!["lua", "luac"],
+        "dotnet-core" | "dotnet" => vec!["dotnet"],
+        "kotlin" => vec!["kotlin", "kotlinc"],
+        "scala" => vec!["scala", "scalac"],
+        other => vec![other],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extracts_program_words() {
+        assert_eq!(
+            systemd_line("ExecStart=-/home/me/.cargo/bin/syncd --daemon")
+                .unwrap()
+                .1,
+            ["/home/me/.cargo/bin/syncd"]
+        );
+        assert!(systemd_line("Description=sync").is_none());
+
+        let mut section = String::new();
+        assert!(git_line("[credential \"https://example.com\"]", &mut section).is_none());
+        assert_eq!(
+            git_line("\thelper = manager", &mut section).unwrap().1,
+            ["manager", "git-credential-manager"]
+        );
+        assert!(git_line("[core]", &mut section).is_none());
+        assert_eq!(
+            git_line("pager = delta --dark", &mut section).unwrap().1,
+            ["delta"]
+        );
+
+        assert_eq!(
+            ssh_line("  ProxyCommand cloudflared access ssh --hostname %h")
+                .unwrap()
+                .1[0],
+            "cloudflared"
+        );
+        assert_eq!(
+            shell_line("eval \"$(starship init bash)\"", "shell")
+                .unwrap()
+                .1,
+            ["starship"]
+        );
+    }
+
+    #[test]
+    fn only_command_position_counts() {
+        let shell = |line| shell_line(line, "shell").unwrap().1;
+        assert_eq!(
+            shell("FOO=1 rc_tool --with other_tool | grep -q x && notify-send done"),
+            ["rc_tool", "grep", "notify-send"]
+        );
+        assert_eq!(shell("echo jq; export EDITOR=hx"), ["echo", "export"]);
+        assert_eq!(shell("alias x='ls -la' # run jq"), ["alias"]);
+        assert_eq!(
+            shell("PATH=\"$HOME/bin:$PATH\" ~/bin/sync 2>&1 >&2 tail || (cd /tmp; make)"),
+            ["~/bin/sync", "cd", "make"]
+        );
+        assert_eq!(
+            shell("x=`hostname` eval \"$(zoxide init bash)\""),
+            ["hostname", "zoxide"]
+        );
+
+        assert_eq!(
+            crontab_line("*/5 * * * * backupd --to nas % input", false)
+                .unwrap()
+                .1,
+            ["backupd"]
+        );
+        assert_eq!(
+            crontab_line("@reboot root sleep 5; /opt/bin/warm", true)
+                .unwrap()
+                .1,
+            ["sleep", "/opt/bin/warm"]
+        );
+        assert!(crontab_line("MAILTO=me", false).is_none());
+
+        assert_eq!(
+            tool_versions_line("nodejs 20.11.0").unwrap().1,
+            ["node", "npm", "npx", "corepack"]
+        );
+        assert_eq!(tool_versions_line("jq 1.7").unwrap().1, ["jq"]);
+    }
+}
@@ -0,0 +1,451 @@
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::analyzer::dir_usage;
+use crate::models::{Config, LastUsedSource};
+use crate::pool;
+use crate::projects;
+
+/// Where a version manager keeps installed versions.
+enum Layout {
+    /// `<root>/<dir>/<version>`, all of one tool (nvm, pyenv, rbenv).
+    Versions {
+        dir: &'static str,
+        tool: &'static str,
+    },
+    /// `<root>/installs/<tool>/<version>` (asdf, mise).
+    Installs,
+}
+
+struct Manager {
+    name: &'static str,
+    /// Environment variable that moves the manager's root.
+    env: &'static str,
+    /// Default root, relative to the home directory.
+    home_dir: &'static str,
+    layout: Layout,
+}
+
+const MANAGERS: &[Manager] = &[
+    Manager {
+        name: "nvm",
+        env: "NVM_DIR",
+        home_dir: ".nvm",
+        layout: Layout::Versions {
+            dir: "versions/node",
+            tool: "node",
+        },
+    },
+    Manager {
+        name: "pyenv",
+        env: "PYENV_ROOT",
+        home_dir: ".pyenv",
+        layout: Layout::Versions {
+            dir: "versions",
+            tool: "python",
+        },
+    },
+    Manager {
+        name: "rbenv",
+        env: "RBENV_ROOT",
+        home_dir: ".rbenv",
+        layout: Layout::Versions {
+            dir: "versions",
+            tool: "ruby",
+        },
+    },
+    Manager {
+        name: "asdf",
+        env: "ASDF_DATA_DIR",
+        home_dir: ".asdf",
+        layout: Layout::Installs,
+    },
+    Manager {
+        name: "mise",
+        env: "MISE_DATA_DIR",
+        home_dir: ".local/share/mise",
+        layout: Layout::Installs,
+    },
+];
+
+/// Project files that pin runtime versions.
+const VERSION_FILES: &[&str] = &[
+    ".nvmrc",
+    ".python-version",
+    ".ruby-version",
+    ".tool-versions",
+    "mise.toml",
+    ".mise.toml",
+];
+
+fn root_of(manager: &Manager) -> Option<PathBuf> {
+    match std::env::var_os(manager.env) {
+        Some(root) if !root.is_empty() => Some(PathBuf::from(root)),
+        _ => dirs::home_dir().map(|home| home.join(manager.home_dir)),
+    }
+}
+
+/// asdf plugin names and mise tool names for the same runtime.
+fn canonical_tool(name: &str) -> &str {
+    match name {
+        "nodejs" => "node",
+        "golang" => "go",
+        other => other,
+    }
+}
+
+/// One installed version of a runtime.
+#[derive(Debug, Clone)]
+pub struct Runtime {
+    /// "nvm", "pyenv", "rbenv", "asdf" or "mise".
+    pub manager: &'static str,
+    /// "node", "python", "ruby", or another asdf/mise tool.
+    pub tool: String,
+    /// Directory name, e.g. "v20.11.0" (nvm) or "3.12.1".
+    pub version: String,
+    pub path: PathBuf,
+    pub size: u64,
+    pub last_used: SystemTime,
+    pub last_used_source: LastUsedSource,
+}
+
+impl Runtime {
+    /// "pyenv/python@3.12.1": shown in output, used by `pin` and recorded in the manifest.
+    pub fn name(&self) -> String {
+        format!("{}/{}@{}", self.manager, self.tool, self.version)
+    }
+}
+
+/// Install directories of the version managers present, e.g. ("nvm", ~/.nvm/versions/node).
+pub fn install_dirs() -> Vec<(&'static str, PathBuf)> {
+    MANAGERS
+        .iter()
+        .filter_map(|m| {
+            let root = root_of(m)?;
+            let dir = match m.layout {
+                Layout::Versions { dir, .. } => root.join(dir),
+                Layout::Installs => root.join("installs"),
+            };
+            dir.is_dir().then_some((m.name, dir))
+        })
+        .collect()
+}
+
+/// Real version directories in `dir`, oldest version first. Symlinks are aliases (mise's "20" -> "20.11.0") or
+/// pyenv-virtualenv environments, not installs of their own.
+fn version_dirs(dir: &Path) -> Vec<(String, PathBuf)> {
+    let mut found: Vec<(String, PathBuf)> = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_ok_and(|t| t.is_dir()))
+        .map(|e| (e.file_name().to_string_lossy().to_string(), e.path()))
+        .collect();
+    // 3.9 before 3.10; names without numbers (tool directories) alphabetically.
+    found.sort_by(|(a, _), (b, _)| version_key(a).cmp(&version_key(b)).then(a.cmp(b)));
+    found
+}
+
+/// Every installed version of every manager present, measured on `scan_threads` workers.
+pub fn installed(config: &Config) -> Vec<Runtime> {
+    let mut found: Vec<(&'static str, String, String, PathBuf)> = Vec::new();
+    for manager in MANAGERS {
+        let Some(root) = root_of(manager) else {
+            continue;
+        };
+        match manager.layout {
+            Layout::Versions { dir, tool } => {
+                for (version, path) in version_dirs(&root.join(dir)) {
+                    found.push((manager.name, tool.to_string(), version, path));
+                }
+            }
+            Layout::Installs => {
+                for (tool, tool_dir) in version_dirs(&root.join("installs")) {
+                    let tool = canonical_tool(&tool).to_string();
+                    for (version, path) in version_dirs(&tool_dir) {
+                        found.push((manager.name, tool.clone(), version, path));
+                    }
+                }
+            }
+        }
+    }
+
+    pool::map(
+        &found,
+        config.scan_threads,
+        |(manager, tool, version, path)| {
+            let usage = dir_usage(path, config.windows_use_access_time);
+            Runtime {
+                manager,
+                tool: tool.clone(),
+                version: version.clone(),
+                path: path.clone(),
+                size: usage.size,
+                last_used: usage.last_used,
+                last_used_source: usage.last_used_source,
+            }
+        },
+    )
+}
+
+/// Why a version must stay, even if nothing ran it lately.
+#[derive(Debug, Clone)]
+pub enum KeptBy {
+    /// A manager's global default: nvm's `alias/default`, pyenv's or rbenv's `version`, or the
+    /// home directory's `.tool-versions` / mise config.
+    Default(PathBuf),
+    /// A version file in a project under `project_roots`.
+    Project { project: PathBuf, file: PathBuf },
+}
+
+impl fmt::Display for KeptBy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeptBy::Default(file) => write!(f, "default ({})", file.display()),
+            KeptBy::Project { project, file } => {
+                let file = file.strip_prefix(project).unwrap_or(file);
+                write!(f, "{} ({})", project.display(), file.display())
+            }
+        }
+    }
+}
+
+impl KeptBy {
+    /// "default" or "project".
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            KeptBy::Default(_) => "default",
+            KeptBy::Project { .. } => "project",
+        }
+    }
+}
+
+/// A version some file asks for.
+#[derive(Debug, Clone)]
+pub struct VersionPin {
+    pub tool: String,
+    /// As written: "20", "v20.11.0", "3.12", "lts/*", ...
+    pub version: String,
+    pub kept_by: KeptBy,
+}
+
+/// `(tool, version)` pairs in a version file called `file_name`.
+fn parse_pins(file_name: &str, text: &str) -> Vec<(String, String)> {
+    let lines = || {
+        text.lines()
+            .map(|l| l.split('#').next().unwrap_or("").trim())
+            .filter(|l| !l.is_empty())
+    };
+    match file_name {
+        // asdf and mise: "nodejs 20.11.0 18.19.0" (later versions are fallbacks).
+        ".tool-versions" => lines()
+            .flat_map(|line| {
+                let mut words = line.split_whitespace();
+                let tool = canonical_tool(words.next().unwrap_or("")).to_string();
+                words.map(move |v| (tool.clone(), v.to_string()))
+            })
+            .collect(),
+        // [tools] node = "20" or python = ["3.12", "3.11"]
+        "mise.toml" | ".mise.toml" | "config.toml" => {
+            let Ok(doc) = toml::from_str::<toml::Table>(text) else {
+                return Vec::new();
+            };
+            let Some(tools) = doc.get("tools").and_then(|t| t.as_table()) else {
+                return Vec::new();
+            };
+            tools
+                .iter()
+                .flat_map(|(tool, value)| {
+                    let versions: Vec<String> = match value {
+                        toml::Value::String(v) => vec![v.clone()],
+                        toml::Value::Array(vs) => vs
+                            .iter()
+                            .filter_map(|v| v.as_str().map(str::to_string))
+                            .collect(),
+                        toml::Value::Table(t) => t
+                            .get("version")
+                            .and_then(|v| v.as_str())
+                            .map(|v| vec![v.to_string()])
+                            .unwrap_or_default(),
+                        _ => Vec::new(),
+                    };
+                    let tool = canonical_tool(tool).to_string();
+                    versions.into_iter().map(move |v| (tool.clone(), v))
+                })
+                .collect()
+        }
+        // .nvmrc and nvm's alias/default hold one version.
+        ".nvmrc" | "default" => lines()
+            .take(1)
+            .map(|v| ("node".to_string(), v.to_string()))
+            .collect(),
+        // pyenv allows several versions, one per line.
+        ".python-version" => lines()
+            .map(|v| ("python".to_string(), v.to_string()))
+            .collect(),
+        ".ruby-version" => lines()
+            .take(1)
+            .map(|v| ("ruby".to_string(), v.to_string()))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Global defaults of every manager, then version files under `project_roots`.
+pub fn pins(project_roots: &[PathBuf]) -> Vec<VersionPin> {
+    let mut defaults: Vec<(PathBuf, &str)> = Vec::new();
+    for manager in MANAGERS {
+        let Some(root) = root_of(manager) else {
+            continue;
+        };
+        match manager.name {
+            "nvm" => defaults.push((root.join("alias").join("default"), "default")),
+            "pyenv" => defaults.push((root.join("version"), ".python-version")),
+            "rbenv" => defaults.push((root.join("version"), ".ruby-version")),
+            _ => {}
+        }
+    }
+    if let Some(home) = dirs::home_dir() {
+        defaults.push((home.join(".tool-versions"), ".tool-versions"));
+    }
+    let mise_config = std::env::var_os("MISE_CONFIG_DIR")
+        .filter(|d| !d.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| dirs::home_dir().map(|home| home.join(".config").join("mise")));
+    if let Some(dir) = mise_config {
+        defaults.push((dir.join("config.toml"), "config.toml"));
+    }
+
+    let mut found = Vec::new();
+    for (file, format) in defaults {
+        let Ok(text) = fs::read_to_string(&file) else {
+            continue;
+        };
+        for (tool, version) in parse_pins(format, &text) {
+            found.push(VersionPin {
+                tool,
+                version,
+                kept_by: KeptBy::Default(file.clone()),
+            });
+        }
+    }
+    for (project, file) in projects::find_named_files(project_roots, VERSION_FILES) {
+        let Ok(text) = fs::read_to_string(&file) else {
+            continue;
+        };
+        let name = file
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        for (tool, version) in parse_pins(&name, &text) {
+            found.push(VersionPin {
+                tool,
+                version,
+                kept_by: KeptBy::Project {
+                    project: project.clone(),
+                    file: file.clone(),
+                },
+            });
+        }
+    }
+    found
+}
+
+/// nvm writes "v20.11.0", rbenv accepts "ruby-3.3.0"; compare without either prefix.
+fn bare_version<'a>(version: &'a str, tool: &str) -> &'a str {
+    let version = version.strip_prefix('v').unwrap_or(version);
+    version
+        .strip_prefix(tool)
+        .and_then(|rest| rest.strip_prefix('-'))
+        .unwrap_or(version)
+}
+
+/// Names that resolve to "the newest installed" rather than a fixed version.
+fn is_alias(version: &str) -> bool {
+    matches!(version, "latest" | "node" | "stable" | "lts" | "lts/*") || version.starts_with("lts/")
+}
+
+/// Whether `pin` selects `version`: exactly, or as a prefix ("20" selects "20.11.0").
+pub fn version_matches(pin: &str, version: &str, tool: &str) -> bool {
+    let pin = bare_version(pin, tool);
+    let version = bare_version(version, tool);
+    version == pin
+        || version
+            .strip_prefix(pin)
+            .is_some_and(|rest| rest.starts_with('.'))
+}
+
+/// Numeric parts of a version for ordering: "3.12.1" -> [3, 12, 1].
+fn version_key(version: &str) -> Vec<u64> {
+    version
+        .split(|c: char| !c.is_ascii_digit())
+        .filter_map(|part| part.parse().ok())
+        .collect()
+}
+
+/// The first pin that keeps `runtime`. An alias such as "lts/*" or "latest" keeps the newest
+/// installed version of that tool in the same manager; nvm can't be asked which one it means
+/// without running it.
+pub fn kept_by<'a>(
+    runtime: &Runtime,
+    pins: &'a [VersionPin],
+    installed: &[Runtime],
+) -> Option<&'a KeptBy> {
+    let is_newest = || {
+        installed
+            .iter()
+            .filter(|r| r.manager == runtime.manager && r.tool == runtime.tool)
+            .max_by_key(|r| version_key(&r.version))
+            .is_some_and(|newest| newest.path == runtime.path)
+    };
+    pins.iter()
+        .filter(|pin| pin.tool == runtime.tool)
+        .find(|pin| {
+            if is_alias(&pin.version) {
+                is_newest()
+            } else {
+                version_matches(&pin.version, &runtime.version, &runtime.tool)
+            }
+        })
+        .map(|pin| &pin.kept_by)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_pins_to_installed_versions() {
+        assert!(version_matches("20", "v20.11.0", "node"));
+        assert!(version_matches("v20.11.0", "v20.11.0", "node"));
+        assert!(!version_matches("2", "v20.11.0", "node"));
+        assert!(version_matches("3.12", "3.12.1", "python"));
+        assert!(!version_matches("3.1", "3.12.1", "python"));
+        assert!(version_matches("ruby-3.3.0", "3.3.0", "ruby"));
+
+        assert_eq!(
+            parse_pins(
+                ".tool-versions",
+                "nodejs 20.11.0 18.19.0 # lts\npython 3.12.1\n"
+            ),
+            [
+                ("node".to_string(), "20.11.0".to_string()),
+                ("node".to_string(), "18.19.0".to_string()),
+                ("python".to_string(), "3.12.1".to_string()),
+            ]
+        );
+        assert_eq!(
+            parse_pins(
+                "mise.toml",
+                "[tools]\nnode = \"20\"\npython = [\"3.12\", \"3.11\"]\n"
+            )
+            .len(),
+            3
+        );
+        assert_eq!(parse_pins(".python-version", "3.12.1\n3.11.7\n").len(), 2);
+        assert!(version_key("3.12.1") > version_key("3.9.18"));
+        assert!(is_alias("lts/iron"));
+    }
+}
@@ -0,0 +1,146 @@
+use anyhow::{anyhow, bail, Context, Result};
+use colored::Colorize;
+use std::time::Duration;
+
+use crate::models::Config;
+
+const SECS_PER_DAY: u64 = 86_400;
+
+/// Parse a human-friendly threshold such as "90d", "2w", "6months" or "36h".
+/// A bare number is read as days, matching the historical `--days` behavior.
+pub fn parse_threshold(input: &str) -> Result<Duration> {
+    let input = input.trim();
+    if input.is_empty() {
+        bail!("threshold is empty");
+    }
+    if input.starts_with('-') {
+        bail!("threshold must not be negative: '{}'", input);
+    }
+    if let Ok(days) = input.parse::<u64>() {
+        return from_days(days);
+    }
+    humantime::parse_duration(input).map_err(|err| {
+        anyhow!(
+            "invalid threshold '{}': {} (examples: 90d, 2w, 6months, 36h)",
+            input,
+            err
+        )
+    })
+}
+
+/// `clap` value parser for `--older-than`.
+pub fn parse_cli_threshold(input: &str) -> std::result::Result<Duration, String> {
+    parse_threshold(input).map_err(|err| err.to_string())
+}
+
+pub fn from_days(days: u64) -> Result<Duration> {
+    days.checked_mul(SECS_PER_DAY)
+        .map(Duration::from_secs)
+        .ok_or_else(|| anyhow!("threshold of {} days is too large", days))
+}
+
+/// The configured default: `default_threshold` (duration string) wins over the legacy
+/// `default_threshold_days` integer.
+pub fn configured_default(config: &Config) -> Result<Duration> {
+    if let Some(raw) = &config.default_threshold {
+        return parse_threshold(raw).context("Invalid default_threshold in config");
+    }
+    if config.default_threshold_days < 0 {
+        bail!(
+            "default_threshold_days must not be negative (got {})",
+            config.default_threshold_days
+        );
+    }
+    from_days(config.default_threshold_days as u64)
+}
+
+/// The configured `min_threshold`, if any.
+pub fn configured_minimum(config: &Config) -> Result<Option<Duration>> {
+    config
+        .min_threshold
+        .as_deref()
+        .map(|raw| parse_threshold(raw).context("Invalid min_threshold in config"))
+        .transpose()
+}
+
+/// Raise `threshold` to the configured minimum (with a warning) if it is shorter.
+pub fn enforce_minimum(threshold: Duration, config: &Config) -> Result<Duration> {
+    match configured_minimum(config)? {
+        Some(min) if threshold < min => {
+            eprintln!(
+                "{} Threshold {} is below min_threshold; using {}.",
+                "[!]".yellow(),
+                describe(threshold),
+                describe(min)
+            );
+            Ok(min)
+        }
+        _ => Ok(threshold),
+    }
+}
+
+/// Effective threshold: the command line first, then the config default; never below
+/// `min_threshold`.
+pub fn resolve(explicit: Option<Duration>, config: &Config) -> Result<Duration> {
+    let threshold = match explicit {
+        Some(t) => t,
+        None => configured_default(config)?,
+    };
+    enforce_minimum(threshold, config)
+}
+
+/// Threshold given explicitly on the command line, if any (`--older-than` or legacy `--days`).
+pub fn explicit(older_than: Option<Duration>, days: Option<u64>) -> Result<Option<Duration>> {
+    match (older_than, days) {
+        (Some(d), _) => Ok(Some(d)),
+        (None, Some(days)) => from_days(days).map(Some),
+        (None, None) => Ok(None),
+    }
+}
+
+/// Human-readable form for messages, e.g. "90 days" or "6months".
+pub fn describe(threshold: Duration) -> String {
+    let secs = threshold.as_secs();
+    if secs.is_multiple_of(SECS_PER_DAY) {
+        let days = secs / SECS_PER_DAY;
+        return format!("{} day{}", days, if days == 1 { "" } else { "s" });
+    }
+    humantime::format_duration(Duration::from_secs(secs)).to_string()
+}
+
+/// Compact form that `--older-than` accepts, e.g. "90d" or "6months".
+pub fn to_arg(threshold: Duration) -> String {
+    let secs = threshold.as_secs();
+    if secs.is_multiple_of(SECS_PER_DAY) {
+        return format!("{}d", secs / SECS_PER_DAY);
+    }
+    humantime::format_duration(Duration::from_secs(secs))
+        .to_string()
+        .replace(' ', "")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_units_and_bare_days() {
+        assert_eq!(parse_threshold("90").unwrap(), from_days(90).unwrap());
+        assert_eq!(parse_threshold("2w").unwrap(), from_days(14).unwrap());
+        assert_eq!(
+            parse_threshold("36h").unwrap(),
+            Duration::from_secs(36 * 3600)
+        );
+        assert!(parse_threshold("6months").unwrap() > from_days(180).unwrap());
+        assert!(parse_threshold("-3d").is_err());
+        assert!(parse_threshold("soon").is_err());
+    }
+
+    #[test]
+    fn formats_round_trip() {
+        assert_eq!(describe(from_days(1).unwrap()), "1 day");
+        assert_eq!(to_arg(from_days(90).unwrap()), "90d");
+        let d = Duration::from_secs(36 * 3600);
+        assert_eq!(parse_threshold(&to_arg(d)).unwrap(), d);
+    }
+}
@@ -0,0 +1,207 @@
+use anyhow::{Context, Result};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::analyzer::dir_usage;
+use crate::models::{Config, LastUsedSource};
+use crate::pool;
+use crate::projects::ToolchainFile;
+
+/// An installed rustup toolchain: one directory under `$RUSTUP_HOME/toolchains`.
+#[derive(Debug, Clone)]
+pub struct Toolchain {
+    /// Directory name, e.g. "stable-x86_64-unknown-linux-gnu" or "1.75.0-aarch64-apple-darwin".
+    pub name: String,
+    pub path: PathBuf,
+    /// Total size of every file in the toolchain (0 for linked toolchains).
+    pub size: u64,
+    /// Newest last-used time of the programs in its `bin` directory.
+    pub last_used: SystemTime,
+    pub last_used_source: LastUsedSource,
+    /// Added with `rustup toolchain link`: a symlink to a toolchain built elsewhere, which
+    /// bin-expire never moves.
+    pub linked: bool,
+}
+
+/// Why a toolchain must stay, even if nothing ran from it lately.
+#[derive(Debug, Clone)]
+pub enum UsedBy {
+    Default,
+    /// `rustup override set` for this directory.
+    Override(PathBuf),
+    Project(ToolchainFile),
+}
+
+impl fmt::Display for UsedBy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UsedBy::Default => write!(f, "default toolchain"),
+            UsedBy::Override(dir) => write!(f, "override for {}", dir.display()),
+            UsedBy::Project(file) => write!(f, "{}", file),
+        }
+    }
+}
+
+impl UsedBy {
+    /// "default", "override" or "project".
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UsedBy::Default => "default",
+            UsedBy::Override(_) => "override",
+            UsedBy::Project(_) => "project",
+        }
+    }
+}
+
+/// `$RUSTUP_HOME`, else `~/.rustup`.
+pub fn rustup_home() -> Option<PathBuf> {
+    match std::env::var_os("RUSTUP_HOME") {
+        Some(home) if !home.is_empty() => Some(PathBuf::from(home)),
+        _ => dirs::home_dir().map(|home| home.join(".rustup")),
+    }
+}
+
+/// The parts of rustup's settings.toml that pin toolchains.
+#[derive(Debug, Default)]
+pub struct Settings {
+    pub default_toolchain: Option<String>,
+    /// Directory overrides (`rustup override set`): directory and toolchain.
+    pub overrides: Vec<(PathBuf, String)>,
+}
+
+impl Settings {
+    /// A missing settings.toml means no default and no overrides.
+    pub fn load(rustup_home: &Path) -> Result<Self> {
+        let path = rustup_home.join("settings.toml");
+        if !path.exists() {
+            return Ok(Settings::default());
+        }
+        let raw = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let table: toml::Table =
+            toml::from_str(&raw).with_context(|| format!("Failed to parse {}", path.display()))?;
+
+        let overrides = table
+            .get("overrides")
+            .and_then(|o| o.as_table())
+            .map(|o| {
+                o.iter()
+                    .filter_map(|(dir, name)| {
+                        Some((PathBuf::from(dir), name.as_str()?.to_string()))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        Ok(Settings {
+            default_toolchain: table
+                .get("default_toolchain")
+                .and_then(|d| d.as_str())
+                .map(str::to_string),
+            overrides,
+        })
+    }
+}
+
+/// Whether `channel` (as written in settings.toml or a rust-toolchain file) names the installed
+/// toolchain `name`: exactly, or with the host triple appended. "nightly" does not match
+/// "nightly-2024-01-01-x86_64-unknown-linux-gnu"; triples start with a letter, dates don't.
+pub fn channel_matches(channel: &str, name: &str) -> bool {
+    name == channel
+        || name
+            .strip_prefix(channel)
+            .and_then(|rest| rest.strip_prefix('-'))
+            .is_some_and(|triple| triple.starts_with(|c: char| c.is_ascii_alphabetic()))
+}
+
+/// The first reason to keep `name`: the default toolchain, a directory override, or a project's
+/// rust-toolchain file.
+pub fn used_by(name: &str, settings: &Settings, files: &[ToolchainFile]) -> Option<UsedBy> {
+    if settings
+        .default_toolchain
+        .as_deref()
+        .is_some_and(|d| channel_matches(d, name))
+    {
+        return Some(UsedBy::Default);
+    }
+    if let Some((dir, _)) = settings
+        .overrides
+        .iter()
+        .find(|(_, channel)| channel_matches(channel, name))
+    {
+        return Some(UsedBy::Override(dir.clone()));
+    }
+    files
+        .iter()
+        .find(|f| channel_matches(&f.channel, name))
+        .map(|f| UsedBy::Project(f.clone()))
+}
+
+fn measure(name: String, path: PathBuf, windows_use_access_time: bool) -> Toolchain {
+    // `rustup toolchain link` leaves a symlink; what it points to isn't ours to measure.
+    let linked = fs::symlink_metadata(&path).is_ok_and(|m| m.file_type().is_symlink());
+    let usage = dir_usage(&path, windows_use_access_time);
+    Toolchain {
+        name,
+        path,
+        size: if linked { 0 } else { usage.size },
+        last_used: usage.last_used,
+        last_used_source: usage.last_used_source,
+        linked,
+    }
+}
+
+/// Every toolchain under `rustup_home`, sorted by name. Toolchains are measured on
+/// `scan_threads` workers; a full one is tens of thousands of files.
+pub fn installed(rustup_home: &Path, config: &Config) -> Result<Vec<Toolchain>> {
+    let dir = rustup_home.join("toolchains");
+    let mut entries: Vec<(String, PathBuf)> = fs::read_dir(&dir)
+        .with_context(|| format!("Failed to read {}", dir.display()))?
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path().is_dir())
+        .map(|e| (e.file_name().to_string_lossy().to_string(), e.path()))
+        .collect();
+    entries.sort();
+
+    Ok(pool::map(&entries, config.scan_threads, |(name, path)| {
+        measure(name.clone(), path.clone(), config.windows_use_access_time)
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_channels_to_toolchain_dirs() {
+        let host = "stable-x86_64-unknown-linux-gnu";
+        assert!(channel_matches("stable", host));
+        assert!(channel_matches(host, host));
+        assert!(!channel_matches("beta", host));
+        assert!(channel_matches("1.75.0", "1.75.0-x86_64-unknown-linux-gnu"));
+        assert!(!channel_matches("1.75", "1.75.0-x86_64-unknown-linux-gnu"));
+        assert!(!channel_matches(
+            "nightly",
+            "nightly-2024-01-01-x86_64-unknown-linux-gnu"
+        ));
+        assert!(channel_matches(
+            "nightly-2024-01-01",
+            "nightly-2024-01-01-x86_64-unknown-linux-gnu"
+        ));
+
+        let settings = Settings {
+            default_toolchain: Some(host.to_string()),
+            overrides: vec![(PathBuf::from("/src/old"), "1.70.0".to_string())],
+        };
+        assert!(matches!(
+            used_by(host, &settings, &[]),
+            Some(UsedBy::Default)
+        ));
+        assert!(matches!(
+            used_by("1.70.0-x86_64-unknown-linux-gnu", &settings, &[]),
+            Some(UsedBy::Override(_))
+        ));
+        assert!(used_by("beta-x86_64-unknown-linux-gnu", &settings, &[]).is_none());
+    }
+}
@@ -3,53 +3,238 @@ use colored::Colorize;
 use std::path::{Path, PathBuf};
 use tabled::Tabled;
 
+use crate::say;
+
 #[cfg(windows)]
 use crate::analyzer::{select_last_used_time, FileTimes};
 
-// Default View: Compare Access vs Mod dates
+pub mod prompt;
+pub mod table;
+
+// Pin listing (`pin` without a name)
 #[derive(Tabled)]
-pub struct DefaultRow {
+pub struct PinRow {
     #[tabled(rename = "ST")]
     pub st: &'static str,
 
     #[tabled(rename = "NAME")]
     pub name: String,
 
+    #[tabled(rename = "REASON")]
+    pub reason: String,
+
+    #[tabled(rename = "UNTIL")]
+    pub until: String,
+
+    #[tabled(rename = "PINNED AT")]
+    pub pinned_at: String,
+}
+
+// Per-binary scan history (`history`)
+#[derive(Tabled)]
+pub struct HistoryRow {
+    #[tabled(rename = "NAME")]
+    pub name: String,
+
+    #[tabled(rename = "STATUS")]
+    pub status: String,
+
+    #[tabled(rename = "FIRST SEEN")]
+    pub first_seen: String,
+
+    #[tabled(rename = "WENT STALE")]
+    pub went_stale: String,
+
+    #[tabled(rename = "LAST SEEN")]
+    pub last_seen: String,
+
     #[tabled(rename = "SIZE")]
     pub size: String,
+}
+
+// Directory sizes per recorded scan (`history --sizes`)
+#[derive(Tabled)]
+pub struct ScanSizeRow {
+    #[tabled(rename = "SCAN")]
+    pub scan: u64,
+
+    #[tabled(rename = "TAKEN AT")]
+    pub taken_at: String,
 
-    #[tabled(rename = "ACCESSED")]
-    pub accessed: String,
+    #[tabled(rename = "DIR")]
+    pub dir: String,
 
-    #[tabled(rename = "MODIFIED")]
-    pub modified: String,
+    #[tabled(rename = "BINARIES")]
+    pub binaries: usize,
+
+    #[tabled(rename = "TOTAL")]
+    pub total: String,
+
+    #[tabled(rename = "CHANGE")]
+    pub change: String,
 }
 
-// Verbose View: Adds Path
+// One binary across recorded scans (`history <name>`)
 #[derive(Tabled)]
-pub struct VerboseRow {
+pub struct TimelineRow {
+    #[tabled(rename = "SCAN")]
+    pub scan: u64,
+
+    #[tabled(rename = "TAKEN AT")]
+    pub taken_at: String,
+
+    #[tabled(rename = "STATUS")]
+    pub status: String,
+
+    #[tabled(rename = "SIZE")]
+    pub size: String,
+
+    #[tabled(rename = "LAST USED")]
+    pub last_used: String,
+
+    #[tabled(rename = "PATH")]
+    pub path: String,
+}
+
+// Changes between two recorded scans (`diff`)
+#[derive(Tabled)]
+pub struct DiffRow {
+    #[tabled(rename = "CHANGE")]
+    pub change: String,
+
+    #[tabled(rename = "NAME")]
+    pub name: String,
+
+    #[tabled(rename = "LAST USED BEFORE")]
+    pub before: String,
+
+    #[tabled(rename = "LAST USED AFTER")]
+    pub after: String,
+
+    #[tabled(rename = "PATH")]
+    pub path: String,
+}
+
+// Archive manifest listing (`list`)
+#[derive(Tabled)]
+pub struct ListRow {
     #[tabled(rename = "ST")]
     pub st: &'static str,
 
-    #[tabled(rename = "SRC")]
-    pub src: &'static str,
-
     #[tabled(rename = "NAME")]
     pub name: String,
 
+    #[tabled(rename = "MOVED AT")]
+    pub moved_at: String,
+
+    #[tabled(rename = "ORIGINAL PATH")]
+    pub original_path: String,
+
+    #[tabled(rename = "ARCHIVED PATH")]
+    pub archived_path: String,
+}
+
+// Installed rustup toolchains (`toolchains`)
+#[derive(Tabled)]
+pub struct ToolchainRow {
+    #[tabled(rename = "ST")]
+    pub st: &'static str,
+
+    #[tabled(rename = "TOOLCHAIN")]
+    pub name: String,
+
     #[tabled(rename = "SIZE")]
     pub size: String,
 
-    #[tabled(rename = "ACCESSED")]
-    pub accessed: String,
+    #[tabled(rename = "LAST USED")]
+    pub last_used: String,
+
+    #[tabled(rename = "KEPT BY")]
+    pub kept_by: String,
+}
+
+// Installed runtime versions (`runtimes`)
+#[derive(Tabled)]
+pub struct RuntimeRow {
+    #[tabled(rename = "ST")]
+    pub st: &'static str,
+
+    #[tabled(rename = "MANAGER")]
+    pub manager: &'static str,
+
+    #[tabled(rename = "TOOL")]
+    pub tool: String,
 
-    #[tabled(rename = "MODIFIED")]
-    pub modified: String,
+    #[tabled(rename = "VERSION")]
+    pub version: String,
+
+    #[tabled(rename = "SIZE")]
+    pub size: String,
+
+    #[tabled(rename = "LAST USED")]
+    pub last_used: String,
+
+    #[tabled(rename = "KEPT BY")]
+    pub kept_by: String,
+}
+
+// Package caches per ecosystem (`caches`)
+#[derive(Tabled)]
+pub struct CacheSummaryRow {
+    #[tabled(rename = "CACHE")]
+    pub cache: &'static str,
+
+    #[tabled(rename = "ENTRIES")]
+    pub entries: usize,
+
+    #[tabled(rename = "SIZE")]
+    pub size: String,
+
+    #[tabled(rename = "STALE")]
+    pub stale: usize,
+
+    #[tabled(rename = "RECLAIMABLE")]
+    pub reclaimable: String,
 
     #[tabled(rename = "PATH")]
     pub path: String,
 }
 
+// Stale package cache entries (`caches --verbose`)
+#[derive(Tabled)]
+pub struct CacheEntryRow {
+    #[tabled(rename = "CACHE")]
+    pub cache: &'static str,
+
+    #[tabled(rename = "ENTRY")]
+    pub name: String,
+
+    #[tabled(rename = "SIZE")]
+    pub size: String,
+
+    #[tabled(rename = "LAST USED")]
+    pub last_used: String,
+}
+
+// Build output directories (`sweep`)
+#[derive(Tabled)]
+pub struct SweepRow {
+    #[tabled(rename = "ST")]
+    pub st: &'static str,
+
+    #[tabled(rename = "KIND")]
+    pub kind: &'static str,
+
+    #[tabled(rename = "PATH")]
+    pub path: String,
+
+    #[tabled(rename = "SIZE")]
+    pub size: String,
+
+    #[tabled(rename = "PROJECT TOUCHED")]
+    pub touched: String,
+}
+
 /// Helper to convert "~" to the actual home directory
 pub fn expand_tilde(path: &str) -> PathBuf {
     if !path.starts_with('~') {
@@ -140,7 +325,7 @@ pub fn print_mount_option_warning(path: &Path) {
             return;
         };
         if opts.contains("noatime") || opts.contains("relatime") {
-            println!(
+            say!(
                 "{} Warning: Filesystem is mounted with 'noatime' or 'relatime'. 'Last Accessed' dates may be inaccurate.",
                 "[!]".yellow()
             );
@@ -189,7 +374,7 @@ pub fn maybe_fallback_from_atime_contamination(
         );
         eprintln!("    Falling back to modified time (mtime) for this run.");
         eprintln!("    Tip: Set windows_use_access_time=false in config.toml to avoid this check.");
-        println!();
+        say!();
 
         for bin in binaries.iter_mut() {
             let (last_used, source) = select_last_used_time(
@@ -234,35 +419,64 @@ pub fn print_windows_notice(windows_use_access_time: bool) {
     eprintln!();
 }
 
+pub struct StatusCounts {
+    pub ok: u64,
+    pub stub: u64,
+    pub stale: u64,
+    pub broken: u64,
+    pub pinned: u64,
+    pub referenced: u64,
+}
+
 pub fn print_scan_status_info(
-    days: i64,
-    ok_count: u64,
-    stub_count: u64,
-    stale_count: u64,
+    threshold: std::time::Duration,
+    counts: StatusCounts,
     hide_ok: bool,
     hide_stub: bool,
 ) {
-    println!(
-        "{} (info) STALE={} OK={} STUB={} (filters: hide_ok={}, hide_stub={}).",
+    let threshold = crate::threshold::describe(threshold);
+    say!(
+        "{} (info) STALE={} OK={} STUB={} BROKEN={} PINNED={} REFERENCED={} (filters: hide_ok={}, hide_stub={}).",
         "[i]".blue(),
-        stale_count,
-        ok_count,
-        stub_count,
+        counts.stale,
+        counts.ok,
+        counts.stub,
+        counts.broken,
+        counts.pinned,
+        counts.referenced,
         hide_ok,
         hide_stub
     );
-    println!(
-        "{} OK: non-stub binaries with last_used within {} days.",
+    say!(
+        "{} OK: non-stub binaries with last_used within {}.",
         "[i]".blue(),
-        days
+        threshold
     );
-    println!(
-        "{} STALE: non-stub binaries with last_used older than {} days.",
+    say!(
+        "{} STALE: non-stub binaries with last_used older than {}.",
         "[i]".blue(),
-        days
+        threshold
     );
-    println!(
+    say!(
         "{} STUB: a 0-byte .exe App Execution Alias stub; treated specially and never archived.",
         "[i]".blue()
     );
+    if counts.broken > 0 {
+        say!(
+            "{} BROKEN: a symlink whose target no longer exists; never archived.",
+            "[i]".blue()
+        );
+    }
+    if counts.pinned > 0 {
+        say!(
+            "{} PINNED: protected with `bin-expire pin` until the pin expires; never archived.",
+            "[i]".blue()
+        );
+    }
+    if counts.referenced > 0 {
+        say!(
+            "{} REFERENCED: would be stale, but a systemd unit, crontab, shell rc, git or ssh config runs it; never archived.",
+            "[i]".blue()
+        );
+    }
 }
@@ -0,0 +1,111 @@
+use anyhow::{Context, Result};
+use colored::Colorize;
+use std::io::{self, BufRead, Write};
+
+use crate::output;
+use crate::say;
+
+/// Read one line from stdin. Returns `None` on EOF (e.g. stdin is closed or not attached).
+fn read_line() -> Result<Option<String>> {
+    let mut line = String::new();
+    let n = io::stdin()
+        .lock()
+        .read_line(&mut line)
+        .context("Failed to read from stdin")?;
+    if n == 0 {
+        return Ok(None);
+    }
+    Ok(Some(line.trim().to_string()))
+}
+
+/// Ask a yes/no question (default: no). Returns `None` if stdin reached EOF without an answer.
+pub fn confirm(question: &str) -> Result<Option<bool>> {
+    loop {
+        let mut out = output::human();
+        write!(out, "{} {} ", question, "[y/N]".dimmed()).ok();
+        out.flush().ok();
+
+        let Some(answer) = read_line()? else {
+            say!();
+            return Ok(None);
+        };
+        match answer.to_ascii_lowercase().as_str() {
+            "y" | "yes" => return Ok(Some(true)),
+            "" | "n" | "no" => return Ok(Some(false)),
+            _ => say!("Please answer 'y' or 'n'."),
+        }
+    }
+}
+
+/// Parse a toggle command such as "1 3 5-7" into zero-based indices.
+fn parse_toggles(input: &str, len: usize) -> Option<Vec<usize>> {
+    let mut out = Vec::new();
+    for part in input.split(|c: char| c == ',' || c.is_whitespace()) {
+        if part.is_empty() {
+            continue;
+        }
+        let (lo, hi) = match part.split_once('-') {
+            Some((a, b)) => (a.parse::<usize>().ok()?, b.parse::<usize>().ok()?),
+            None => {
+                let n = part.parse::<usize>().ok()?;
+                (n, n)
+            }
+        };
+        if lo == 0 || hi < lo || hi > len {
+            return None;
+        }
+        out.extend((lo - 1)..hi);
+    }
+    Some(out)
+}
+
+/// Show a numbered checklist (everything starts selected) and let the user toggle entries.
+/// Returns the selection on Enter, or `None` if the user quits or stdin reaches EOF.
+pub fn checklist(title: &str, labels: &[String]) -> Result<Option<Vec<bool>>> {
+    let mut selected = vec![true; labels.len()];
+
+    loop {
+        say!();
+        say!("{}", title.bold());
+        for (i, label) in labels.iter().enumerate() {
+            let mark = if selected[i] {
+                "[x]".green()
+            } else {
+                "[ ]".dimmed()
+            };
+            say!("  {} {:>3}. {}", mark, i + 1, label);
+        }
+        say!(
+            "{}",
+            "Toggle with numbers (e.g. \"1 3\" or \"2-5\"), a = all, n = none, Enter = done, q = quit"
+                .dimmed()
+        );
+        let mut out = output::human();
+        write!(out, "> ").ok();
+        out.flush().ok();
+
+        let Some(input) = read_line()? else {
+            say!();
+            return Ok(None);
+        };
+        match input.to_ascii_lowercase().as_str() {
+            "" => return Ok(Some(selected)),
+            "q" | "quit" => return Ok(None),
+            "a" | "all" => selected.iter_mut().for_each(|s| *s = true),
+            "n" | "none" => selected.iter_mut().for_each(|s| *s = false),
+            other => match parse_toggles(other, labels.len()) {
+                Some(indices) => {
+                    for i in indices {
+                        selected[i] = !selected[i];
+                    }
+                }
+                None => say!(
+                    "{} Unrecognized input '{}'; use numbers between 1 and {}.",
+                    "[!]".yellow(),
+                    other,
+                    labels.len()
+                ),
+            },
+        }
+    }
+}
@@ -0,0 +1,179 @@
+use clap::ValueEnum;
+use std::cmp::Ordering;
+use tabled::builder::Builder;
+use tabled::settings::style::Style;
+
+use crate::analyzer::ecosystem_for;
+use crate::models::{BinaryInfo, BinaryStatus, LastUsedSource};
+use crate::ui::{format_bytes, format_date_short};
+
+/// A scan table column (selectable with `--columns`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Column {
+    /// Status glyph
+    St,
+    /// Where last_used came from (A=atime, M=mtime, ?=unknown)
+    Src,
+    Name,
+    Size,
+    Accessed,
+    Modified,
+    /// The timestamp used for the stale check
+    LastUsed,
+    /// Directory containing the binary
+    Dir,
+    /// Toolchain/ecosystem the directory belongs to (cargo, go, ...)
+    Ecosystem,
+    /// File kind from its first bytes, plus the Go version for Go binaries
+    Kind,
+    Path,
+}
+
+impl Column {
+    pub const DEFAULT: [Column; 5] = [
+        Column::St,
+        Column::Name,
+        Column::Size,
+        Column::Accessed,
+        Column::Modified,
+    ];
+
+    pub const VERBOSE: [Column; 7] = [
+        Column::St,
+        Column::Src,
+        Column::Name,
+        Column::Size,
+        Column::Accessed,
+        Column::Modified,
+        Column::Path,
+    ];
+
+    fn header(self) -> &'static str {
+        match self {
+            Column::St => "ST",
+            Column::Src => "SRC",
+            Column::Name => "NAME",
+            Column::Size => "SIZE",
+            Column::Accessed => "ACCESSED",
+            Column::Modified => "MODIFIED",
+            Column::LastUsed => "LAST USED",
+            Column::Dir => "DIR",
+            Column::Ecosystem => "ECOSYSTEM",
+            Column::Kind => "KIND",
+            Column::Path => "PATH",
+        }
+    }
+
+    fn cell(self, bin: &BinaryInfo, status: BinaryStatus) -> String {
+        match self {
+            Column::St => status.glyph().to_string(),
+            Column::Src => match bin.last_used_source {
+                LastUsedSource::Accessed => "A",
+                LastUsedSource::Modified => "M",
+                LastUsedSource::Unknown => "?",
+            }
+            .to_string(),
+            Column::Name => bin.name.clone(),
+            Column::Size => format_bytes(bin.size),
+            Column::Accessed => format_date_short(bin.accessed),
+            Column::Modified => format_date_short(bin.modified),
+            Column::LastUsed => format_date_short(Some(bin.last_used)),
+            Column::Dir => parent_dir(bin),
+            Column::Ecosystem => ecosystem_for(&bin.path).to_string(),
+            Column::Kind => bin.details.as_ref().map_or("-".to_string(), |d| d.label()),
+            Column::Path => bin.path.display().to_string(),
+        }
+    }
+}
+
+/// Scan table sort order. Sizes sort largest first and times oldest first,
+/// so the most interesting rows are on top; `--reverse` flips any order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum SortKey {
+    #[default]
+    Name,
+    /// Largest first
+    Size,
+    /// Oldest last_used first
+    Age,
+    /// Oldest access time first
+    Accessed,
+    /// Oldest modification time first
+    Modified,
+    /// By directory, then name
+    Dir,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum GroupBy {
+    Dir,
+    Ecosystem,
+    Status,
+}
+
+fn parent_dir(bin: &BinaryInfo) -> String {
+    bin.path
+        .parent()
+        .map(|p| p.display().to_string())
+        .unwrap_or_default()
+}
+
+pub fn sort_rows(rows: &mut [(BinaryInfo, BinaryStatus)], key: SortKey, reverse: bool) {
+    let by_name =
+        |a: &BinaryInfo, b: &BinaryInfo| a.name.to_lowercase().cmp(&b.name.to_lowercase());
+
+    rows.sort_by(|(a, _), (b, _)| {
+        let primary = match key {
+            SortKey::Name => Ordering::Equal,
+            SortKey::Size => b.size.cmp(&a.size),
+            SortKey::Age => a.last_used.cmp(&b.last_used),
+            SortKey::Accessed => a.accessed.cmp(&b.accessed),
+            SortKey::Modified => a.modified.cmp(&b.modified),
+            SortKey::Dir => a.path.parent().cmp(&b.path.parent()),
+        };
+        primary.then_with(|| by_name(a, b))
+    });
+
+    if reverse {
+        rows.reverse();
+    }
+}
+
+/// Group label for a row; groups are shown in order of first appearance
+/// (status groups always in severity order).
+pub fn group_label(group_by: GroupBy, bin: &BinaryInfo, status: BinaryStatus) -> String {
+    match group_by {
+        GroupBy::Dir => parent_dir(bin),
+        GroupBy::Ecosystem => ecosystem_for(&bin.path).to_string(),
+        GroupBy::Status => status.as_str().to_uppercase(),
+    }
+}
+
+pub fn status_rank(status: BinaryStatus) -> u8 {
+    match status {
+        BinaryStatus::Stale => 0,
+        BinaryStatus::Broken => 1,
+        BinaryStatus::Stub => 2,
+        BinaryStatus::Pinned => 3,
+        BinaryStatus::Referenced => 4,
+        BinaryStatus::Ok => 5,
+    }
+}
+
+/// Render rows with the selected columns. `modern` selects the boxed (verbose) style;
+/// otherwise the compact markdown style is used.
+pub fn render(rows: &[(BinaryInfo, BinaryStatus)], columns: &[Column], modern: bool) -> String {
+    let mut builder = Builder::default();
+    builder.push_record(columns.iter().map(|c| c.header()));
+    for (bin, status) in rows {
+        builder.push_record(columns.iter().map(|c| c.cell(bin, *status)));
+    }
+
+    let mut table = builder.build();
+    if modern {
+        table.with(Style::modern());
+    } else {
+        table.with(Style::markdown());
+    }
+    table.to_string()
+}
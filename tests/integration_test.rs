@@ -1,8 +1,9 @@
 use filetime::{set_file_times, FileTime};
 use serde::Deserialize;
 use std::fs;
+use std::io::Write;
 use std::path::{Path, PathBuf};
-use std::process::{Command, Output};
+use std::process::{Command, Output, Stdio};
 use std::time::{Duration, SystemTime};
 
 #[derive(Debug, Deserialize)]
@@ -42,9 +43,19 @@ fn unique_dir(prefix: &str) -> PathBuf {
     std::env::temp_dir().join(format!("{}_{}_{}", prefix, pid, ts))
 }
 
+/// Point the binary at the test's config root and keep the host's system config and
+/// BIN_EXPIRE_* overrides out of the test.
+fn isolate_config(cmd: &mut Command, config_root: &Path) {
+    cmd.env("BIN_EXPIRE_CONFIG_DIR", config_root);
+    cmd.env("BIN_EXPIRE_SYSTEM_CONFIG", "");
+    cmd.env_remove("BIN_EXPIRE_THRESHOLD");
+    cmd.env_remove("BIN_EXPIRE_ARCHIVE_PATH");
+    cmd.env_remove("BIN_EXPIRE_PROFILE");
+}
+
 fn run_cli(args: &[&str], config_root: &Path) -> Output {
     let mut cmd = Command::new("cargo");
-    cmd.env("BIN_EXPIRE_CONFIG_DIR", config_root);
+    isolate_config(&mut cmd, config_root);
     cmd.args(["run", "--"]);
     cmd.args(args);
     if test_verbose() {
@@ -60,6 +71,42 @@ fn run_cli(args: &[&str], config_root: &Path) -> Output {
     cmd.output().expect("Failed to execute command")
 }
 
+fn run_cli_with_env(args: &[&str], config_root: &Path, env: &[(&str, &str)]) -> Output {
+    let mut cmd = Command::new("cargo");
+    isolate_config(&mut cmd, config_root);
+    cmd.envs(env.iter().copied());
+    cmd.args(["run", "--"]);
+    cmd.args(args);
+    cmd.output().expect("Failed to execute command")
+}
+
+fn run_cli_with_stdin(args: &[&str], config_root: &Path, stdin: &str) -> Output {
+    let mut cmd = Command::new("cargo");
+    isolate_config(&mut cmd, config_root);
+    cmd.args(["run", "--"]);
+    cmd.args(args);
+    cmd.stdin(Stdio::piped());
+    cmd.stdout(Stdio::piped());
+    cmd.stderr(Stdio::piped());
+    if test_verbose() {
+        eprintln!(
+            "[bin-expire tests] running: cargo run -- {} (stdin: {:?})",
+            args.join(" "),
+            stdin
+        );
+    }
+    let mut child = cmd.spawn().expect("Failed to execute command");
+    child
+        .stdin
+        .take()
+        .expect("stdin was not piped")
+        .write_all(stdin.as_bytes())
+        .expect("Failed to write stdin");
+    child
+        .wait_with_output()
+        .expect("Failed to wait for command")
+}
+
 fn cleanup_dir(path: &Path) {
     if test_keep_artifacts() {
         eprintln!(
@@ -217,7 +264,14 @@ fn test_archive_and_restore_roundtrip() {
     let artifacts_dir = test_dir.join("_test_artifacts");
 
     let output = run_cli(
-        &["archive", "-p", test_dir.to_str().unwrap(), "--days", "30"],
+        &[
+            "archive",
+            "-p",
+            test_dir.to_str().unwrap(),
+            "--days",
+            "30",
+            "--yes",
+        ],
         &config_root,
     );
     write_artifact(&artifacts_dir, "archive.stdout.txt", &output.stdout);
@@ -323,7 +377,14 @@ fn test_restore_safety_and_archive_again() {
     set_file_times(&file_path, ft, ft).expect("Failed to backdate file");
 
     let output = run_cli(
-        &["archive", "-p", test_dir.to_str().unwrap(), "--days", "30"],
+        &[
+            "archive",
+            "-p",
+            test_dir.to_str().unwrap(),
+            "--days",
+            "30",
+            "--yes",
+        ],
         &config_root,
     );
     write_artifact(&artifacts_dir, "archive1.stdout.txt", &output.stdout);
@@ -418,7 +479,14 @@ fn test_restore_safety_and_archive_again() {
     fs::write(&file2_path, "content2").expect("Failed to write test file 2");
     set_file_times(&file2_path, ft, ft).expect("Failed to backdate file 2");
     let output = run_cli(
-        &["archive", "-p", test_dir.to_str().unwrap(), "--days", "30"],
+        &[
+            "archive",
+            "-p",
+            test_dir.to_str().unwrap(),
+            "--days",
+            "30",
+            "--yes",
+        ],
         &config_root,
     );
     write_artifact(&artifacts_dir, "archive2.stdout.txt", &output.stdout);
@@ -459,3 +527,2807 @@ fn test_restore_safety_and_archive_again() {
     cleanup_dir(&archive_dir);
     cleanup_dir(&config_root);
 }
+
+/// This test verifies `archive --atomic` leaves everything in place when a move fails, including
+/// moving back the files that were archived before the failing one.
+#[test]
+fn test_atomic_archive_failure_leaves_files_and_manifest() {
+    let test_dir = unique_dir("test_integration_dir_atomic");
+    let config_root = unique_dir("test_integration_config_atomic");
+    let archive_root = unique_dir("test_integration_archive_atomic");
+
+    fs::create_dir_all(&test_dir).expect("Failed to create test dir");
+    fs::create_dir_all(&archive_root).expect("Failed to create archive root");
+
+    // A regular file where the archive directory should be makes every move fail.
+    let archive_dir = archive_root.join("not_a_dir");
+    fs::write(&archive_dir, "blocker").expect("Failed to write archive blocker file");
+
+    let cfg_dir = config_root.join("bin-expire");
+    fs::create_dir_all(&cfg_dir).expect("Failed to create config dir");
+
+    let archive_str = archive_dir.to_string_lossy().replace('\\', "\\\\");
+    let config_toml = format!(
+        "ignored_bins = []\ndefault_threshold_days = 90\narchive_path = \"{}\"\nwindows_use_access_time = false\n",
+        archive_str
+    );
+    fs::write(cfg_dir.join("config.toml"), config_toml).expect("Failed to write config.toml");
+
+    let old_time = SystemTime::now() - Duration::from_secs(86400 * 100);
+    let ft = FileTime::from_system_time(old_time);
+    let file_paths: Vec<PathBuf> = ["old_tool_a.exe", "old_tool_b.exe"]
+        .iter()
+        .map(|name| {
+            let p = test_dir.join(name);
+            fs::write(&p, "content").expect("Failed to write test file");
+            set_file_times(&p, ft, ft).expect("Failed to backdate file");
+            p
+        })
+        .collect();
+
+    let artifacts_dir = test_dir.join("_test_artifacts");
+    let output = run_cli(
+        &[
+            "archive",
+            "-p",
+            test_dir.to_str().unwrap(),
+            "--days",
+            "30",
+            "--atomic",
+            "--yes",
+        ],
+        &config_root,
+    );
+    write_artifact(&artifacts_dir, "archive_atomic.stdout.txt", &output.stdout);
+    write_artifact(&artifacts_dir, "archive_atomic.stderr.txt", &output.stderr);
+
+    assert!(
+        !output.status.success(),
+        "Atomic archive unexpectedly succeeded. stdout:\n{}\nstderr:\n{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    for p in &file_paths {
+        assert!(p.exists(), "{} was not left in place", p.display());
+    }
+    let names = read_manifest_names(&cfg_dir.join("archive.json"));
+    assert!(
+        names.is_empty(),
+        "Manifest was modified by a failed atomic archive. names={:?}",
+        names
+    );
+
+    // The first move succeeds and the second fails: the first file is moved back.
+    let good_archive = archive_root.join("good");
+    let good_str = good_archive.to_string_lossy().replace('\\', "\\\\");
+    fs::write(
+        cfg_dir.join("config.toml"),
+        format!(
+            "ignored_bins = []\ndefault_threshold_days = 90\narchive_path = \"{}\"\nwindows_use_access_time = false\n",
+            good_str
+        ),
+    )
+    .expect("Failed to write config.toml");
+    let plan_path = config_root.join("plan.json");
+    let output = run_cli(
+        &[
+            "archive",
+            "-p",
+            test_dir.to_str().unwrap(),
+            "--days",
+            "30",
+            "--plan",
+            plan_path.to_str().unwrap(),
+        ],
+        &config_root,
+    );
+    assert!(output.status.success());
+    let mut plan: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&plan_path).unwrap()).unwrap();
+    let items = plan["items"].as_array_mut().unwrap();
+    assert_eq!(items.len(), 2);
+    let first_destination = PathBuf::from(items[0]["destination"].as_str().unwrap());
+    items[1]["destination"] =
+        serde_json::Value::from(archive_dir.join("old_tool.exe").to_str().unwrap());
+    fs::write(&plan_path, serde_json::to_string(&plan).unwrap()).unwrap();
+
+    let manifest_path = cfg_dir.join("archive.json");
+    let manifest_before = fs::read(&manifest_path).ok();
+    let output = run_cli(
+        &[
+            "archive",
+            "--apply",
+            plan_path.to_str().unwrap(),
+            "--atomic",
+            "--yes",
+        ],
+        &config_root,
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!output.status.success(), "{}", stdout);
+    assert!(
+        stdout.contains("Moved"),
+        "first item was not moved: {}",
+        stdout
+    );
+    for p in &file_paths {
+        assert!(p.exists(), "{} was not moved back", p.display());
+    }
+    assert!(!first_destination.exists());
+    assert_eq!(fs::read(&manifest_path).ok(), manifest_before);
+
+    cleanup_dir(&test_dir);
+    cleanup_dir(&archive_root);
+    cleanup_dir(&config_root);
+}
+
+/// This test verifies an item that `archive --atomic` can't roll back is recorded in the
+/// manifest, and the error names it.
+#[test]
+fn test_atomic_rollback_failure_is_recorded() {
+    let test_dir = unique_dir("test_integration_dir_stranded");
+    let config_root = unique_dir("test_integration_config_stranded");
+    let archive_dir = unique_dir("test_integration_archive_stranded");
+    fs::create_dir_all(&test_dir).expect("Failed to create test dir");
+    fs::create_dir_all(&archive_dir).expect("Failed to create archive dir");
+    let env = [("BIN_EXPIRE_ARCHIVE_PATH", archive_dir.to_str().unwrap())];
+
+    let old = FileTime::from_system_time(SystemTime::now() - Duration::from_secs(86400 * 100));
+    for name in ["a_tool", "c_tool", "e_tool"] {
+        let p = test_dir.join(name);
+        fs::write(&p, "content").expect("Failed to write test file");
+        set_file_times(&p, old, old).expect("Failed to backdate file");
+    }
+
+    let plan_path = config_root.join("plan.json");
+    let output = run_cli_with_env(
+        &[
+            "archive",
+            "-p",
+            test_dir.to_str().unwrap(),
+            "--days",
+            "30",
+            "--plan",
+            plan_path.to_str().unwrap(),
+        ],
+        &config_root,
+        &env,
+    );
+    assert!(output.status.success());
+
+    // c_tool moves to a directory where a_tool used to be, so a_tool can't go back; e_tool
+    // fails because its destination's parent is a file.
+    let blocker = archive_dir.join("blocker");
+    fs::write(&blocker, "blocker").expect("Failed to write blocker");
+    let mut plan: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&plan_path).unwrap()).unwrap();
+    let items = plan["items"].as_array_mut().unwrap();
+    items.sort_by_key(|i| i["name"].as_str().unwrap().to_string());
+    items[1]["destination"] =
+        serde_json::Value::from(test_dir.join("a_tool/sub/c_tool").to_str().unwrap());
+    items[2]["destination"] = serde_json::Value::from(blocker.join("e_tool").to_str().unwrap());
+    fs::write(&plan_path, serde_json::to_string(&plan).unwrap()).unwrap();
+
+    let output = run_cli_with_env(
+        &[
+            "archive",
+            "--apply",
+            plan_path.to_str().unwrap(),
+            "--atomic",
+            "--yes",
+        ],
+        &config_root,
+        &env,
+    );
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!output.status.success());
+    assert!(
+        stderr.contains("1 item(s) could not be rolled back") && stderr.contains(": a_tool ("),
+        "{}",
+        stderr
+    );
+    assert!(archive_dir.join("a_tool").is_file());
+    assert!(test_dir.join("c_tool").is_file());
+    assert!(test_dir.join("e_tool").is_file());
+    let names = read_manifest_names(&config_root.join("bin-expire").join("archive.json"));
+    assert_eq!(names, ["a_tool"]);
+
+    cleanup_dir(&test_dir);
+    cleanup_dir(&archive_dir);
+    cleanup_dir(&config_root);
+}
+
+/// This test verifies `archive --plan` moves nothing, `--apply` refuses a plan whose files
+/// changed, and applies an unchanged plan.
+#[test]
+fn test_archive_plan_and_apply() {
+    let test_dir = unique_dir("test_integration_dir_plan");
+    let config_root = unique_dir("test_integration_config_plan");
+    let archive_dir = unique_dir("test_integration_archive_plan");
+
+    fs::create_dir_all(&test_dir).expect("Failed to create test dir");
+    fs::create_dir_all(&archive_dir).expect("Failed to create archive dir");
+
+    let cfg_dir = config_root.join("bin-expire");
+    fs::create_dir_all(&cfg_dir).expect("Failed to create config dir");
+
+    let archive_str = archive_dir.to_string_lossy().replace('\\', "\\\\");
+    let config_toml = format!(
+        "ignored_bins = []\ndefault_threshold_days = 90\narchive_path = \"{}\"\nwindows_use_access_time = false\n",
+        archive_str
+    );
+    fs::write(cfg_dir.join("config.toml"), config_toml).expect("Failed to write config.toml");
+
+    let file_name = "old_tool.exe";
+    let file_path = test_dir.join(file_name);
+    fs::write(&file_path, "content").expect("Failed to write test file");
+    let old_time = SystemTime::now() - Duration::from_secs(86400 * 100);
+    let ft = FileTime::from_system_time(old_time);
+    set_file_times(&file_path, ft, ft).expect("Failed to backdate file");
+
+    // An existing archive entry forces a suffixed destination, which the plan must show.
+    fs::write(archive_dir.join(file_name), "older archive").expect("Failed to seed archive");
+
+    let artifacts_dir = test_dir.join("_test_artifacts");
+    let plan_path = config_root.join("plan.json");
+    let plan_str = plan_path.to_str().unwrap();
+
+    let output = run_cli(
+        &[
+            "archive",
+            "-p",
+            test_dir.to_str().unwrap(),
+            "--days",
+            "30",
+            "--plan",
+            plan_str,
+        ],
+        &config_root,
+    );
+    write_artifact(&artifacts_dir, "plan.stdout.txt", &output.stdout);
+    write_artifact(&artifacts_dir, "plan.stderr.txt", &output.stderr);
+    assert!(
+        output.status.success(),
+        "archive --plan failed: {}\nstderr:\n{}",
+        output.status,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(file_path.exists(), "--plan must not move files");
+    let plan_raw = fs::read_to_string(&plan_path).expect("Plan file was not written");
+    assert!(
+        plan_raw.contains("old_tool.exe.1"),
+        "Plan did not record the suffixed destination:\n{}",
+        plan_raw
+    );
+
+    // Changing the file after planning must make --apply refuse.
+    fs::write(&file_path, "changed content").expect("Failed to modify test file");
+    let output = run_cli(&["archive", "--apply", plan_str], &config_root);
+    write_artifact(&artifacts_dir, "apply_changed.stdout.txt", &output.stdout);
+    write_artifact(&artifacts_dir, "apply_changed.stderr.txt", &output.stderr);
+    assert!(
+        !output.status.success(),
+        "--apply succeeded even though the file changed"
+    );
+    assert!(file_path.exists(), "File moved despite failed verification");
+
+    // Re-plan and apply the unchanged plan.
+    set_file_times(&file_path, ft, ft).expect("Failed to backdate file");
+    let output = run_cli(
+        &[
+            "archive",
+            "-p",
+            test_dir.to_str().unwrap(),
+            "--days",
+            "30",
+            "--plan",
+            plan_str,
+        ],
+        &config_root,
+    );
+    assert!(output.status.success(), "second archive --plan failed");
+    let output = run_cli(&["archive", "--apply", plan_str, "--yes"], &config_root);
+    write_artifact(&artifacts_dir, "apply.stdout.txt", &output.stdout);
+    write_artifact(&artifacts_dir, "apply.stderr.txt", &output.stderr);
+    assert!(
+        output.status.success(),
+        "archive --apply failed: {}\nstderr:\n{}",
+        output.status,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(!file_path.exists(), "File still in place after --apply");
+    assert!(
+        archive_dir.join("old_tool.exe.1").exists(),
+        "File was not moved to the planned destination"
+    );
+
+    cleanup_dir(&test_dir);
+    cleanup_dir(&archive_dir);
+    cleanup_dir(&config_root);
+}
+
+/// This test verifies archive asks before moving, and that `--interactive` can deselect binaries.
+#[test]
+fn test_archive_confirmation_and_interactive_selection() {
+    let test_dir = unique_dir("test_integration_dir_confirm");
+    let config_root = unique_dir("test_integration_config_confirm");
+    let archive_dir = unique_dir("test_integration_archive_confirm");
+
+    fs::create_dir_all(&test_dir).expect("Failed to create test dir");
+    fs::create_dir_all(&archive_dir).expect("Failed to create archive dir");
+
+    let cfg_dir = config_root.join("bin-expire");
+    fs::create_dir_all(&cfg_dir).expect("Failed to create config dir");
+
+    let archive_str = archive_dir.to_string_lossy().replace('\\', "\\\\");
+    let config_toml = format!(
+        "ignored_bins = []\ndefault_threshold_days = 90\narchive_path = \"{}\"\nwindows_use_access_time = false\n",
+        archive_str
+    );
+    fs::write(cfg_dir.join("config.toml"), config_toml).expect("Failed to write config.toml");
+
+    let old_time = SystemTime::now() - Duration::from_secs(86400 * 100);
+    let ft = FileTime::from_system_time(old_time);
+    let file_a = test_dir.join("old_tool_a.exe");
+    let file_b = test_dir.join("old_tool_b.exe");
+    for p in [&file_a, &file_b] {
+        fs::write(p, "content").expect("Failed to write test file");
+        set_file_times(p, ft, ft).expect("Failed to backdate file");
+    }
+
+    let dir_str = test_dir.to_str().unwrap();
+    let artifacts_dir = test_dir.join("_test_artifacts");
+
+    // Answering "n" keeps everything in place.
+    let output = run_cli_with_stdin(
+        &["archive", "-p", dir_str, "--days", "30"],
+        &config_root,
+        "n\n",
+    );
+    write_artifact(&artifacts_dir, "declined.stdout.txt", &output.stdout);
+    write_artifact(&artifacts_dir, "declined.stderr.txt", &output.stderr);
+    assert!(
+        file_a.exists() && file_b.exists(),
+        "Files moved after declining"
+    );
+
+    // No answer at all (closed stdin) must fail rather than silently archive.
+    let output = run_cli(&["archive", "-p", dir_str, "--days", "30"], &config_root);
+    assert!(
+        !output.status.success(),
+        "archive without --yes succeeded with no confirmation"
+    );
+    assert!(
+        file_a.exists() && file_b.exists(),
+        "Files moved without confirmation"
+    );
+
+    // Deselect the second entry (entries are listed in scan order) and accept.
+    let output = run_cli_with_stdin(
+        &["archive", "-p", dir_str, "--days", "30", "--interactive"],
+        &config_root,
+        "2\n\n",
+    );
+    write_artifact(&artifacts_dir, "interactive.stdout.txt", &output.stdout);
+    write_artifact(&artifacts_dir, "interactive.stderr.txt", &output.stderr);
+    assert!(
+        output.status.success(),
+        "archive --interactive failed: {}\nstderr:\n{}",
+        output.status,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let remaining: Vec<bool> = [&file_a, &file_b].iter().map(|p| p.exists()).collect();
+    assert_eq!(
+        remaining.iter().filter(|exists| **exists).count(),
+        1,
+        "Expected exactly one binary to be left in place. stdout:\n{}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+
+    cleanup_dir(&test_dir);
+    cleanup_dir(&archive_dir);
+    cleanup_dir(&config_root);
+}
+
+/// This test verifies `archive <glob>` archives matching binaries regardless of age,
+/// while still honoring `ignored_bins`.
+#[test]
+fn test_archive_named_binaries() {
+    let test_dir = unique_dir("test_integration_dir_named");
+    let config_root = unique_dir("test_integration_config_named");
+    let archive_dir = unique_dir("test_integration_archive_named");
+
+    fs::create_dir_all(&test_dir).expect("Failed to create test dir");
+    fs::create_dir_all(&archive_dir).expect("Failed to create archive dir");
+
+    let cfg_dir = config_root.join("bin-expire");
+    fs::create_dir_all(&cfg_dir).expect("Failed to create config dir");
+
+    let archive_str = archive_dir.to_string_lossy().replace('\\', "\\\\");
+    let config_toml = format!(
+        "ignored_bins = [\"fresh_keep\"]\ndefault_threshold_days = 90\narchive_path = \"{}\"\nwindows_use_access_time = false\n",
+        archive_str
+    );
+    fs::write(cfg_dir.join("config.toml"), config_toml).expect("Failed to write config.toml");
+
+    // All files are brand new, so an age-based archive would not touch them.
+    for name in ["fresh_tool", "fresh_keep", "other_tool"] {
+        fs::write(test_dir.join(name), "content").expect("Failed to write test file");
+    }
+
+    let output = run_cli(
+        &[
+            "archive",
+            "fresh_*",
+            "-p",
+            test_dir.to_str().unwrap(),
+            "--yes",
+        ],
+        &config_root,
+    );
+    let artifacts_dir = test_dir.join("_test_artifacts");
+    write_artifact(&artifacts_dir, "archive_named.stdout.txt", &output.stdout);
+    write_artifact(&artifacts_dir, "archive_named.stderr.txt", &output.stderr);
+    assert!(
+        output.status.success(),
+        "archive <glob> failed: {}\nstderr:\n{}",
+        output.status,
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    assert!(
+        archive_dir.join("fresh_tool").exists(),
+        "fresh_tool was not archived"
+    );
+    assert!(
+        test_dir.join("fresh_keep").exists(),
+        "ignored binary was archived"
+    );
+    assert!(
+        test_dir.join("other_tool").exists(),
+        "non-matching binary was archived"
+    );
+
+    // A name that matches nothing is an error.
+    let output = run_cli(
+        &[
+            "archive",
+            "does_not_exist",
+            "-p",
+            test_dir.to_str().unwrap(),
+            "--yes",
+        ],
+        &config_root,
+    );
+    assert!(
+        !output.status.success(),
+        "archive with an unknown name unexpectedly succeeded"
+    );
+
+    cleanup_dir(&test_dir);
+    cleanup_dir(&archive_dir);
+    cleanup_dir(&config_root);
+}
+
+/// This test verifies `--output json` writes only a parseable, versioned document to stdout.
+#[test]
+fn test_scan_json_output() {
+    let test_dir = unique_dir("test_integration_dir_json");
+    let config_root = unique_dir("test_integration_config_json");
+    fs::create_dir_all(&test_dir).expect("Failed to create test dir");
+
+    let cfg_dir = config_root.join("bin-expire");
+    fs::create_dir_all(&cfg_dir).expect("Failed to create config dir");
+    fs::write(
+        cfg_dir.join("config.toml"),
+        "ignored_bins = []\ndefault_threshold_days = 90\nwindows_use_access_time = false\n",
+    )
+    .expect("Failed to write config.toml");
+
+    let old_time = SystemTime::now() - Duration::from_secs(86400 * 100);
+    let ft = FileTime::from_system_time(old_time);
+    let stale_path = test_dir.join("old_tool.exe");
+    fs::write(&stale_path, "content").expect("Failed to write test file");
+    set_file_times(&stale_path, ft, ft).expect("Failed to backdate file");
+    fs::write(test_dir.join("new_tool.exe"), "content").expect("Failed to write test file");
+
+    let output = run_cli(
+        &[
+            "scan",
+            "-p",
+            test_dir.to_str().unwrap(),
+            "--days",
+            "30",
+            "--output",
+            "json",
+        ],
+        &config_root,
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        output.status.success(),
+        "scan --output json failed: {}\nstderr:\n{}",
+        output.status,
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let doc: serde_json::Value = serde_json::from_str(&stdout)
+        .unwrap_or_else(|e| panic!("stdout is not valid JSON ({}):\n{}", e, stdout));
+    assert_eq!(doc["schema_version"], 1);
+    assert_eq!(doc["command"], "scan");
+    let records = doc["records"].as_array().expect("records must be an array");
+    let status_of = |name: &str| {
+        records
+            .iter()
+            .find(|r| r["name"] == name)
+            .map(|r| r["status"].as_str().unwrap_or_default().to_string())
+    };
+    assert_eq!(status_of("old_tool.exe").as_deref(), Some("stale"));
+    assert_eq!(status_of("new_tool.exe").as_deref(), Some("ok"));
+
+    cleanup_dir(&test_dir);
+    cleanup_dir(&config_root);
+}
+
+/// This test verifies the documented exit codes of `check` and config errors.
+#[test]
+fn test_check_exit_codes() {
+    let test_dir = unique_dir("test_integration_dir_check");
+    let config_root = unique_dir("test_integration_config_check");
+    fs::create_dir_all(&test_dir).expect("Failed to create test dir");
+
+    let cfg_dir = config_root.join("bin-expire");
+    fs::create_dir_all(&cfg_dir).expect("Failed to create config dir");
+    fs::write(
+        cfg_dir.join("config.toml"),
+        "ignored_bins = []\ndefault_threshold_days = 90\nwindows_use_access_time = false\n",
+    )
+    .expect("Failed to write config.toml");
+
+    let dir_str = test_dir.to_str().unwrap();
+    fs::write(test_dir.join("new_tool.exe"), "content").expect("Failed to write test file");
+
+    let output = run_cli(&["check", "-p", dir_str, "--days", "30"], &config_root);
+    assert_eq!(
+        output.status.code(),
+        Some(0),
+        "check with nothing stale should exit 0. stderr:\n{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let old_time = SystemTime::now() - Duration::from_secs(86400 * 100);
+    let ft = FileTime::from_system_time(old_time);
+    let stale_path = test_dir.join("old_tool.exe");
+    fs::write(&stale_path, "content").expect("Failed to write test file");
+    set_file_times(&stale_path, ft, ft).expect("Failed to backdate file");
+
+    let output = run_cli(&["check", "-p", dir_str, "--days", "30"], &config_root);
+    assert_eq!(
+        output.status.code(),
+        Some(3),
+        "check should exit 3 on stale"
+    );
+
+    // Plain scan still succeeds unless --fail-if-stale is given.
+    let output = run_cli(&["scan", "-p", dir_str, "--days", "30"], &config_root);
+    assert_eq!(output.status.code(), Some(0));
+    let output = run_cli(
+        &["scan", "-p", dir_str, "--days", "30", "--fail-if-stale"],
+        &config_root,
+    );
+    assert_eq!(output.status.code(), Some(3));
+
+    let missing = test_dir.join("does_not_exist");
+    let output = run_cli(&["scan", "-p", missing.to_str().unwrap()], &config_root);
+    assert_eq!(
+        output.status.code(),
+        Some(1),
+        "scanning no valid directories should exit 1"
+    );
+
+    fs::write(
+        cfg_dir.join("config.toml"),
+        "default_threshold_days = \"oops\n",
+    )
+    .expect("Failed to write broken config.toml");
+    let output = run_cli(&["scan", "-p", dir_str], &config_root);
+    assert_eq!(
+        output.status.code(),
+        Some(5),
+        "a broken config should exit 5"
+    );
+
+    cleanup_dir(&test_dir);
+    cleanup_dir(&config_root);
+}
+
+/// This test verifies `--sort size` orders rows largest first and `--columns` limits the table.
+#[test]
+fn test_scan_sort_and_columns() {
+    let test_dir = unique_dir("test_integration_dir_sort");
+    let config_root = unique_dir("test_integration_config_sort");
+    fs::create_dir_all(&test_dir).expect("Failed to create test dir");
+
+    let cfg_dir = config_root.join("bin-expire");
+    fs::create_dir_all(&cfg_dir).expect("Failed to create config dir");
+    fs::write(
+        cfg_dir.join("config.toml"),
+        "ignored_bins = []\ndefault_threshold_days = 90\nwindows_use_access_time = false\n",
+    )
+    .expect("Failed to write config.toml");
+
+    fs::write(test_dir.join("a_small"), vec![0u8; 10]).expect("Failed to write test file");
+    fs::write(test_dir.join("z_large"), vec![0u8; 4096]).expect("Failed to write test file");
+
+    let output = run_cli(
+        &[
+            "scan",
+            "-p",
+            test_dir.to_str().unwrap(),
+            "--verbose",
+            "--sort",
+            "size",
+            "--columns",
+            "name,size",
+        ],
+        &config_root,
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "scan failed:\n{}", stdout);
+
+    let large = stdout.find("z_large").expect("z_large row missing");
+    let small = stdout.find("a_small").expect("a_small row missing");
+    assert!(large < small, "Expected largest first:\n{}", stdout);
+    assert!(
+        !stdout.contains("MODIFIED"),
+        "--columns should drop unselected columns:\n{}",
+        stdout
+    );
+
+    cleanup_dir(&test_dir);
+    cleanup_dir(&config_root);
+}
+
+/// This test verifies duration thresholds: `--older-than`, `default_threshold` and validation.
+#[test]
+fn test_duration_thresholds() {
+    let test_dir = unique_dir("test_integration_dir_threshold");
+    let config_root = unique_dir("test_integration_config_threshold");
+    fs::create_dir_all(&test_dir).expect("Failed to create test dir");
+
+    let cfg_dir = config_root.join("bin-expire");
+    fs::create_dir_all(&cfg_dir).expect("Failed to create config dir");
+    fs::write(
+        cfg_dir.join("config.toml"),
+        "ignored_bins = []\ndefault_threshold_days = 90\nwindows_use_access_time = false\n",
+    )
+    .expect("Failed to write config.toml");
+
+    // 36 hours old: stale for 1d, not for 2d (whole-day rounding would call it 1 day).
+    let old_time = SystemTime::now() - Duration::from_secs(3600 * 36);
+    let ft = FileTime::from_system_time(old_time);
+    let path = test_dir.join("tool.exe");
+    fs::write(&path, "content").expect("Failed to write test file");
+    set_file_times(&path, ft, ft).expect("Failed to backdate file");
+    let dir_str = test_dir.to_str().unwrap();
+
+    let output = run_cli(
+        &["check", "-p", dir_str, "--older-than", "1d"],
+        &config_root,
+    );
+    assert_eq!(
+        output.status.code(),
+        Some(3),
+        "36h old should be stale for 1d"
+    );
+    let output = run_cli(
+        &["check", "-p", dir_str, "--older-than", "2d"],
+        &config_root,
+    );
+    assert_eq!(output.status.code(), Some(0), "36h old is not stale for 2d");
+    let output = run_cli(
+        &["check", "-p", dir_str, "--older-than", "1w"],
+        &config_root,
+    );
+    assert_eq!(output.status.code(), Some(0));
+
+    let output = run_cli(
+        &["check", "-p", dir_str, "--older-than", "soon"],
+        &config_root,
+    );
+    assert_eq!(
+        output.status.code(),
+        Some(2),
+        "invalid --older-than is a usage error"
+    );
+    let output = run_cli(
+        &["check", "-p", dir_str, "--older-than", "1d", "--days", "1"],
+        &config_root,
+    );
+    assert_eq!(
+        output.status.code(),
+        Some(2),
+        "--days and --older-than conflict"
+    );
+
+    fs::write(
+        cfg_dir.join("config.toml"),
+        "ignored_bins = []\ndefault_threshold = \"1d\"\ndefault_threshold_days = 90\nwindows_use_access_time = false\n",
+    )
+    .expect("Failed to write config.toml");
+    let output = run_cli(&["check", "-p", dir_str], &config_root);
+    assert_eq!(
+        output.status.code(),
+        Some(3),
+        "default_threshold should win over default_threshold_days"
+    );
+
+    fs::write(
+        cfg_dir.join("config.toml"),
+        "ignored_bins = []\ndefault_threshold = \"-5d\"\nwindows_use_access_time = false\n",
+    )
+    .expect("Failed to write config.toml");
+    let output = run_cli(&["check", "-p", dir_str], &config_root);
+    assert_eq!(
+        output.status.code(),
+        Some(5),
+        "an invalid default_threshold should be a config error"
+    );
+
+    cleanup_dir(&test_dir);
+    cleanup_dir(&config_root);
+}
+
+/// This test verifies glob/regex/scoped ignore rules, `.bin-expire-ignore`, and the verbose listing.
+#[test]
+fn test_ignore_rules() {
+    let test_dir = unique_dir("test_integration_dir_ignore");
+    let other_dir = unique_dir("test_integration_dir_ignore_other");
+    let config_root = unique_dir("test_integration_config_ignore");
+    fs::create_dir_all(&test_dir).expect("Failed to create test dir");
+    fs::create_dir_all(&other_dir).expect("Failed to create test dir");
+
+    let cfg_dir = config_root.join("bin-expire");
+    fs::create_dir_all(&cfg_dir).expect("Failed to create config dir");
+    let config = format!(
+        "ignored_bins = [\"cargo-*\", \"re:^go(pls|imports)$\", {{ pattern = \"scoped_tool\", dir = {:?} }}]\ndefault_threshold_days = 90\nwindows_use_access_time = false\n",
+        other_dir.to_str().unwrap()
+    );
+    fs::write(cfg_dir.join("config.toml"), config).expect("Failed to write config.toml");
+
+    for name in [
+        "cargo-watch",
+        "gopls",
+        "gofmt",
+        "scoped_tool",
+        "keep_me",
+        "local_junk",
+    ] {
+        fs::write(test_dir.join(name), "content").expect("Failed to write test file");
+    }
+    fs::write(
+        test_dir.join(".bin-expire-ignore"),
+        "# local rules\nlocal_*\n!cargo-watch\n",
+    )
+    .expect("Failed to write ignore file");
+
+    let output = run_cli(
+        &[
+            "--output",
+            "json",
+            "scan",
+            "-p",
+            test_dir.to_str().unwrap(),
+            "--verbose",
+        ],
+        &config_root,
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        output.status.success(),
+        "scan failed:\n{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let doc: serde_json::Value = serde_json::from_str(&stdout).expect("stdout is not valid JSON");
+    let records = doc["records"].as_array().unwrap();
+    let find = |name: &str| records.iter().find(|r| r["name"] == name).cloned();
+
+    // Re-included by the ignore file's "!cargo-watch", which comes after the config glob.
+    assert_eq!(find("cargo-watch").unwrap()["status"], "ok");
+    assert_eq!(find("gopls").unwrap()["status"], "ignored");
+    assert_eq!(find("gofmt").unwrap()["status"], "ok");
+    // Scoped to another directory, so it does not apply here.
+    assert_eq!(find("scoped_tool").unwrap()["status"], "ok");
+    let junk = find("local_junk").unwrap();
+    assert_eq!(junk["status"], "ignored");
+    assert!(
+        junk["ignored_by"]
+            .as_str()
+            .unwrap()
+            .contains(".bin-expire-ignore:2"),
+        "Expected the ignore file line as the rule: {}",
+        junk
+    );
+    assert!(
+        find(".bin-expire-ignore").is_none(),
+        "the ignore file itself is not a binary"
+    );
+
+    // Human-readable verbose output lists ignored binaries with their rule.
+    let output = run_cli(
+        &["scan", "-p", test_dir.to_str().unwrap(), "--verbose"],
+        &config_root,
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("Ignored (2)") && stdout.contains("re:^go(pls|imports)$"),
+        "Expected an ignored listing:\n{}",
+        stdout
+    );
+
+    cleanup_dir(&test_dir);
+    cleanup_dir(&other_dir);
+    cleanup_dir(&config_root);
+}
+
+/// This test verifies `pin` / `unpin`: PINNED status with reason, archive skipping, and expiry.
+#[test]
+fn test_pin_and_unpin() {
+    let test_dir = unique_dir("test_integration_dir_pin");
+    let config_root = unique_dir("test_integration_config_pin");
+    fs::create_dir_all(&test_dir).expect("Failed to create test dir");
+
+    let cfg_dir = config_root.join("bin-expire");
+    fs::create_dir_all(&cfg_dir).expect("Failed to create config dir");
+    let archive_dir = config_root.join("archive");
+    fs::write(
+        cfg_dir.join("config.toml"),
+        format!(
+            "ignored_bins = []\ndefault_threshold_days = 30\narchive_path = {:?}\nwindows_use_access_time = false\n",
+            archive_dir.to_str().unwrap()
+        ),
+    )
+    .expect("Failed to write config.toml");
+
+    let old_time = SystemTime::now() - Duration::from_secs(86400 * 100);
+    let ft = FileTime::from_system_time(old_time);
+    let path = test_dir.join("runbook_tool");
+    fs::write(&path, "content").expect("Failed to write test file");
+    set_file_times(&path, ft, ft).expect("Failed to backdate file");
+    let dir_str = test_dir.to_str().unwrap();
+
+    let output = run_cli(
+        &[
+            "pin",
+            "runbook_tool",
+            "--reason",
+            "on-call runbook",
+            "--until",
+            "2999-01-01",
+        ],
+        &config_root,
+    );
+    assert!(
+        output.status.success(),
+        "pin failed:\n{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let output = run_cli(&["check", "-p", dir_str], &config_root);
+    assert_eq!(
+        output.status.code(),
+        Some(0),
+        "a pinned binary is not stale"
+    );
+
+    let output = run_cli(&["--output", "json", "scan", "-p", dir_str], &config_root);
+    let doc: serde_json::Value =
+        serde_json::from_slice(&output.stdout).expect("stdout is not valid JSON");
+    let record = &doc["records"][0];
+    assert_eq!(record["status"], "pinned");
+    assert_eq!(record["pin_reason"], "on-call runbook");
+    assert_eq!(record["pinned_until"], "2999-01-01");
+
+    let output = run_cli(&["archive", "-p", dir_str, "--yes"], &config_root);
+    assert!(output.status.success());
+    assert!(path.exists(), "a pinned binary must not be archived");
+
+    let output = run_cli(&["unpin", "runbook_tool"], &config_root);
+    assert!(output.status.success());
+    let output = run_cli(&["check", "-p", dir_str], &config_root);
+    assert_eq!(
+        output.status.code(),
+        Some(3),
+        "unpinned binary is stale again"
+    );
+    let output = run_cli(&["unpin", "runbook_tool"], &config_root);
+    assert_eq!(output.status.code(), Some(1), "unpinning twice is an error");
+
+    // An expired pin no longer protects the binary.
+    fs::write(
+        cfg_dir.join("pins.json"),
+        r#"{"pins":[{"name":"runbook_tool","reason":"old","until":"2001-01-01","pinned_at":"2000-01-01T00:00:00Z"}]}"#,
+    )
+    .expect("Failed to write pins.json");
+    let output = run_cli(&["scan", "-p", dir_str], &config_root);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("expired on 2001-01-01"),
+        "Expected an expiry note:\n{}",
+        stdout
+    );
+    let output = run_cli(&["check", "-p", dir_str], &config_root);
+    assert_eq!(output.status.code(), Some(3));
+
+    cleanup_dir(&test_dir);
+    cleanup_dir(&config_root);
+}
+
+/// This test verifies config migration keeps comments and `config set/get/show/validate`, and
+/// that `config set` can fix a broken file.
+#[test]
+fn test_config_subcommand_and_migration() {
+    let config_root = unique_dir("test_integration_config_cmd");
+    let cfg_dir = config_root.join("bin-expire");
+    fs::create_dir_all(&cfg_dir).expect("Failed to create config dir");
+    let cfg_path = cfg_dir.join("config.toml");
+
+    // A pre-versioned config with comments and a commented-out key.
+    fs::write(
+        &cfg_path,
+        "# my settings\nignored_bins = [\"cargo\"] # core tools\n# windows_use_access_time = false\ndefault_threshold_days = 60 # two months\n",
+    )
+    .expect("Failed to write config.toml");
+
+    let output = run_cli(&["config", "validate"], &config_root);
+    assert!(output.status.success(), "validate failed");
+    assert!(
+        !fs::read_to_string(&cfg_path)
+            .unwrap()
+            .contains("config_version"),
+        "validate must not modify the file"
+    );
+
+    let output = run_cli(&["config", "get", "default_threshold_days"], &config_root);
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "60");
+
+    let migrated = fs::read_to_string(&cfg_path).unwrap();
+    assert!(
+        migrated.contains("# my settings"),
+        "comments must survive:\n{}",
+        migrated
+    );
+    assert!(migrated.contains("# windows_use_access_time = false"));
+    assert!(
+        migrated.contains("\nwindows_use_access_time = true"),
+        "a commented-out key counts as missing:\n{}",
+        migrated
+    );
+    assert!(migrated.contains("config_version = 1"));
+
+    let output = run_cli(
+        &["config", "set", "default_threshold_days", "45"],
+        &config_root,
+    );
+    assert!(output.status.success());
+    let updated = fs::read_to_string(&cfg_path).unwrap();
+    assert!(
+        updated.contains("default_threshold_days = 45 # two months"),
+        "set should keep the trailing comment:\n{}",
+        updated
+    );
+
+    let output = run_cli(
+        &["config", "set", "default_threshold", "soon"],
+        &config_root,
+    );
+    assert_eq!(
+        output.status.code(),
+        Some(5),
+        "invalid values are config errors"
+    );
+    assert_eq!(
+        fs::read_to_string(&cfg_path).unwrap(),
+        updated,
+        "an invalid set must not change the file"
+    );
+
+    let output = run_cli(&["--output", "json", "config", "show"], &config_root);
+    let doc: serde_json::Value =
+        serde_json::from_slice(&output.stdout).expect("stdout is not valid JSON");
+    let record = |key: &str| {
+        doc["records"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|r| r["key"] == key)
+            .cloned()
+            .unwrap()
+    };
+    assert_eq!(record("default_threshold_days")["value"], "45");
+    assert!(record("default_threshold_days")["origin"]
+        .as_str()
+        .unwrap()
+        .ends_with("config.toml"));
+    assert_eq!(record("default_threshold")["origin"], "default");
+
+    fs::write(&cfg_path, "default_threshold_days = \"oops\n").expect("Failed to write");
+    let output = run_cli(&["config", "validate"], &config_root);
+    assert_eq!(output.status.code(), Some(5));
+    let output = run_cli(&["config", "path"], &config_root);
+    assert!(
+        output.status.success(),
+        "config path works with a broken config"
+    );
+
+    // `config set` can repair the value that breaks the file.
+    fs::write(&cfg_path, "config_version = 1\nmax_depth = 0\n").expect("Failed to write");
+    let output = run_cli(&["config", "set", "max_depth", "1"], &config_root);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(
+        fs::read_to_string(&cfg_path).unwrap(),
+        "config_version = 1\nmax_depth = 1\n"
+    );
+    assert!(!cfg_dir.join("config.toml.tmp").exists());
+    let output = run_cli(&["config", "validate"], &config_root);
+    assert!(output.status.success());
+
+    cleanup_dir(&config_root);
+}
+
+/// This test verifies layered config: system < user < environment < --config, with merged
+/// ignore lists and a minimum threshold that lower-precedence layers can't undercut.
+#[test]
+fn test_layered_config() {
+    let test_dir = unique_dir("test_integration_dir_layers");
+    let config_root = unique_dir("test_integration_config_layers");
+    fs::create_dir_all(&test_dir).expect("Failed to create test dir");
+    let cfg_dir = config_root.join("bin-expire");
+    fs::create_dir_all(&cfg_dir).expect("Failed to create config dir");
+
+    let system = config_root.join("system.toml");
+    fs::write(
+        &system,
+        "ignored_bins = [\"sys_tool\"]\nmin_threshold = \"30d\"\ndefault_threshold = \"180d\"\narchive_path = \"/srv/system-archive\"\n",
+    )
+    .expect("Failed to write system config");
+    fs::write(
+        cfg_dir.join("config.toml"),
+        "config_version = 1\nignored_bins = [\"user_tool\"]\ndefault_threshold_days = 60\nmin_threshold = \"1d\"\n",
+    )
+    .expect("Failed to write config.toml");
+    let flag = config_root.join("extra.toml");
+    fs::write(&flag, "archive_path = \"/tmp/flag-archive\"\n")
+        .expect("Failed to write --config file");
+
+    let env = [
+        ("BIN_EXPIRE_SYSTEM_CONFIG", system.to_str().unwrap()),
+        ("BIN_EXPIRE_THRESHOLD", "45d"),
+    ];
+    let output = run_cli_with_env(
+        &[
+            "--output",
+            "json",
+            "--config",
+            flag.to_str().unwrap(),
+            "config",
+            "show",
+        ],
+        &config_root,
+        &env,
+    );
+    assert!(
+        output.status.success(),
+        "config show failed:\n{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let doc: serde_json::Value =
+        serde_json::from_slice(&output.stdout).expect("stdout is not valid JSON");
+    let record = |key: &str| {
+        doc["records"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|r| r["key"] == key)
+            .cloned()
+            .unwrap()
+    };
+    assert_eq!(
+        record("ignored_bins")["value"],
+        "[\"sys_tool\", \"user_tool\"]"
+    );
+    let origin = record("ignored_bins")["origin"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    assert!(
+        origin.starts_with("system: ") && origin.contains(" + user: "),
+        "{}",
+        origin
+    );
+    assert_eq!(record("default_threshold")["value"], "\"45d\"");
+    assert_eq!(
+        record("default_threshold")["origin"],
+        "env: BIN_EXPIRE_THRESHOLD"
+    );
+    assert_eq!(record("default_threshold_days")["origin"], "default");
+    assert_eq!(
+        record("min_threshold")["value"],
+        "\"30d\"",
+        "the strictest minimum wins"
+    );
+    assert_eq!(record("archive_path")["value"], "\"/tmp/flag-archive\"");
+    assert!(record("archive_path")["origin"]
+        .as_str()
+        .unwrap()
+        .starts_with("--config: "));
+
+    // 10 days old: stale for --older-than 1d, but min_threshold raises that to 30d.
+    let old_time = SystemTime::now() - Duration::from_secs(86400 * 10);
+    let ft = FileTime::from_system_time(old_time);
+    for name in ["sys_tool", "user_tool", "tool"] {
+        let path = test_dir.join(name);
+        fs::write(&path, "content").expect("Failed to write test file");
+        set_file_times(&path, ft, ft).expect("Failed to backdate file");
+    }
+    let dir_str = test_dir.to_str().unwrap();
+    let output = run_cli_with_env(
+        &["check", "-p", dir_str, "--older-than", "1d"],
+        &config_root,
+        &env,
+    );
+    assert_eq!(output.status.code(), Some(0), "min_threshold should apply");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("below min_threshold"));
+
+    let output = run_cli_with_env(
+        &["--output", "json", "scan", "-p", dir_str, "--verbose"],
+        &config_root,
+        &env,
+    );
+    let doc: serde_json::Value =
+        serde_json::from_slice(&output.stdout).expect("stdout is not valid JSON");
+    let ignored: Vec<&str> = doc["records"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter(|r| r["status"] == "ignored")
+        .map(|r| r["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        ignored,
+        ["sys_tool", "user_tool"],
+        "ignore lists are merged"
+    );
+
+    let output = run_cli_with_env(
+        &["check", "-p", dir_str],
+        &config_root,
+        &[
+            ("BIN_EXPIRE_SYSTEM_CONFIG", system.to_str().unwrap()),
+            ("BIN_EXPIRE_THRESHOLD", "soon"),
+        ],
+    );
+    assert_eq!(
+        output.status.code(),
+        Some(5),
+        "invalid env override is a config error"
+    );
+
+    cleanup_dir(&test_dir);
+    cleanup_dir(&config_root);
+}
+
+/// This test verifies `--profile` and BIN_EXPIRE_PROFILE select a `[profile.<name>]` section,
+/// unknown profiles are config errors, and `config validate` checks the merged result.
+#[test]
+fn test_config_profiles() {
+    let nightly_dir = unique_dir("test_integration_dir_profile_nightly");
+    let cleanup_dir_path = unique_dir("test_integration_dir_profile_cleanup");
+    let config_root = unique_dir("test_integration_config_profiles");
+    fs::create_dir_all(&nightly_dir).expect("Failed to create test dir");
+    fs::create_dir_all(&cleanup_dir_path).expect("Failed to create test dir");
+    let cfg_dir = config_root.join("bin-expire");
+    fs::create_dir_all(&cfg_dir).expect("Failed to create config dir");
+
+    // 10 days old everywhere: stale under the cleanup profile (7d), fresh under nightly (180d).
+    let old_time = SystemTime::now() - Duration::from_secs(86400 * 10);
+    let ft = FileTime::from_system_time(old_time);
+    for path in [
+        nightly_dir.join("night_tool"),
+        cleanup_dir_path.join("old_tool"),
+        cleanup_dir_path.join("keep_me"),
+    ] {
+        fs::write(&path, "content").expect("Failed to write test file");
+        set_file_times(&path, ft, ft).expect("Failed to backdate file");
+    }
+
+    fs::write(
+        cfg_dir.join("config.toml"),
+        format!(
+            "config_version = 1\ndefault_profile = \"nightly\"\ndefault_threshold_days = 30\n\n\
+             [profile.nightly]\nscan_dirs = [{:?}]\ndefault_threshold = \"180d\"\n\n\
+             [profile.cleanup]\nscan_dirs = [{:?}]\ndefault_threshold = \"7d\"\nignored_bins = [\"keep_me\"]\n",
+            nightly_dir.to_str().unwrap(),
+            cleanup_dir_path.to_str().unwrap()
+        ),
+    )
+    .expect("Failed to write config.toml");
+
+    let scanned = |output: &Output| -> Vec<(String, String)> {
+        assert!(
+            output.status.code() == Some(0) || output.status.code() == Some(3),
+            "scan failed:\n{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        let doc: serde_json::Value =
+            serde_json::from_slice(&output.stdout).expect("stdout is not valid JSON");
+        doc["records"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|r| {
+                (
+                    r["name"].as_str().unwrap().to_string(),
+                    r["status"].as_str().unwrap().to_string(),
+                )
+            })
+            .collect()
+    };
+
+    // default_profile: scans the nightly dir with its 180d threshold.
+    let output = run_cli(&["--output", "json", "scan"], &config_root);
+    assert_eq!(
+        scanned(&output),
+        [("night_tool".to_string(), "ok".to_string())]
+    );
+
+    // BIN_EXPIRE_PROFILE beats default_profile; the profile's ignore rules apply.
+    let output = run_cli_with_env(
+        &["--output", "json", "scan"],
+        &config_root,
+        &[("BIN_EXPIRE_PROFILE", "cleanup")],
+    );
+    assert_eq!(
+        scanned(&output),
+        [("old_tool".to_string(), "stale".to_string())]
+    );
+
+    // --profile beats BIN_EXPIRE_PROFILE.
+    let output = run_cli_with_env(
+        &["--output", "json", "--profile", "nightly", "scan"],
+        &config_root,
+        &[("BIN_EXPIRE_PROFILE", "cleanup")],
+    );
+    assert_eq!(
+        scanned(&output),
+        [("night_tool".to_string(), "ok".to_string())]
+    );
+
+    let output = run_cli(
+        &["--profile", "cleanup", "config", "get", "default_threshold"],
+        &config_root,
+    );
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "7d");
+    let output = run_cli(&["--profile", "cleanup", "config", "show"], &config_root);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("Profile: cleanup (from --profile)"),
+        "{}",
+        stdout
+    );
+    assert!(stdout.contains("profile: cleanup"), "{}", stdout);
+
+    let output = run_cli(&["--profile", "weekly", "scan"], &config_root);
+    assert_eq!(output.status.code(), Some(5), "unknown profile");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Unknown profile 'weekly'") && stderr.contains("cleanup, nightly"),
+        "{}",
+        stderr
+    );
+
+    // `config validate` checks the merged config the other commands would load.
+    let output = run_cli(
+        &["--profile", "cleanup", "config", "validate"],
+        &config_root,
+    );
+    assert_eq!(output.status.code(), Some(0));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("Merged configuration with [profile.cleanup] (from --profile) is valid"),
+        "{}",
+        stdout
+    );
+    let output = run_cli(&["--profile", "weekly", "config", "validate"], &config_root);
+    assert_eq!(output.status.code(), Some(5), "unknown --profile");
+    let output = run_cli_with_env(
+        &["config", "validate"],
+        &config_root,
+        &[("BIN_EXPIRE_PROFILE", "weekly")],
+    );
+    assert_eq!(output.status.code(), Some(5), "unknown BIN_EXPIRE_PROFILE");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Unknown profile 'weekly'"), "{}", stderr);
+    // Every file is valid on its own; the environment layer is not.
+    let output = run_cli_with_env(
+        &["config", "validate"],
+        &config_root,
+        &[("BIN_EXPIRE_THRESHOLD", "soon")],
+    );
+    assert_eq!(output.status.code(), Some(5), "invalid merged value");
+
+    // Keys that only make sense at the top level are rejected inside a profile.
+    fs::write(
+        cfg_dir.join("config.toml"),
+        "config_version = 1\n[profile.bad]\ndefault_profile = \"bad\"\n",
+    )
+    .expect("Failed to write config.toml");
+    let output = run_cli(&["config", "validate"], &config_root);
+    assert_eq!(output.status.code(), Some(5));
+
+    cleanup_dir(&nightly_dir);
+    cleanup_dir(&cleanup_dir_path);
+    cleanup_dir(&config_root);
+}
+
+/// This test verifies `archive` leaves a stale binary in place while a process is running it.
+#[cfg(target_os = "linux")]
+#[test]
+fn test_archive_skips_running_binaries() {
+    let test_dir = unique_dir("test_integration_dir_running");
+    let config_root = unique_dir("test_integration_config_running");
+    fs::create_dir_all(&test_dir).expect("Failed to create test dir");
+
+    let sleep = ["/bin/sleep", "/usr/bin/sleep"]
+        .iter()
+        .map(Path::new)
+        .find(|p| p.exists())
+        .expect("sleep not found");
+    let daemon = test_dir.join("old_daemon");
+    fs::copy(sleep, &daemon).expect("Failed to copy sleep");
+    let idle = test_dir.join("idle_tool");
+    fs::write(&idle, "content").expect("Failed to write test file");
+
+    let mut child = Command::new(&daemon)
+        .arg("60")
+        .spawn()
+        .expect("Failed to start the copied binary");
+
+    // Backdate after it has started: exec and the loader refresh atime.
+    std::thread::sleep(Duration::from_millis(500));
+    let old_time = SystemTime::now() - Duration::from_secs(86400 * 200);
+    let ft = FileTime::from_system_time(old_time);
+    for path in [&daemon, &idle] {
+        set_file_times(path, ft, ft).expect("Failed to backdate file");
+    }
+
+    let output = run_cli_with_env(
+        &[
+            "--output",
+            "json",
+            "archive",
+            "-p",
+            test_dir.to_str().unwrap(),
+            "--older-than",
+            "30d",
+            "--yes",
+        ],
+        &config_root,
+        &[(
+            "BIN_EXPIRE_ARCHIVE_PATH",
+            config_root.join("archive").to_str().unwrap(),
+        )],
+    );
+    let _ = child.kill();
+    let _ = child.wait();
+
+    assert_eq!(
+        output.status.code(),
+        Some(0),
+        "stderr:\n{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains(&format!(
+            "Skipping 'old_daemon': in use (running as PID {}",
+            child.id()
+        )),
+        "{}",
+        stderr
+    );
+    assert!(daemon.exists(), "running binary must stay in place");
+    assert!(!idle.exists(), "idle binary is archived");
+
+    let doc: serde_json::Value =
+        serde_json::from_slice(&output.stdout).expect("stdout is not valid JSON");
+    let result = |name: &str| {
+        doc["records"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|r| r["name"] == name)
+            .map(|r| r["result"].as_str().unwrap().to_string())
+    };
+    assert_eq!(result("old_daemon").as_deref(), Some("skipped"));
+    assert_eq!(result("idle_tool").as_deref(), Some("moved"));
+
+    cleanup_dir(&test_dir);
+    cleanup_dir(&config_root);
+}
+
+/// This test verifies binaries named in git, ssh and systemd config are reported as
+/// referenced and never archived.
+#[test]
+fn test_referenced_binaries_are_protected() {
+    let test_dir = unique_dir("test_integration_dir_referenced");
+    let config_root = unique_dir("test_integration_config_referenced");
+    let home = config_root.join("home");
+    fs::create_dir_all(&test_dir).expect("Failed to create test dir");
+    fs::create_dir_all(home.join(".ssh")).expect("Failed to create fake home");
+    fs::create_dir_all(home.join(".config/systemd/user")).expect("Failed to create fake home");
+
+    let old_time = SystemTime::now() - Duration::from_secs(86400 * 200);
+    let ft = FileTime::from_system_time(old_time);
+    for name in [
+        "git-credential-vaultz",
+        "proxy_hop",
+        "syncd_tool",
+        "rc_tool",
+        "rc_arg_tool",
+        "rc_run_tool",
+        "plain_stale",
+    ] {
+        let path = test_dir.join(name);
+        fs::write(&path, "content").expect("Failed to write test file");
+        set_file_times(&path, ft, ft).expect("Failed to backdate file");
+    }
+
+    fs::write(
+        home.join(".gitconfig"),
+        "[user]\n\tname = me\n[credential \"https://example.com\"]\n\thelper = vaultz --timeout 10\n",
+    )
+    .expect("Failed to write .gitconfig");
+    fs::write(
+        home.join(".ssh/config"),
+        format!(
+            "Host bastion\n  ProxyCommand {} %h %p\n",
+            test_dir.join("proxy_hop").display()
+        ),
+    )
+    .expect("Failed to write ssh config");
+    fs::write(
+        home.join(".config/systemd/user/sync.service"),
+        "[Service]\nExecStart=-syncd_tool --watch\n",
+    )
+    .expect("Failed to write unit");
+    fs::write(
+        home.join(".bashrc"),
+        "# rc_tool is not run here\nalias x='ls'\necho rc_arg_tool && rc_run_tool --quiet\n",
+    )
+    .expect("Failed to write .bashrc");
+
+    // cargo (and rustup) find their own files through HOME unless told otherwise.
+    let real_home = PathBuf::from(std::env::var("HOME").unwrap_or_default());
+    let cargo_home = std::env::var("CARGO_HOME")
+        .unwrap_or_else(|_| real_home.join(".cargo").display().to_string());
+    let rustup_home = std::env::var("RUSTUP_HOME")
+        .unwrap_or_else(|_| real_home.join(".rustup").display().to_string());
+    let home_str = home.to_str().unwrap();
+    let env = [
+        ("HOME", home_str),
+        ("XDG_CONFIG_HOME", ""),
+        ("CARGO_HOME", cargo_home.as_str()),
+        ("RUSTUP_HOME", rustup_home.as_str()),
+    ];
+    let dir_str = test_dir.to_str().unwrap();
+    let output = run_cli_with_env(
+        &["--output", "json", "scan", "-p", dir_str, "--days", "30"],
+        &config_root,
+        &env,
+    );
+    assert!(
+        output.status.success(),
+        "scan failed:\n{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let doc: serde_json::Value =
+        serde_json::from_slice(&output.stdout).expect("stdout is not valid JSON");
+    let record = |name: &str| {
+        doc["records"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|r| r["name"] == name)
+            .cloned()
+            .unwrap()
+    };
+    assert_eq!(record("git-credential-vaultz")["status"], "referenced");
+    assert_eq!(
+        record("git-credential-vaultz")["referenced_by"],
+        format!(
+            "{}:4 (credential.helper)",
+            home.join(".gitconfig").display()
+        )
+    );
+    assert_eq!(record("proxy_hop")["status"], "referenced");
+    assert!(record("proxy_hop")["referenced_by"]
+        .as_str()
+        .unwrap()
+        .ends_with(":2 (ProxyCommand)"));
+    assert_eq!(record("syncd_tool")["status"], "referenced");
+    assert_eq!(record("rc_tool")["status"], "stale", "comments don't count");
+    assert_eq!(
+        record("rc_arg_tool")["status"],
+        "stale",
+        "arguments don't count"
+    );
+    assert_eq!(record("rc_run_tool")["status"], "referenced");
+    assert_eq!(record("plain_stale")["status"], "stale");
+
+    let archive_dir = config_root.join("archive");
+    let mut env_archive = env.to_vec();
+    env_archive.push(("BIN_EXPIRE_ARCHIVE_PATH", archive_dir.to_str().unwrap()));
+    let output = run_cli_with_env(
+        &["archive", "-p", dir_str, "--days", "30", "--yes"],
+        &config_root,
+        &env_archive,
+    );
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Skipping 'proxy_hop': referenced by"),
+        "{}",
+        stderr
+    );
+    assert!(test_dir.join("proxy_hop").exists());
+    assert!(test_dir.join("git-credential-vaultz").exists());
+    assert!(test_dir.join("syncd_tool").exists());
+    assert!(!test_dir.join("rc_tool").exists());
+    assert!(!test_dir.join("plain_stale").exists());
+
+    cleanup_dir(&test_dir);
+    cleanup_dir(&config_root);
+}
+
+/// This test verifies `scan --projects` marks binaries used by build files, CI workflows and
+/// package.json scripts under project_roots, and skips vendored node_modules.
+#[test]
+fn test_project_reference_pass() {
+    let test_dir = unique_dir("test_integration_dir_projects");
+    let config_root = unique_dir("test_integration_config_projects");
+    let src = config_root.join("src");
+    fs::create_dir_all(&test_dir).expect("Failed to create test dir");
+    let cfg_dir = config_root.join("bin-expire");
+    fs::create_dir_all(&cfg_dir).expect("Failed to create config dir");
+
+    let old_time = SystemTime::now() - Duration::from_secs(86400 * 200);
+    let ft = FileTime::from_system_time(old_time);
+    for name in ["protoc-gen-x", "lintme", "unused_tool", "vendored_only"] {
+        let path = test_dir.join(name);
+        fs::write(&path, "content").expect("Failed to write test file");
+        set_file_times(&path, ft, ft).expect("Failed to backdate file");
+    }
+
+    let api = src.join("api");
+    fs::create_dir_all(api.join(".git")).expect("Failed to create repo");
+    fs::create_dir_all(api.join(".github/workflows")).expect("Failed to create repo");
+    fs::create_dir_all(api.join("node_modules/dep")).expect("Failed to create repo");
+    fs::write(
+        api.join("Makefile"),
+        "# protoc-gen-x is not needed here\ngen:\n\tprotoc --plugin=protoc-gen-x=$(HOME)/.cargo/bin/protoc-gen-x\n",
+    )
+    .expect("Failed to write Makefile");
+    fs::write(
+        api.join(".github/workflows/ci.yml"),
+        "jobs:\n  lint:\n    steps:\n      - run: lintme --strict\n",
+    )
+    .expect("Failed to write workflow");
+    fs::write(
+        api.join("node_modules/dep/package.json"),
+        "{\n  \"scripts\": {\n    \"x\": \"vendored_only\"\n  }\n}\n",
+    )
+    .expect("Failed to write package.json");
+    let web = src.join("web");
+    fs::create_dir_all(&web).expect("Failed to create project");
+    fs::write(
+        web.join("package.json"),
+        "{\n  \"name\": \"unused_tool\",\n  \"scripts\": {\n    \"lint\": \"lintme src\"\n  }\n}\n",
+    )
+    .expect("Failed to write package.json");
+
+    let dir_str = test_dir.to_str().unwrap();
+    let output = run_cli(&["scan", "-p", dir_str, "--projects"], &config_root);
+    assert_eq!(
+        output.status.code(),
+        Some(5),
+        "--projects needs project_roots"
+    );
+
+    fs::write(
+        cfg_dir.join("config.toml"),
+        format!(
+            "config_version = 1\nproject_roots = [{:?}]\n",
+            src.to_str().unwrap()
+        ),
+    )
+    .expect("Failed to write config.toml");
+
+    let output = run_cli(
+        &["--output", "json", "scan", "-p", dir_str, "--projects"],
+        &config_root,
+    );
+    assert!(
+        output.status.success(),
+        "scan failed:\n{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let doc: serde_json::Value =
+        serde_json::from_slice(&output.stdout).expect("stdout is not valid JSON");
+    let used = |name: &str| {
+        doc["records"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|r| r["name"] == name)
+            .unwrap()["used_by_projects"]
+            .clone()
+    };
+    assert_eq!(
+        used("protoc-gen-x"),
+        format!("{} (Makefile:3)", api.display())
+    );
+    assert_eq!(
+        used("lintme"),
+        format!(
+            "{} (.github/workflows/ci.yml:4); {} (package.json:4)",
+            api.display(),
+            web.display()
+        )
+    );
+    assert!(used("unused_tool").is_null(), "only scripts count");
+    assert!(used("vendored_only").is_null(), "node_modules is skipped");
+
+    // Without --projects nothing is searched.
+    let output = run_cli(&["--output", "json", "scan", "-p", dir_str], &config_root);
+    let doc: serde_json::Value =
+        serde_json::from_slice(&output.stdout).expect("stdout is not valid JSON");
+    assert!(doc["records"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .all(|r| r["used_by_projects"].is_null()));
+
+    cleanup_dir(&test_dir);
+    cleanup_dir(&config_root);
+}
+
+/// This test verifies each `scan` is recorded in the history and `diff` / `history` report
+/// what changed between them.
+#[test]
+fn test_scan_history_and_diff() {
+    let test_dir = unique_dir("test_integration_dir_history");
+    let config_root = unique_dir("test_integration_config_history");
+    fs::create_dir_all(&test_dir).expect("Failed to create test dir");
+    let dir_str = test_dir.to_str().unwrap();
+
+    let json = |args: &[&str]| -> serde_json::Value {
+        let output = run_cli(args, &config_root);
+        assert!(
+            output.status.success(),
+            "{:?} failed:\n{}",
+            args,
+            String::from_utf8_lossy(&output.stderr)
+        );
+        serde_json::from_slice(&output.stdout).expect("stdout is not valid JSON")
+    };
+    let backdate = |name: &str, days: u64| {
+        let t = FileTime::from_system_time(SystemTime::now() - Duration::from_secs(86400 * days));
+        set_file_times(test_dir.join(name), t, t).expect("Failed to set file times");
+    };
+
+    let output = run_cli(&["diff"], &config_root);
+    assert_eq!(output.status.code(), Some(1), "nothing to diff yet");
+
+    for (name, content) in [("alpha", "a"), ("beta", "bb"), ("gamma", "ccc")] {
+        fs::write(test_dir.join(name), content).expect("Failed to write test file");
+    }
+    backdate("alpha", 1);
+    backdate("beta", 100);
+    backdate("gamma", 100);
+    json(&["--output", "json", "scan", "-p", dir_str, "--days", "30"]);
+
+    // alpha goes stale, beta is used again, gamma is removed, delta is new.
+    backdate("alpha", 100);
+    backdate("beta", 0);
+    fs::remove_file(test_dir.join("gamma")).expect("Failed to remove gamma");
+    fs::write(test_dir.join("delta"), "dddd").expect("Failed to write test file");
+    json(&["--output", "json", "scan", "-p", dir_str, "--days", "30"]);
+
+    // check is for CI and doesn't add to the history.
+    run_cli(&["check", "-p", dir_str, "--days", "30"], &config_root);
+
+    let doc = json(&["--output", "json", "diff"]);
+    let changes: Vec<(String, String)> = doc["records"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|r| {
+            (
+                r["change"].as_str().unwrap().to_string(),
+                r["name"].as_str().unwrap().to_string(),
+            )
+        })
+        .collect();
+    let expected: Vec<(String, String)> = [
+        ("went_stale", "alpha"),
+        ("used_again", "beta"),
+        ("added", "delta"),
+        ("removed", "gamma"),
+    ]
+    .iter()
+    .map(|(c, n)| (c.to_string(), n.to_string()))
+    .collect();
+    assert_eq!(changes, expected);
+
+    let doc = json(&["--output", "json", "history"]);
+    let record = |name: &str| {
+        doc["records"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|r| r["name"] == name)
+            .cloned()
+            .unwrap()
+    };
+    assert_eq!(record("alpha")["status"], "stale");
+    assert_eq!(record("alpha")["went_stale"], record("alpha")["last_seen"]);
+    assert_eq!(record("alpha")["scans"], 2);
+    assert_eq!(record("beta")["status"], "ok");
+    assert!(record("beta")["went_stale"].is_null());
+    assert_eq!(record("gamma")["status"], "gone");
+    assert_eq!(record("delta")["scans"], 1);
+
+    let doc = json(&["--output", "json", "history", "--sizes"]);
+    let sizes = doc["records"].as_array().unwrap();
+    assert_eq!(sizes.len(), 2, "one record per scan of the directory");
+    assert_eq!(sizes[0]["total_size"], 6);
+    assert!(sizes[0]["change"].is_null());
+    assert_eq!(sizes[1]["total_size"], 7);
+    assert_eq!(sizes[1]["change"], 1);
+
+    let doc = json(&["--output", "json", "history", "beta"]);
+    let statuses: Vec<&str> = doc["records"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|r| r["status"].as_str().unwrap())
+        .collect();
+    assert_eq!(statuses, ["stale", "ok"]);
+
+    let output = run_cli(&["diff", "1", "9"], &config_root);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("No recorded scan #9"));
+
+    let output = run_cli(&["history"], &config_root);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("2 scans from"), "{}", stdout);
+
+    cleanup_dir(&test_dir);
+    cleanup_dir(&config_root);
+}
+
+/// This test verifies `archive` only moves a binary once it has been seen stale in enough
+/// runs over the configured period, and that previews don't count as runs.
+#[test]
+fn test_archive_waits_for_confirmed_staleness() {
+    let test_dir = unique_dir("test_integration_dir_eligibility");
+    let config_root = unique_dir("test_integration_config_eligibility");
+    fs::create_dir_all(&test_dir).expect("Failed to create test dir");
+    let dir_str = test_dir.to_str().unwrap();
+    let archive_dir = config_root.join("archive");
+    let env = [("BIN_EXPIRE_ARCHIVE_PATH", archive_dir.to_str().unwrap())];
+
+    let backdate = |name: &str, days: u64| {
+        let t = FileTime::from_system_time(SystemTime::now() - Duration::from_secs(86400 * days));
+        set_file_times(test_dir.join(name), t, t).expect("Failed to set file times");
+    };
+    for name in ["old_tool", "reused_tool"] {
+        fs::write(test_dir.join(name), "content").expect("Failed to write test file");
+        backdate(name, 100);
+    }
+
+    for (key, value) in [("confirm_stale_scans", "3"), ("confirm_stale_period", "7d")] {
+        let output = run_cli(&["config", "set", key, value], &config_root);
+        assert!(
+            output.status.success(),
+            "config set {} failed:\n{}",
+            key,
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    let output = run_cli(
+        &["--output", "json", "scan", "-p", dir_str, "--days", "30"],
+        &config_root,
+    );
+    assert!(output.status.success());
+    let doc: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let old = doc["records"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|r| r["name"] == "old_tool")
+        .unwrap();
+    assert_eq!(old["stale_scans"], 1);
+    assert_eq!(old["confirmed_stale"], false);
+
+    // Previews don't count as observations and leave eligibility.json alone.
+    let store_path = config_root.join("bin-expire").join("eligibility.json");
+    let before = fs::read_to_string(&store_path).unwrap();
+    let plan_path = config_root.join("plan.json");
+    for preview in [
+        vec!["archive", "-p", dir_str, "--days", "30", "--dry-run"],
+        vec![
+            "archive",
+            "-p",
+            dir_str,
+            "--days",
+            "30",
+            "--plan",
+            plan_path.to_str().unwrap(),
+        ],
+    ] {
+        let output = run_cli_with_env(&preview, &config_root, &env);
+        assert!(output.status.success());
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(
+            stderr.contains("not confirmed stale yet (stale in 2 of 3 scans"),
+            "{}",
+            stderr
+        );
+    }
+    assert_eq!(fs::read_to_string(&store_path).unwrap(), before);
+
+    // Second and third observation: enough scans, but the first one was only just now.
+    for scans in [2, 3] {
+        let output = run_cli_with_env(
+            &["archive", "-p", dir_str, "--days", "30", "--yes"],
+            &config_root,
+            &env,
+        );
+        assert!(output.status.success());
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let expected = format!(
+            "Skipping 'old_tool': not confirmed stale yet (stale in {} of 3 scans, for 0 days of 7 days)",
+            scans
+        );
+        assert!(stderr.contains(&expected), "{}", stderr);
+    }
+    assert!(test_dir.join("old_tool").exists());
+
+    // reused_tool is used in between: its streak starts over.
+    backdate("reused_tool", 0);
+    run_cli(&["scan", "-p", dir_str, "--days", "30"], &config_root);
+    let mut store: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&store_path).unwrap()).unwrap();
+    let streaks = store["streaks"].as_object_mut().unwrap();
+    assert!(!streaks.keys().any(|k| k.ends_with("reused_tool")));
+
+    // Pretend the streak began eight days ago.
+    let eight_days_ago =
+        humantime::format_rfc3339_seconds(SystemTime::now() - Duration::from_secs(86400 * 8))
+            .to_string();
+    for streak in streaks.values_mut() {
+        streak["first_stale"] = serde_json::Value::from(eight_days_ago.clone());
+    }
+    fs::write(&store_path, serde_json::to_string(&store).unwrap()).unwrap();
+
+    let output = run_cli_with_env(
+        &["archive", "-p", dir_str, "--days", "30", "--yes"],
+        &config_root,
+        &env,
+    );
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(
+        !test_dir.join("old_tool").exists(),
+        "confirmed stale: archived"
+    );
+    assert!(test_dir.join("reused_tool").exists());
+
+    cleanup_dir(&test_dir);
+    cleanup_dir(&config_root);
+}
+
+/// This test verifies `scan --inspect` caches what it read, reuses it for unchanged files and
+/// reads changed ones again.
+#[test]
+fn test_scan_cache_reuses_unchanged_files() {
+    let test_dir = unique_dir("test_integration_dir_scan_cache");
+    let config_root = unique_dir("test_integration_config_scan_cache");
+    fs::create_dir_all(&test_dir).expect("Failed to create test dir");
+    let dir_str = test_dir.to_str().unwrap();
+
+    let script = test_dir.join("old_script");
+    fs::write(&script, "#!/bin/sh\necho hi\n").expect("Failed to write test file");
+    let old_time = FileTime::from_system_time(SystemTime::now() - Duration::from_secs(86400 * 200));
+    set_file_times(&script, old_time, old_time).expect("Failed to backdate file");
+
+    let record = |extra: &[&str]| -> serde_json::Value {
+        let mut args = vec!["--output", "json", "scan", "-p", dir_str, "--days", "30"];
+        args.extend_from_slice(extra);
+        let output = run_cli(&args, &config_root);
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        let doc: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        doc["records"][0].clone()
+    };
+
+    // Without --inspect nothing is read, and nothing is cached yet.
+    assert!(record(&[])["kind"].is_null());
+
+    let first = record(&["--inspect"]);
+    assert_eq!(first["kind"], "script");
+    let real_hash = first["sha256"].as_str().unwrap().to_string();
+    assert_eq!(real_hash.len(), 64);
+
+    // Reading the file to hash it must not count as using it.
+    let accessed = fs::metadata(&script).unwrap().accessed().unwrap();
+    assert_eq!(FileTime::from_system_time(accessed), old_time);
+    assert_eq!(first["status"], "stale");
+
+    // Prove the second scan comes from the cache by planting a marker in it.
+    let cache_path = config_root.join("bin-expire").join("scan-cache.json");
+    let raw = fs::read_to_string(&cache_path).expect("scan cache was not written");
+    fs::write(&cache_path, raw.replace(&real_hash, "from-cache")).unwrap();
+    assert_eq!(record(&[])["sha256"], "from-cache");
+    assert_eq!(record(&["--inspect"])["sha256"], "from-cache");
+    assert_eq!(
+        record(&["--inspect", "--no-cache"])["sha256"],
+        real_hash.as_str()
+    );
+
+    // A changed file is read again.
+    fs::write(&script, "#!/bin/sh\necho changed\n").expect("Failed to rewrite test file");
+    assert!(record(&[])["kind"].is_null());
+    let changed = record(&["--inspect"]);
+    assert_ne!(changed["sha256"], "from-cache");
+    assert_ne!(changed["sha256"], real_hash.as_str());
+
+    cleanup_dir(&test_dir);
+    cleanup_dir(&config_root);
+}
+
+/// This test verifies a parallel scan reports exactly what a single-threaded one does.
+#[test]
+fn test_parallel_scan_matches_single_threaded() {
+    let dir_a = unique_dir("test_integration_dir_parallel_a");
+    let dir_b = unique_dir("test_integration_dir_parallel_b");
+    let config_root = unique_dir("test_integration_config_parallel");
+    for (dir, prefix) in [(&dir_a, "a"), (&dir_b, "b")] {
+        fs::create_dir_all(dir).expect("Failed to create test dir");
+        for i in 0..40 {
+            let path = dir.join(format!("{}_tool_{:02}", prefix, i));
+            fs::write(&path, "x".repeat(i)).expect("Failed to write test file");
+            let t = FileTime::from_system_time(
+                SystemTime::now() - Duration::from_secs(86400 * (i as u64 * 7)),
+            );
+            set_file_times(&path, t, t).expect("Failed to set file times");
+        }
+    }
+    let dirs = format!(
+        "scan_dirs = [{:?}, {:?}]\n",
+        dir_a.to_str().unwrap(),
+        dir_b.to_str().unwrap()
+    );
+    let config_file = config_root.join("dirs.toml");
+    fs::create_dir_all(&config_root).unwrap();
+    fs::write(&config_file, dirs).unwrap();
+
+    let records = |threads: &[&str]| -> Vec<serde_json::Value> {
+        let mut args = vec![
+            "--config",
+            config_file.to_str().unwrap(),
+            "--output",
+            "json",
+        ];
+        args.extend_from_slice(threads);
+        args.extend(["scan", "--verbose", "--sort", "size", "--no-cache"]);
+        let output = run_cli(&args, &config_root);
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        let doc: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        doc["records"].as_array().unwrap().clone()
+    };
+
+    let single = records(&["--single-threaded"]);
+    assert_eq!(single.len(), 80);
+    assert_eq!(records(&["--threads", "8"]), single);
+
+    let output = run_cli(
+        &["--threads", "2", "--single-threaded", "scan"],
+        &config_root,
+    );
+    assert_eq!(output.status.code(), Some(2));
+
+    cleanup_dir(&dir_a);
+    cleanup_dir(&dir_b);
+    cleanup_dir(&config_root);
+}
+
+/// This test verifies glob scan dirs and `--max-depth` find nested executables without
+/// following a symlink loop forever.
+#[cfg(unix)]
+#[test]
+fn test_nested_and_glob_scan_dirs() {
+    use std::os::unix::fs::{symlink, PermissionsExt};
+
+    let root = unique_dir("test_integration_dir_nested");
+    let config_root = unique_dir("test_integration_config_nested");
+    let share = root.join("share");
+    for sub in ["alpha/bin", "beta/bin", "alpha/lib"] {
+        fs::create_dir_all(share.join(sub)).expect("Failed to create test dir");
+    }
+    for tool in ["alpha/bin/alpha_tool", "beta/bin/beta_tool"] {
+        let path = share.join(tool);
+        fs::write(&path, "#!/bin/sh\n").expect("Failed to write test file");
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+    fs::write(share.join("alpha/lib/data.txt"), "not a binary").unwrap();
+    symlink(&share, share.join("alpha/loop")).expect("Failed to create symlink loop");
+
+    let names = |args: &[&str]| -> (Vec<String>, String) {
+        let mut full = vec!["--output", "json"];
+        full.extend_from_slice(args);
+        let output = run_cli(&full, &config_root);
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        let doc: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        let mut names: Vec<String> = doc["records"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|r| r["name"].as_str().unwrap().to_string())
+            .collect();
+        names.sort();
+        (names, String::from_utf8_lossy(&output.stderr).to_string())
+    };
+
+    // Default depth: nothing is directly inside `root`.
+    let root_str = root.to_str().unwrap();
+    let (found, _) = names(&["scan", "-p", root_str, "--verbose"]);
+    assert!(found.is_empty(), "{:?}", found);
+
+    let pattern = format!("{}/share/*/bin", root_str);
+    let (found, _) = names(&["scan", "-p", &pattern, "--verbose"]);
+    assert_eq!(found, ["alpha_tool", "beta_tool"]);
+
+    // Recursing: only executables below the top level, and the loop is not followed forever.
+    let (found, stderr) = names(&["scan", "-p", root_str, "--verbose", "--max-depth", "8"]);
+    assert_eq!(found, ["alpha_tool", "beta_tool"]);
+    assert!(stderr.contains("symlink loop"), "{}", stderr);
+
+    let missing = format!("{}/nothing/*/bin", root_str);
+    let output = run_cli(&["scan", "-p", &missing], &config_root);
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("No directories match"), "{}", stderr);
+
+    let output = run_cli(&["scan", "-p", root_str, "--max-depth", "0"], &config_root);
+    assert_eq!(output.status.code(), Some(2));
+
+    cleanup_dir(&root);
+    cleanup_dir(&config_root);
+}
+
+/// This test verifies that history and diff follow binaries below the top of a recursive
+/// (`--max-depth`) scan: they go stale and gone like top-level ones and count towards the
+/// directory's size, while a shallower scan doesn't report them as gone.
+#[test]
+fn test_history_follows_nested_binaries() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let test_dir = unique_dir("test_integration_dir_history_nested");
+    let config_root = unique_dir("test_integration_config_history_nested");
+    let nested = test_dir.join("sub").join("bin").join("nested");
+    let top = test_dir.join("top");
+    fs::create_dir_all(nested.parent().unwrap()).expect("Failed to create test dir");
+    for (path, content) in [(&top, "#!/bin/sh\n"), (&nested, "#!/bin/sh\nexit 0\n")] {
+        fs::write(path, content).expect("Failed to write test file");
+        fs::set_permissions(path, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+    let dir_str = test_dir.to_str().unwrap();
+
+    let json = |args: &[&str]| -> serde_json::Value {
+        let output = run_cli(args, &config_root);
+        assert!(
+            output.status.success(),
+            "{:?} failed:\n{}",
+            args,
+            String::from_utf8_lossy(&output.stderr)
+        );
+        serde_json::from_slice(&output.stdout).expect("stdout is not valid JSON")
+    };
+    let changes = |args: &[&str]| -> Vec<(String, String)> {
+        json(args)["records"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|r| {
+                (
+                    r["change"].as_str().unwrap().to_string(),
+                    r["name"].as_str().unwrap().to_string(),
+                )
+            })
+            .collect()
+    };
+    let scan = |depth: &str| {
+        json(&[
+            "--output",
+            "json",
+            "scan",
+            "-p",
+            dir_str,
+            "--days",
+            "30",
+            "--max-depth",
+            depth,
+        ]);
+    };
+
+    scan("3");
+    let old = FileTime::from_system_time(SystemTime::now() - Duration::from_secs(86400 * 100));
+    for path in [&top, &nested] {
+        set_file_times(path, old, old).expect("Failed to backdate file");
+    }
+    scan("3");
+    let expected = |change: &str| {
+        vec![
+            (change.to_string(), "nested".to_string()),
+            (change.to_string(), "top".to_string()),
+        ]
+    };
+    let mut went_stale = changes(&["--output", "json", "diff"]);
+    went_stale.sort();
+    assert_eq!(went_stale, expected("went_stale"));
+
+    let doc = json(&["--output", "json", "history", "--sizes"]);
+    let sizes = doc["records"].as_array().unwrap();
+    assert_eq!(sizes.len(), 2);
+    assert_eq!(sizes[1]["binaries"], 2);
+    assert_eq!(sizes[1]["total_size"], 10 + 17);
+
+    // A top-level scan didn't look that deep, so `nested` isn't gone...
+    scan("1");
+    assert!(changes(&["--output", "json", "diff"]).is_empty());
+    let doc = json(&["--output", "json", "history"]);
+    let nested_record = doc["records"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|r| r["name"] == "nested")
+        .cloned()
+        .unwrap();
+    assert_eq!(nested_record["status"], "stale");
+
+    // ...but once it's removed, the next deep scan says so.
+    fs::remove_file(&nested).expect("Failed to remove nested");
+    scan("3");
+    assert_eq!(
+        changes(&["--output", "json", "diff", "2", "4"]),
+        [("removed".to_string(), "nested".to_string())]
+    );
+
+    cleanup_dir(&test_dir);
+    cleanup_dir(&config_root);
+}
+
+/// This test verifies `toolchains` lists rustup toolchains and archives only the stale,
+/// unreferenced ones, which `restore` brings back.
+#[test]
+fn test_toolchains_mode() {
+    let root = unique_dir("test_integration_dir_toolchains");
+    let config_root = unique_dir("test_integration_config_toolchains");
+    let rustup_home = root.join("rustup");
+    let archive_dir = root.join("archive");
+    let project = root.join("src").join("legacy");
+    fs::create_dir_all(project.join(".git")).expect("Failed to create project dir");
+    fs::write(
+        project.join("rust-toolchain.toml"),
+        "[toolchain]\nchannel = \"1.70.0\"\n",
+    )
+    .unwrap();
+    fs::create_dir_all(&rustup_home).expect("Failed to create rustup home");
+    fs::write(
+        rustup_home.join("settings.toml"),
+        "version = \"12\"\ndefault_toolchain = \"stable-x86_64-unknown-linux-gnu\"\n",
+    )
+    .unwrap();
+
+    let old = FileTime::from_system_time(SystemTime::now() - Duration::from_secs(86400 * 400));
+    for name in [
+        "stable-x86_64-unknown-linux-gnu",
+        "1.70.0-x86_64-unknown-linux-gnu",
+        "nightly-2024-01-01-x86_64-unknown-linux-gnu",
+        "beta-x86_64-unknown-linux-gnu",
+    ] {
+        let toolchain = rustup_home.join("toolchains").join(name);
+        fs::create_dir_all(toolchain.join("bin")).expect("Failed to create toolchain");
+        fs::create_dir_all(toolchain.join("lib")).unwrap();
+        fs::write(
+            toolchain.join("lib").join("librustc_driver.so"),
+            "x".repeat(100),
+        )
+        .unwrap();
+        let rustc = toolchain.join("bin").join("rustc");
+        fs::write(&rustc, "#!/bin/sh\n").unwrap();
+        if name.starts_with("beta") {
+            continue;
+        }
+        set_file_times(&rustc, old, old).expect("Failed to backdate toolchain");
+    }
+
+    let config_file = config_root.join("toolchains.toml");
+    fs::create_dir_all(&config_root).unwrap();
+    fs::write(
+        &config_file,
+        format!(
+            "project_roots = [{:?}]\narchive_path = {:?}\n",
+            root.join("src").to_str().unwrap(),
+            archive_dir.to_str().unwrap()
+        ),
+    )
+    .unwrap();
+    let config_arg = config_file.to_str().unwrap();
+    // RUSTUP_HOME must not reach `cargo run` (the cargo proxy would look there), so run the
+    // built binary directly.
+    let run = |args: &[&str]| -> Output {
+        let mut cmd = Command::new(env!("CARGO_BIN_EXE_bin-expire"));
+        isolate_config(&mut cmd, &config_root);
+        cmd.env("RUSTUP_HOME", &rustup_home);
+        cmd.args(["--config", config_arg]);
+        cmd.args(args);
+        cmd.output().expect("Failed to execute command")
+    };
+
+    let output = run(&["--output", "json", "toolchains"]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let doc: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let statuses: Vec<(String, String)> = doc["records"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|r| {
+            (
+                r["name"].as_str().unwrap().to_string(),
+                r["status"].as_str().unwrap().to_string(),
+            )
+        })
+        .collect();
+    let status_of = |name: &str| {
+        statuses
+            .iter()
+            .find(|(n, _)| n.starts_with(name))
+            .map(|(_, s)| s.clone())
+            .unwrap()
+    };
+    assert_eq!(status_of("stable"), "default");
+    assert_eq!(status_of("1.70.0"), "project");
+    assert_eq!(status_of("nightly"), "stale");
+    assert_eq!(status_of("beta"), "ok");
+
+    // Only the stale, unreferenced toolchain moves, and restore brings it back.
+    let nightly = "nightly-2024-01-01-x86_64-unknown-linux-gnu";
+    let output = run(&["toolchains", "--archive", "--yes"]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(!rustup_home.join("toolchains").join(nightly).exists());
+    assert!(archive_dir
+        .join("toolchains")
+        .join(nightly)
+        .join("bin/rustc")
+        .exists());
+    assert!(rustup_home
+        .join("toolchains/stable-x86_64-unknown-linux-gnu")
+        .exists());
+    let names = read_manifest_names(&config_root.join("bin-expire").join("archive.json"));
+    assert_eq!(names, [nightly]);
+
+    let output = run(&["restore", nightly]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(rustup_home
+        .join("toolchains")
+        .join(nightly)
+        .join("lib/librustc_driver.so")
+        .exists());
+
+    let output = run(&["toolchains", "--remove", "--dry-run"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Would delete 1 toolchains"), "{}", stdout);
+    assert!(rustup_home.join("toolchains").join(nightly).exists());
+
+    cleanup_dir(&root);
+    cleanup_dir(&config_root);
+}
+
+/// This test verifies `runtimes` lists version-manager installs and archives the stale,
+/// unpinned ones, which `restore` brings back.
+#[test]
+fn test_runtimes_mode() {
+    let root = unique_dir("test_integration_dir_runtimes");
+    let config_root = unique_dir("test_integration_config_runtimes");
+    let home = root.join("home");
+    let archive_dir = root.join("archive");
+    let nvm = home.join(".nvm");
+    let pyenv = home.join(".pyenv");
+    let asdf = home.join(".asdf");
+
+    let old = FileTime::from_system_time(SystemTime::now() - Duration::from_secs(86400 * 400));
+    let install = |dir: PathBuf, program: &str, stale: bool| {
+        fs::create_dir_all(dir.join("bin")).expect("Failed to create runtime dir");
+        fs::create_dir_all(dir.join("lib")).unwrap();
+        fs::write(dir.join("lib").join("libruntime.so"), "x".repeat(200)).unwrap();
+        let exe = dir.join("bin").join(program);
+        fs::write(&exe, "#!/bin/sh\n").unwrap();
+        if stale {
+            set_file_times(&exe, old, old).expect("Failed to backdate runtime");
+        }
+    };
+    install(nvm.join("versions/node/v16.20.0"), "node", true);
+    install(nvm.join("versions/node/v18.17.0"), "node", true);
+    install(nvm.join("versions/node/v20.11.0"), "node", true);
+    install(pyenv.join("versions/3.9.18"), "python3", true);
+    install(pyenv.join("versions/3.12.1"), "python3", false);
+    install(asdf.join("installs/nodejs/14.21.3"), "node", true);
+    fs::create_dir_all(nvm.join("alias")).unwrap();
+    fs::write(nvm.join("alias").join("default"), "20\n").unwrap();
+
+    let project = root.join("src").join("web");
+    fs::create_dir_all(project.join(".git")).expect("Failed to create project dir");
+    fs::write(project.join(".nvmrc"), "v18.17.0\n").unwrap();
+    fs::write(project.join(".tool-versions"), "nodejs 14.21.3\n").unwrap();
+
+    let config_file = config_root.join("runtimes.toml");
+    fs::create_dir_all(&config_root).unwrap();
+    fs::write(
+        &config_file,
+        format!(
+            "project_roots = [{:?}]\narchive_path = {:?}\n",
+            root.join("src").to_str().unwrap(),
+            archive_dir.to_str().unwrap()
+        ),
+    )
+    .unwrap();
+    let config_arg = config_file.to_str().unwrap();
+    // HOME moves ~/.tool-versions and the manager roots, and must not reach `cargo run`, so run
+    // the built binary directly.
+    let run = |args: &[&str]| -> Output {
+        let mut cmd = Command::new(env!("CARGO_BIN_EXE_bin-expire"));
+        isolate_config(&mut cmd, &config_root);
+        cmd.env("HOME", &home);
+        for var in [
+            "NVM_DIR",
+            "PYENV_ROOT",
+            "RBENV_ROOT",
+            "ASDF_DATA_DIR",
+            "MISE_DATA_DIR",
+            "MISE_CONFIG_DIR",
+        ] {
+            cmd.env_remove(var);
+        }
+        cmd.args(["--config", config_arg]);
+        cmd.args(args);
+        cmd.output().expect("Failed to execute command")
+    };
+
+    let output = run(&["--output", "json", "runtimes"]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let doc: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let mut statuses: Vec<(String, String)> = doc["records"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|r| {
+            (
+                r["name"].as_str().unwrap().to_string(),
+                r["status"].as_str().unwrap().to_string(),
+            )
+        })
+        .collect();
+    statuses.sort();
+    let expected = [
+        ("asdf/node@14.21.3", "project"),
+        ("nvm/node@v16.20.0", "stale"),
+        ("nvm/node@v18.17.0", "project"),
+        ("nvm/node@v20.11.0", "default"),
+        ("pyenv/python@3.12.1", "ok"),
+        ("pyenv/python@3.9.18", "stale"),
+    ];
+    assert_eq!(
+        statuses,
+        expected.map(|(n, s)| (n.to_string(), s.to_string()))
+    );
+
+    let output = run(&["pin", "pyenv/python@3.9.18"]);
+    assert!(output.status.success());
+    let output = run(&["runtimes", "--archive", "--yes"]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(!nvm.join("versions/node/v16.20.0").exists());
+    assert!(archive_dir
+        .join("runtimes/nvm/node/v16.20.0/bin/node")
+        .exists());
+    assert!(pyenv.join("versions/3.9.18").exists());
+    assert!(nvm.join("versions/node/v18.17.0").exists());
+    let names = read_manifest_names(&config_root.join("bin-expire").join("archive.json"));
+    assert_eq!(names, ["nvm/node@v16.20.0"]);
+
+    let output = run(&["restore", "nvm/node@v16.20.0"]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(nvm
+        .join("versions/node/v16.20.0/lib/libruntime.so")
+        .exists());
+
+    cleanup_dir(&root);
+    cleanup_dir(&config_root);
+}
+
+/// This test verifies `caches` reports stale cargo and Go cache entries and deletes them
+/// only with `--delete`.
+#[test]
+fn test_caches_mode() {
+    let root = unique_dir("test_integration_dir_caches");
+    let config_root = unique_dir("test_integration_config_caches");
+    let cargo_home = root.join("cargo");
+    let mod_cache = root.join("gomod");
+
+    let old = FileTime::from_system_time(SystemTime::now() - Duration::from_secs(86400 * 400));
+    let write = |path: PathBuf, len: usize, stale: bool| {
+        fs::create_dir_all(path.parent().unwrap()).expect("Failed to create cache dir");
+        fs::write(&path, "x".repeat(len)).unwrap();
+        if stale {
+            set_file_times(&path, old, old).expect("Failed to backdate cache file");
+        }
+    };
+    let index = "index.crates.io-6f17d22bba15001f";
+    let registry = cargo_home.join("registry");
+    write(
+        registry.join("cache").join(index).join("serde-1.0.0.crate"),
+        100,
+        true,
+    );
+    write(
+        registry
+            .join("cache")
+            .join(index)
+            .join("anyhow-1.0.80.crate"),
+        50,
+        false,
+    );
+    write(
+        registry
+            .join("src")
+            .join(index)
+            .join("serde-1.0.0/src/lib.rs"),
+        300,
+        true,
+    );
+    write(
+        cargo_home.join("git/checkouts/tokio-abc123/f00dfeed/Cargo.toml"),
+        40,
+        true,
+    );
+    let cobra = mod_cache.join("github.com/spf13/cobra@v1.8.0");
+    write(cobra.join("command.go"), 500, true);
+    write(mod_cache.join("golang.org/x/mod@v0.14.0/go.mod"), 20, false);
+    write(
+        mod_cache.join("cache/download/github.com/spf13/cobra/@v/v1.8.0.zip"),
+        70,
+        true,
+    );
+    // Go makes module directories read-only.
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&cobra, fs::Permissions::from_mode(0o555)).unwrap();
+    }
+
+    // CARGO_HOME must not reach `cargo run`, so run the built binary directly.
+    let run = |args: &[&str]| -> Output {
+        let mut cmd = Command::new(env!("CARGO_BIN_EXE_bin-expire"));
+        isolate_config(&mut cmd, &config_root);
+        cmd.env("CARGO_HOME", &cargo_home);
+        cmd.env("GOMODCACHE", &mod_cache);
+        cmd.args(args);
+        cmd.output().expect("Failed to execute command")
+    };
+
+    let output = run(&["--output", "json", "caches", "--days", "90"]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let doc: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let stale: Vec<(String, String, u64)> = doc["records"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|r| {
+            (
+                r["ecosystem"].as_str().unwrap().to_string(),
+                r["name"].as_str().unwrap().to_string(),
+                r["size"].as_u64().unwrap(),
+            )
+        })
+        .collect();
+    let expected = [
+        ("cargo-crates", format!("{}/serde-1.0.0.crate", index), 100),
+        ("cargo-sources", format!("{}/serde-1.0.0", index), 300),
+        ("cargo-git", "tokio-abc123/f00dfeed".to_string(), 40),
+        (
+            "go-modules",
+            "github.com/spf13/cobra@v1.8.0".to_string(),
+            500,
+        ),
+    ];
+    assert_eq!(
+        stale,
+        expected
+            .iter()
+            .map(|(e, n, s)| (e.to_string(), n.clone(), *s))
+            .collect::<Vec<_>>()
+    );
+
+    let output = run(&["caches", "--days", "90"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("go module versions"), "{}", stdout);
+    assert!(
+        stdout.contains("940 B reclaimable in 4 stale entries"),
+        "{}",
+        stdout
+    );
+
+    let output = run(&["caches", "--days", "90", "--delete", "--dry-run"]);
+    assert!(output.status.success());
+    assert!(cobra.exists());
+
+    let output = run(&["caches", "--days", "90", "--delete", "--yes"]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Deleted 4 entries"));
+    assert!(!cobra.exists());
+    assert!(!registry
+        .join("src")
+        .join(index)
+        .join("serde-1.0.0")
+        .exists());
+    assert!(registry
+        .join("cache")
+        .join(index)
+        .join("anyhow-1.0.80.crate")
+        .exists());
+    assert!(mod_cache.join("golang.org/x/mod@v0.14.0").exists());
+    assert!(mod_cache.join("cache/download").exists());
+
+    cleanup_dir(&root);
+    cleanup_dir(&config_root);
+}
+
+/// This test verifies `sweep` finds build directories of stale projects and archives or
+/// deletes them under their path below the root, and that `restore` puts one back.
+#[test]
+fn test_sweep_mode() {
+    let root = unique_dir("test_integration_dir_sweep");
+    let config_root = unique_dir("test_integration_config_sweep");
+    let archive_dir = root.join("archive");
+    let src = root.join("src");
+
+    let old = FileTime::from_system_time(SystemTime::now() - Duration::from_secs(86400 * 400));
+    let write = |path: PathBuf, len: usize, stale: bool| {
+        fs::create_dir_all(path.parent().unwrap()).expect("Failed to create project dir");
+        fs::write(&path, "x".repeat(len)).unwrap();
+        if stale {
+            set_file_times(&path, old, old).expect("Failed to backdate project file");
+        }
+    };
+    write(src.join("old-tool/Cargo.toml"), 10, true);
+    write(src.join("old-tool/src/main.rs"), 10, true);
+    // Fresh build output doesn't make the project fresh.
+    write(src.join("old-tool/target/debug/old-tool"), 1000, false);
+    write(src.join("old-tool/target/CACHEDIR.TAG"), 10, false);
+    write(src.join("old-web/package.json"), 10, true);
+    write(
+        src.join("old-web/node_modules/left-pad/index.js"),
+        500,
+        true,
+    );
+    write(src.join("fresh/Cargo.toml"), 10, false);
+    write(src.join("fresh/target/debug/fresh"), 2000, false);
+    // Same project name one level down: named by its path below the root.
+    write(src.join("work/old-tool/Cargo.toml"), 10, true);
+    write(src.join("work/old-tool/target/debug/old-tool"), 300, false);
+
+    let archive_env = [("BIN_EXPIRE_ARCHIVE_PATH", archive_dir.to_str().unwrap())];
+    let src_arg = src.to_str().unwrap();
+
+    let output = run_cli_with_env(
+        &[
+            "--output", "json", "sweep", "--dir", src_arg, "--days", "90",
+        ],
+        &config_root,
+        &archive_env,
+    );
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let doc: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let statuses: Vec<(String, String, u64)> = doc["records"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|r| {
+            (
+                r["name"].as_str().unwrap().to_string(),
+                r["status"].as_str().unwrap().to_string(),
+                r["size"].as_u64().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        statuses,
+        [
+            ("fresh/target".to_string(), "ok".to_string(), 2000),
+            ("old-tool/target".to_string(), "stale".to_string(), 1010),
+            ("old-web/node_modules".to_string(), "stale".to_string(), 500),
+            ("work/old-tool/target".to_string(), "stale".to_string(), 300),
+        ]
+    );
+
+    let output = run_cli_with_env(
+        &[
+            "sweep",
+            "--dir",
+            src_arg,
+            "--days",
+            "90",
+            "--remove",
+            "--dry-run",
+        ],
+        &config_root,
+        &archive_env,
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("Would delete 3 build directories"),
+        "{}",
+        stdout
+    );
+    assert!(src.join("old-tool/target").exists());
+
+    // --dry-run and --yes only mean something with --archive or --remove.
+    for flag in ["--dry-run", "--yes"] {
+        let output = run_cli(&["sweep", "--dir", src_arg, flag], &config_root);
+        assert_eq!(output.status.code(), Some(2), "sweep {}", flag);
+    }
+
+    let output = run_cli_with_env(
+        &[
+            "sweep",
+            "--dir",
+            src_arg,
+            "--days",
+            "90",
+            "--archive",
+            "--yes",
+        ],
+        &config_root,
+        &archive_env,
+    );
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(!src.join("old-tool/target").exists());
+    assert!(!src.join("old-web/node_modules").exists());
+    assert!(src.join("fresh/target").exists());
+    assert!(archive_dir
+        .join("sweep/old-web/node_modules/left-pad/index.js")
+        .exists());
+    let names = read_manifest_names(&config_root.join("bin-expire").join("archive.json"));
+    assert_eq!(
+        names,
+        [
+            "old-tool/target",
+            "old-web/node_modules",
+            "work/old-tool/target"
+        ]
+    );
+    assert!(archive_dir
+        .join("sweep/work/old-tool/target/debug/old-tool")
+        .exists());
+
+    let output = run_cli(&["restore", "old-web/node_modules"], &config_root);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(src.join("old-web/node_modules/left-pad/index.js").exists());
+
+    // Two roots with the same project path would share a name, so nothing is archived.
+    for side in ["a", "b"] {
+        write(root.join(side).join("app/package.json"), 10, true);
+        write(root.join(side).join("app/node_modules/x.js"), 10, true);
+    }
+    let (a, b) = (root.join("a"), root.join("b"));
+    let output = run_cli_with_env(
+        &[
+            "sweep",
+            "--dir",
+            a.to_str().unwrap(),
+            "--dir",
+            b.to_str().unwrap(),
+            "--days",
+            "90",
+            "--archive",
+            "--yes",
+        ],
+        &config_root,
+        &archive_env,
+    );
+    assert!(!output.status.success());
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains("would be archived as 'app/node_modules'")
+    );
+    assert!(a.join("app/node_modules").exists() && b.join("app/node_modules").exists());
+
+    cleanup_dir(&root);
+    cleanup_dir(&config_root);
+}